@@ -1,4 +1,9 @@
-use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use clap::{ArgGroup, Parser, Subcommand};
+
+use crate::output::OutputFormat;
 
 #[derive(Debug, Parser)]
 #[command(author, version, about = "Blog CLI Tool", long_about = None)]
@@ -9,8 +14,49 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub grpc: bool,
 
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub server: Option<String>,
+
+    /// Profile from ~/.config/blog/config.toml, `default_profile` by default
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Keep token in plain text file instead of OS keyring
+    #[arg(long, global = true)]
+    pub insecure_token_file: bool,
+
+    /// Output format, `plain` unless set in profile
+    #[arg(long, global = true)]
+    pub output: Option<OutputFormat>,
+
+    /// Handlebars template rendered for every post (or other item), e.g. '{{id}}\t{{title}}\t{{created_at}}'
+    #[arg(
+        long,
+        global = true,
+        value_name = "TEMPLATE",
+        conflicts_with = "output"
+    )]
+    pub format: Option<String>,
+
+    /// Disable colors, also disabled by NO_COLOR env var
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Timeout for a whole request in seconds, 10 by default
+    #[arg(long, global = true, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+
+    /// Number of retries of failed reads, updates and deletes on network and server errors
+    #[arg(long, global = true, default_value_t = 0)]
+    pub retries: u32,
+
+    /// Show post content as is instead of rendering Markdown
+    #[arg(long, global = true)]
+    pub raw: bool,
+
+    /// Show requests with status and time, -vv adds request and response bodies, -vvv traces everything
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
 }
 
 #[derive(Debug, Subcommand)]
@@ -20,14 +66,16 @@ pub enum Command {
         username: String,
         #[arg(long)]
         email: String,
-        #[arg(long)]
-        password: String,
+        /// Prompted without echo when omitted
+        #[arg(long, env = "BLOG_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
     },
     Login {
         #[arg(long)]
         username: String,
-        #[arg(long)]
-        password: String,
+        /// Prompted without echo when omitted
+        #[arg(long, env = "BLOG_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
     },
     Logout,
     Create {
@@ -35,27 +83,110 @@ pub enum Command {
         title: String,
         #[arg(long)]
         content: String,
+        /// Queue the post locally if server is unreachable, publish it later with `sync`
+        #[arg(long)]
+        offline: bool,
     },
+    /// Publishes posts queued by `create --offline`
+    Sync,
     Get {
         #[arg(long)]
         id: i64,
     },
+    /// Change given fields of a post, others are kept
+    #[command(group(ArgGroup::new("fields").required(true).multiple(true)))]
     Update {
         #[arg(long)]
         id: i64,
-        #[arg(long)]
-        title: String,
-        #[arg(long)]
-        content: String,
+        #[arg(long, group = "fields")]
+        title: Option<String>,
+        #[arg(long, group = "fields")]
+        content: Option<String>,
     },
+    /// Delete a post, several posts by ids or all posts matching filters
+    #[command(group(ArgGroup::new("posts").required(true).multiple(true)))]
     Delete {
-        #[arg(long)]
-        id: i64,
+        #[arg(long, group = "posts", conflicts_with_all = ["ids", "filter", "before"])]
+        id: Option<i64>,
+        /// Comma separated post ids
+        #[arg(long, group = "posts", value_delimiter = ',')]
+        ids: Vec<i64>,
+        /// Only posts matching filter: author=USERNAME or author=me
+        #[arg(long, group = "posts", value_parser = parse_filter)]
+        filter: Vec<PostFilter>,
+        /// Only posts created before the date, e.g. 2023-01-01
+        #[arg(long, group = "posts")]
+        before: Option<NaiveDate>,
+        /// Delete without confirmation
+        #[arg(short, long)]
+        yes: bool,
     },
     List {
+        /// Page size, also used as page size with --all
         #[arg(long)]
         limit: Option<u64>,
-        #[arg(long)]
+        #[arg(long, conflicts_with = "all")]
         offset: Option<u64>,
+        /// Fetch all posts following pagination
+        #[arg(long)]
+        all: bool,
+        /// Write the result to file instead of stdout
+        #[arg(long, requires = "all")]
+        out: Option<PathBuf>,
+    },
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Shows post count and recent activity of the logged in user
+    Stats,
+    /// Prints CLI version and supported server API version
+    Version {
+        /// Query server version and warn if the server API is newer than supported
+        #[arg(long)]
+        check: bool,
     },
 }
+
+impl Command {
+    /// Whether the command acts on behalf of the logged in user, public reads work without token
+    pub fn requires_auth(&self) -> bool {
+        matches!(
+            self,
+            Command::Create { .. }
+                | Command::Sync
+                | Command::Update { .. }
+                | Command::Delete { .. }
+                | Command::Stats
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PostFilter {
+    /// Posts of user with given name, `me` for the logged in user
+    Author(String),
+}
+
+fn parse_filter(value: &str) -> Result<PostFilter, String> {
+    match value.split_once('=') {
+        Some(("author", author)) if !author.is_empty() => {
+            Ok(PostFilter::Author(author.to_string()))
+        }
+        _ => Err(String::from("expected author=USERNAME or author=me")),
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Print path of the config file
+    Path,
+    /// Print the config file
+    Show,
+    /// Set field (server, transport or output) of the selected profile
+    Set { key: String, value: String },
+    /// Remove field of the selected profile
+    Unset { key: String },
+    /// Make profile the default one
+    Use { name: String },
+}
@@ -0,0 +1,159 @@
+use std::{collections::BTreeMap, fs, io::ErrorKind, path::PathBuf};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::CliError, output::OutputFormat};
+
+const DEFAULT_PROFILE: &str = "default";
+
+/// Contents of `~/.config/blog/config.toml`
+///
+/// ```toml
+/// default_profile = "prod"
+///
+/// [profiles.prod]
+/// server = "https://blog.example.com"
+/// transport = "grpc"
+/// output = "table"
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Config {
+    /// Profile used when `--profile` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Named set of defaults, command line options take precedence over them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transport: Option<TransportKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputFormat>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    #[default]
+    Http,
+    Grpc,
+    Auto,
+}
+
+impl Config {
+    /// Directory of config file and tokens, `$XDG_CONFIG_HOME/blog` if the variable is set
+    fn dir() -> Result<PathBuf, CliError> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".config")))
+            .ok_or(CliError::ConfigDirNotFound)?;
+        Ok(config_dir.join("blog"))
+    }
+
+    pub fn path() -> Result<PathBuf, CliError> {
+        Ok(Self::dir()?.join("config.toml"))
+    }
+
+    /// Name of token for given profile and server
+    ///
+    /// Tokens are kept apart so a token issued by one server is never sent to another
+    pub fn token_name(profile: &str, server: &str) -> String {
+        format!("{profile}@{server}")
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '@' | '.' | '-') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+
+    /// Path of token file with given name, creating tokens directory
+    pub fn token_path(name: &str) -> Result<PathBuf, CliError> {
+        let dir = Self::dir()?.join("tokens");
+
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(&dir)?;
+
+        Ok(dir.join(name))
+    }
+
+    /// Path of queue of offline drafts with given name, creating drafts directory
+    pub fn drafts_path(name: &str) -> Result<PathBuf, CliError> {
+        let dir = Self::dir()?.join("drafts");
+        fs::create_dir_all(&dir)?;
+        Ok(dir.join(format!("{name}.json")))
+    }
+
+    /// Loads config file, missing file is an empty config
+    pub fn load() -> Result<Self, CliError> {
+        match fs::read_to_string(Self::path()?) {
+            Ok(content) => Ok(toml::from_str(&content)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), CliError> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Name of profile selected by `--profile` or `default_profile`
+    pub fn profile_name<'a>(&'a self, selected: Option<&'a str>) -> &'a str {
+        selected
+            .or(self.default_profile.as_deref())
+            .unwrap_or(DEFAULT_PROFILE)
+    }
+
+    /// Selected profile, built-in defaults when no profile is configured
+    ///
+    /// Explicitly selected profile must exist
+    pub fn profile(&self, selected: Option<&str>) -> Result<Profile, CliError> {
+        let name = self.profile_name(selected);
+        match self.profiles.get(name) {
+            Some(profile) => Ok(profile.clone()),
+            None if selected.is_none() && self.default_profile.is_none() => Ok(Profile::default()),
+            None => Err(CliError::UnknownProfile(name.to_string())),
+        }
+    }
+}
+
+impl Profile {
+    /// Sets profile field by its name in config file
+    pub fn set(&mut self, key: &str, value: Option<String>) -> Result<(), CliError> {
+        match key {
+            "server" => self.server = value,
+            "transport" => {
+                self.transport = value
+                    .map(|value| TransportKind::from_str(&value, true))
+                    .transpose()
+                    .map_err(CliError::InvalidConfigValue)?
+            }
+            "output" => {
+                self.output = value
+                    .map(|value| OutputFormat::from_str(&value, true))
+                    .transpose()
+                    .map_err(CliError::InvalidConfigValue)?
+            }
+            _ => return Err(CliError::UnknownConfigKey(key.to_string())),
+        }
+        Ok(())
+    }
+}
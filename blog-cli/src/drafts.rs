@@ -0,0 +1,83 @@
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::CliError;
+
+/// Post created with `create --offline` while server was unreachable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Draft {
+    pub title: String,
+    pub content: String,
+    pub queued_at: DateTime<Utc>,
+}
+
+/// What happened to a draft during `sync`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SyncOutcome {
+    Published {
+        id: i64,
+    },
+    /// The post was created by an earlier attempt whose response was lost
+    AlreadyPublished {
+        id: i64,
+    },
+    /// Draft stays in the queue
+    Failed {
+        error: String,
+    },
+    /// Draft stays in the queue, server became unreachable before it was sent
+    Pending,
+}
+
+impl SyncOutcome {
+    pub fn is_failure(&self) -> bool {
+        matches!(self, SyncOutcome::Failed { .. } | SyncOutcome::Pending)
+    }
+}
+
+/// Drafts waiting for `sync` in a JSON file, one queue per profile and server
+pub struct DraftQueue {
+    path: PathBuf,
+}
+
+impl DraftQueue {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Queued drafts in order they were created, missing file is an empty queue
+    pub fn load(&self) -> Result<Vec<Draft>, CliError> {
+        match fs::read(&self.path) {
+            Ok(content) => serde_json::from_slice(&content).map_err(CliError::InvalidDrafts),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Replaces queued drafts, the file is removed when nothing is left
+    pub fn save(&self, drafts: &[Draft]) -> Result<(), CliError> {
+        if drafts.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            };
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(drafts)?)?;
+        Ok(())
+    }
+
+    /// Adds draft to the end of the queue, returns number of queued drafts
+    pub fn push(&self, title: String, content: String) -> Result<usize, CliError> {
+        let mut drafts = self.load()?;
+        drafts.push(Draft {
+            title,
+            content,
+            queued_at: Utc::now(),
+        });
+        self.save(&drafts)?;
+        Ok(drafts.len())
+    }
+}
@@ -7,6 +7,36 @@ pub enum CliError {
     ClientError(#[from] BlogClientError),
     #[error("Token not found. Run register or login command first and repeat request")]
     TokenNotFound,
+    #[error("Unable to find home directory for config file")]
+    ConfigDirNotFound,
     #[error("I/O error: {0}")]
-    IoError(#[from] std::io::Error),
+    Io(#[from] std::io::Error),
+    #[error("Unable to parse config file: {0}")]
+    ConfigParse(#[from] toml::de::Error),
+    #[error("Unable to write config file: {0}")]
+    ConfigSerialize(#[from] toml::ser::Error),
+    #[error("Profile \"{0}\" not found in config file")]
+    UnknownProfile(String),
+    #[error("Unknown config key \"{0}\", expected server, transport or output")]
+    UnknownConfigKey(String),
+    #[error("Invalid config value: {0}")]
+    InvalidConfigValue(String),
+    #[error("Password required, pass --password or set BLOG_PASSWORD env var")]
+    PasswordRequired,
+    #[error("Passwords do not match")]
+    PasswordMismatch,
+    #[error("Confirmation required, pass --yes to run without prompt")]
+    ConfirmationRequired,
+    #[error("Unable to get user name from token, login again")]
+    UnknownUser,
+    #[error("{0} operations failed")]
+    BatchFailed(usize),
+    #[error("Unable to serialize output: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Unable to read offline drafts: {0}")]
+    InvalidDrafts(serde_json::Error),
+    #[error("Invalid --format template: {0}")]
+    InvalidTemplate(#[from] handlebars::TemplateError),
+    #[error("Unable to render --format template: {0}")]
+    TemplateRender(#[from] handlebars::RenderError),
 }
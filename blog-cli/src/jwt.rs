@@ -0,0 +1,30 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+/// Largest `exp` taken as seconds (year 5138), larger values are most likely milliseconds
+const MAX_EXP_SECONDS: i64 = 100_000_000_000;
+
+/// Claims of a token issued by the server, read without signature verification
+#[derive(Debug, Deserialize)]
+pub struct Claims {
+    pub username: String,
+    exp: i64,
+}
+
+impl Claims {
+    /// Decodes payload of JWT, `None` if token has unexpected format
+    pub fn decode(token: &str) -> Option<Self> {
+        let payload = token.split('.').nth(1)?;
+        let payload = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+        serde_json::from_slice(&payload).ok()
+    }
+
+    /// Expiration time, `None` if `exp` is not a valid number of seconds since Unix epoch
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        if self.exp > MAX_EXP_SECONDS {
+            return None;
+        }
+        DateTime::from_timestamp(self.exp, 0)
+    }
+}
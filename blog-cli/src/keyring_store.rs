@@ -0,0 +1,70 @@
+use blog_client::{
+    error::BlogClientError,
+    token_store::{FileTokenStore, TokenStore},
+};
+use keyring::Entry;
+use tracing::{debug, warn};
+
+const KEYRING_SERVICE: &str = "blog-cli";
+
+/// Token kept in OS keyring (Keychain, Credential Manager, Secret Service or kernel keyring)
+///
+/// Falls back to plain text file when keyring is not available, e.g. on a headless server
+pub struct KeyringTokenStore {
+    entry: Option<Entry>,
+    fallback: FileTokenStore,
+}
+
+impl KeyringTokenStore {
+    /// Creates store for keyring entry with given name, `fallback` file is used
+    /// when the keyring can't be accessed
+    pub fn new(name: &str, fallback: FileTokenStore) -> Self {
+        let entry = Entry::new(KEYRING_SERVICE, name)
+            .inspect_err(|e| warn!("OS keyring is not available, token is kept in file: {e}"))
+            .ok();
+        Self { entry, fallback }
+    }
+}
+
+impl TokenStore for KeyringTokenStore {
+    fn load(&self) -> Result<Option<String>, BlogClientError> {
+        let Some(entry) = &self.entry else {
+            return self.fallback.load();
+        };
+
+        match entry.get_password() {
+            Ok(token) => Ok(Some(token)),
+            // Token may have been saved to the file while keyring was unavailable
+            Err(keyring::Error::NoEntry) => self.fallback.load(),
+            Err(e) => {
+                debug!("Unable to read token from OS keyring, using token file: {e}");
+                self.fallback.load()
+            }
+        }
+    }
+
+    fn save(&self, token: &str) -> Result<(), BlogClientError> {
+        let Some(entry) = &self.entry else {
+            return self.fallback.save(token);
+        };
+
+        match entry.set_password(token) {
+            // Plain text copy is no longer needed
+            Ok(()) => self.fallback.clear(),
+            Err(e) => {
+                warn!("Unable to save token to OS keyring, using token file: {e}");
+                self.fallback.save(token)
+            }
+        }
+    }
+
+    fn clear(&self) -> Result<(), BlogClientError> {
+        if let Some(entry) = &self.entry {
+            match entry.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => {}
+                Err(e) => debug!("Unable to remove token from OS keyring: {e}"),
+            }
+        }
+        self.fallback.clear()
+    }
+}
@@ -1,33 +1,128 @@
-use std::{fs, path::Path};
-use tracing::{info, trace, warn};
+use tracing::{trace, warn};
 use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
-use blog_client::{Transport, blog_client::BlogClient, error::BlogClientError};
+use std::{pin::pin, time::Duration};
+
+use chrono::{NaiveDate, NaiveTime, TimeDelta, Utc};
+
+use blog_client::{
+    Transport,
+    blog_client::{BlogClient, Post, PostsCollection, UpdatePostFields},
+    error::BlogClientError,
+    retry::RetryPolicy,
+    token_store::FileTokenStore,
+};
 use clap::Parser;
+use futures_util::TryStreamExt;
+use indicatif::ProgressBar;
 
 use crate::{
-    cli::{Cli, Command},
+    cli::{Cli, Command, ConfigAction, PostFilter},
+    config::{Config, Profile, TransportKind},
+    drafts::{Draft, DraftQueue, SyncOutcome},
     error::CliError,
+    jwt::Claims,
+    keyring_store::KeyringTokenStore,
+    output::{CommandOutput, Rendering, Styling, Template},
+    verbose::TrafficLogger,
 };
 mod cli;
+mod config;
+mod drafts;
 mod error;
+mod jwt;
+mod keyring_store;
+mod output;
+mod prompt;
+mod verbose;
 
 #[tokio::main]
 async fn main() -> Result<(), CliError> {
     let args = Cli::parse();
-    init_logging();
-    let transport = get_transport(args.grpc, &args.server);
+    init_logging(args.verbose);
+    let mut config = Config::load()?;
+
+    let command = match args.command {
+        Command::Config { action } => {
+            let message = handle_config(&mut config, args.profile.as_deref(), action)?;
+            println!("{message}");
+            return Ok(());
+        }
+        Command::Version { check: false } => {
+            println!("{}", version_message());
+            return Ok(());
+        }
+        command => command,
+    };
+
+    let profile = config.profile(args.profile.as_deref())?;
+    let rendering = match &args.format {
+        Some(template) => Rendering::Template(Box::new(Template::parse(template)?)),
+        None => Rendering::Format(args.output.or(profile.output).unwrap_or_default()),
+    };
+    let (kind, server) = resolve_server(args.grpc, args.server, &profile);
+    let token_name = Config::token_name(config.profile_name(args.profile.as_deref()), &server);
+    let token_file = FileTokenStore::new(Config::token_path(&token_name)?);
+    let drafts = DraftQueue::new(Config::drafts_path(&token_name)?);
+
+    let mut builder = BlogClient::builder(get_transport(kind, server)).retry_policy(RetryPolicy {
+        max_attempts: args.retries.saturating_add(1),
+        ..RetryPolicy::default()
+    });
+    if let Some(timeout) = args.timeout {
+        builder = builder.request_timeout(Duration::from_secs(timeout));
+    }
+    if args.verbose > 0 {
+        builder = builder.middleware(TrafficLogger::default());
+    }
+    let client = if args.insecure_token_file {
+        builder.token_store(token_file)
+    } else {
+        builder.token_store(KeyringTokenStore::new(&token_name, token_file))
+    }
+    .build()
+    .await;
+    let styling = Styling::detect(args.no_color, args.raw);
 
-    let client = BlogClient::new(transport).await?;
+    let (client, command) = match (client, command) {
+        (Ok(client), command) => (client, command),
+        // gRPC client connects when created, so unreachable server is detected here
+        (
+            Err(e),
+            Command::Create {
+                title,
+                content,
+                offline: true,
+            },
+        ) if e.is_unreachable() => {
+            let output = queue_draft(&drafts, e, title, content)?;
+            styling.print(&rendering.render(&output, styling)?);
+            return Ok(());
+        }
+        (Err(e), _) => return Err(e.into()),
+    };
 
-    let result = handle_command(client, args.command).await;
+    let result = if command.requires_auth() {
+        check_token_expiry(&client).await
+    } else {
+        Ok(())
+    };
+    let result = match result {
+        Ok(()) => handle_command(&client, command, &rendering, &drafts).await,
+        Err(e) => Err(e),
+    };
 
     match result {
-        Ok(message) => info!("OK: {message}"),
+        Ok(output) => {
+            styling.print(&rendering.render(&output, styling)?);
+            if output.failures() > 0 {
+                return Err(CliError::BatchFailed(output.failures()));
+            }
+        }
         Err(e) => {
             if is_token_invalid(&e) {
                 warn!("Token is invalid, authorization required for next use");
-                delete_token()?;
+                client.clear_token()?;
             }
             return Err(e);
         }
@@ -36,116 +131,457 @@ async fn main() -> Result<(), CliError> {
     Ok(())
 }
 
-async fn handle_command(mut client: BlogClient, command: Command) -> Result<String, CliError> {
+async fn handle_command(
+    client: &BlogClient,
+    command: Command,
+    rendering: &Rendering,
+    drafts: &DraftQueue,
+) -> Result<CommandOutput, CliError> {
     match command {
         cli::Command::Register {
             username,
             email,
             password,
         } => {
+            let password = match password {
+                Some(password) => password,
+                None => prompt::new_password()?,
+            };
             let token = client.register(username, email, password).await?;
-            save_token(token)?;
-            Ok(String::from("User registered succesfully"))
+            client.set_token(token)?;
+            Ok(CommandOutput::Message(String::from(
+                "User registered succesfully",
+            )))
         }
         cli::Command::Login { username, password } => {
+            let password = match password {
+                Some(password) => password,
+                None => prompt::password("Password: ")?,
+            };
             let token = client.login(username, password).await?;
-            save_token(token)?;
-            Ok(String::from("User logged in succesfully"))
+            client.set_token(token)?;
+            Ok(CommandOutput::Message(String::from(
+                "User logged in succesfully",
+            )))
         }
-        cli::Command::Create { title, content } => {
-            let token = load_token()?;
-            client.set_token(token);
-            let post = client.create_post(title, content).await?;
-            Ok(format!("Created post: {post:?}"))
+        cli::Command::Create {
+            title,
+            content,
+            offline,
+        } => {
+            require_token(client)?;
+            match client.create_post(title.clone(), content.clone()).await {
+                Ok(post) => Ok(CommandOutput::Post(post)),
+                Err(e) if offline && e.is_unreachable() => queue_draft(drafts, e, title, content),
+                Err(e) => Err(e.into()),
+            }
         }
+        cli::Command::Sync => sync_drafts(client, drafts).await,
         cli::Command::Get { id } => {
             let post = client.get_post(id).await?;
-            Ok(format!("Got post: {post:?}"))
+            Ok(CommandOutput::Post(post))
         }
         cli::Command::Update { id, title, content } => {
-            let token = load_token()?;
-            client.set_token(token);
-            let post = client.update_post(id, title, content).await?;
-            Ok(format!("Updated post: {post:?}"))
-        }
-        cli::Command::Delete { id } => {
-            let token = load_token()?;
-            client.set_token(token);
+            require_token(client)?;
+            let post = client
+                .update_post_partial(id, UpdatePostFields { title, content })
+                .await?;
+            Ok(CommandOutput::Post(post))
+        }
+        cli::Command::Delete {
+            id: Some(id), yes, ..
+        } => {
+            require_token(client)?;
+            let post = client.get_post(id).await?;
+            if !prompt::confirm(&format!("Delete post #{id} \"{}\"?", post.title), yes)? {
+                return Ok(CommandOutput::Message(String::from("Deletion cancelled")));
+            }
             client.delete_post(id).await?;
-            Ok(format!("Deleted post with id: {id}"))
+            Ok(CommandOutput::Deleted(id))
         }
-        cli::Command::List { limit, offset } => {
+        cli::Command::Delete {
+            ids,
+            filter,
+            before,
+            yes,
+            ..
+        } => delete_posts(client, ids, filter, before, yes).await,
+        cli::Command::List {
+            limit,
+            offset,
+            all: false,
+            ..
+        } => {
             let collection = client.get_posts(limit, offset).await?;
-            Ok(format!(
-                "Posts offset {} from {}, limit {},\n{}",
-                collection.offset,
-                collection.total_posts,
-                collection.limit,
-                collection
-                    .posts
-                    .iter()
-                    .map(|p| format!("* {p:?}"))
-                    .collect::<Vec<_>>()
-                    .join("\n")
-            ))
+            Ok(CommandOutput::Posts(collection))
+        }
+        cli::Command::List {
+            limit,
+            out,
+            all: true,
+            ..
+        } => {
+            let posts = fetch_all_posts(client, limit.unwrap_or(ALL_POSTS_PAGE_SIZE)).await?;
+            let count = posts.len();
+            let output = CommandOutput::Posts(PostsCollection {
+                limit: count as u64,
+                offset: 0,
+                total_posts: count as u64,
+                posts,
+            });
+
+            match out {
+                Some(path) => {
+                    std::fs::write(&path, rendering.render(&output, Styling::PLAIN)?)?;
+                    Ok(CommandOutput::Message(format!(
+                        "Saved {count} posts to {}",
+                        path.display()
+                    )))
+                }
+                None => Ok(output),
+            }
         }
         cli::Command::Logout => {
-            delete_token()?;
-            Ok("User logged out".to_string())
+            client.clear_token()?;
+            Ok(CommandOutput::Message("User logged out".to_string()))
+        }
+        cli::Command::Stats => {
+            require_token(client)?;
+            let stats = client.user_stats().await?;
+            Ok(CommandOutput::UserStats(stats))
+        }
+        cli::Command::Config { .. } => unreachable!("config command is handled without client"),
+        cli::Command::Version { .. } => {
+            let info = client.server_info().await?;
+            if info.api_version > blog_client::API_VERSION {
+                warn!(
+                    "Server API version {} is newer than supported version {}, blog-cli should be updated",
+                    info.api_version,
+                    blog_client::API_VERSION
+                );
+            }
+            let mut message = format!(
+                "{}\nserver {} (API version {})",
+                version_message(),
+                info.version,
+                info.api_version
+            );
+            if !info.features.is_empty() {
+                message.push_str(&format!("\nfeatures: {}", info.features.join(", ")));
+            }
+            if let Some(limits) = info.limits {
+                message.push_str(&format!(
+                    "\nlimits: title {} chars, content {} chars, {} posts per page",
+                    limits.max_title_length, limits.max_content_length, limits.max_page_limit
+                ));
+            }
+            if info.read_only {
+                message.push_str("\nserver is in read-only maintenance mode");
+            }
+            Ok(CommandOutput::Message(message))
         }
     }
 }
 
-const TOKEN_FILE: &str = ".blog_token";
+fn version_message() -> String {
+    format!(
+        "blog-cli {} (API version {})",
+        env!("CARGO_PKG_VERSION"),
+        blog_client::API_VERSION
+    )
+}
 
-fn save_token(token: String) -> Result<(), CliError> {
-    fs::write(TOKEN_FILE, token)?;
+/// Token expiring sooner is treated as expired, so it does not fail in the middle of a command
+const TOKEN_EXPIRY_MARGIN: TimeDelta = TimeDelta::seconds(30);
+
+/// Warns about expired token and removes it, in terminal offers to log in again
+/// as the same user
+async fn check_token_expiry(client: &BlogClient) -> Result<(), CliError> {
+    let Some(claims) = client.get_token().as_deref().and_then(Claims::decode) else {
+        return Ok(());
+    };
+    match claims.expires_at() {
+        Some(expires_at) if expires_at > Utc::now() + TOKEN_EXPIRY_MARGIN => return Ok(()),
+        Some(expires_at) => warn!(
+            "Token of user {} expired at {expires_at}, authorization required",
+            claims.username
+        ),
+        None => warn!(
+            "Token of user {} has invalid expiration time, authorization required",
+            claims.username
+        ),
+    }
+    client.clear_token()?;
+
+    if prompt::is_interactive()
+        && prompt::confirm(&format!("Log in again as {}?", claims.username), false)?
+    {
+        let password = prompt::password("Password: ")?;
+        let token = client.login(claims.username, password).await?;
+        client.set_token(token)?;
+    }
     Ok(())
 }
 
-fn load_token() -> Result<String, CliError> {
-    let path = Path::new(TOKEN_FILE);
+/// Deletes posts with given ids, or all posts if no ids are given, which match all filters
+async fn delete_posts(
+    client: &BlogClient,
+    ids: Vec<i64>,
+    filters: Vec<PostFilter>,
+    before: Option<NaiveDate>,
+    yes: bool,
+) -> Result<CommandOutput, CliError> {
+    require_token(client)?;
+    let me = client
+        .get_token()
+        .as_deref()
+        .and_then(Claims::decode)
+        .map(|claims| claims.username);
+    let authors = filters
+        .into_iter()
+        .map(|PostFilter::Author(author)| match author.as_str() {
+            "me" => me.clone().ok_or(CliError::UnknownUser),
+            _ => Ok(author),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let before = before.map(|date| date.and_time(NaiveTime::MIN).and_utc());
 
-    if path.exists() {
-        let token = fs::read_to_string(path)?.trim().to_string();
+    let posts = if ids.is_empty() {
+        fetch_all_posts(client, ALL_POSTS_PAGE_SIZE).await?
+    } else {
+        let results = client.get_posts_by_ids(ids.clone()).await;
+        ids.into_iter()
+            .zip(results)
+            .filter_map(|(id, result)| {
+                result
+                    .inspect_err(|e| warn!("Post #{id} skipped: {e}"))
+                    .ok()
+            })
+            .collect()
+    };
+    let posts: Vec<Post> = posts
+        .into_iter()
+        .filter(|post| before.is_none_or(|before| post.created_at < before))
+        .filter(|post| {
+            authors
+                .iter()
+                .all(|author| post.author_username.as_ref() == Some(author))
+        })
+        .collect();
+
+    if posts.is_empty() {
+        return Ok(CommandOutput::Message(String::from("No posts to delete")));
+    }
+    for post in &posts {
+        eprintln!("#{} {}", post.id, post.title);
+    }
+    if !prompt::confirm(&format!("Delete {} posts?", posts.len()), yes)? {
+        return Ok(CommandOutput::Message(String::from("Deletion cancelled")));
+    }
+
+    let ids: Vec<i64> = posts.iter().map(|post| post.id).collect();
+    let results = client.delete_posts(ids.clone()).await;
+    Ok(CommandOutput::Deletions(
+        ids.into_iter()
+            .zip(
+                results
+                    .into_iter()
+                    .map(|result| result.map_err(|e| e.to_string())),
+            )
+            .collect(),
+    ))
+}
+
+fn queue_draft(
+    drafts: &DraftQueue,
+    error: BlogClientError,
+    title: String,
+    content: String,
+) -> Result<CommandOutput, CliError> {
+    warn!("Server is unreachable: {error}");
+    let queued = drafts.push(title.clone(), content)?;
+    Ok(CommandOutput::Message(format!(
+        "Post \"{title}\" is saved offline, {queued} drafts wait for `blog-cli sync`"
+    )))
+}
+
+/// Publishes queued drafts in order, failed drafts stay in the queue
+///
+/// Queue is not sent further once the server becomes unreachable
+async fn sync_drafts(client: &BlogClient, drafts: &DraftQueue) -> Result<CommandOutput, CliError> {
+    let queued = drafts.load()?;
+    if queued.is_empty() {
+        return Ok(CommandOutput::Message(String::from("No drafts to publish")));
+    }
+    require_token(client)?;
+    let me = client
+        .get_token()
+        .as_deref()
+        .and_then(Claims::decode)
+        .map(|claims| claims.username);
 
-        if token.is_empty() {
-            Err(CliError::TokenNotFound)
+    let mut results = Vec::new();
+    let mut remaining = Vec::new();
+    let mut unreachable = false;
+    for draft in queued {
+        let outcome = if unreachable {
+            SyncOutcome::Pending
         } else {
-            Ok(token)
+            match publish_draft(client, &draft, me.as_deref()).await {
+                Ok(outcome) => outcome,
+                Err(e) if e.is_unreachable() => {
+                    warn!("Server is unreachable: {e}");
+                    unreachable = true;
+                    SyncOutcome::Pending
+                }
+                Err(e) => SyncOutcome::Failed {
+                    error: e.to_string(),
+                },
+            }
+        };
+
+        if outcome.is_failure() {
+            remaining.push(draft.clone());
         }
-    } else {
-        Err(CliError::TokenNotFound)
+        results.push((draft.title, outcome));
     }
+
+    drafts.save(&remaining)?;
+    Ok(CommandOutput::Synced(results))
 }
 
-fn delete_token() -> Result<(), CliError> {
-    let path = Path::new(TOKEN_FILE);
+/// Creates post from draft unless the same post of the user was created after the draft
+/// was queued, e.g. by an earlier sync which timed out waiting for response
+async fn publish_draft(
+    client: &BlogClient,
+    draft: &Draft,
+    me: Option<&str>,
+) -> Result<SyncOutcome, BlogClientError> {
+    const SEARCH_LIMIT: u64 = 20;
 
-    if path.exists() {
-        fs::remove_file(path)?;
+    // search query must not be empty, such draft is rejected by server when created
+    if !draft.title.trim().is_empty() {
+        let found = client
+            .search_posts(draft.title.clone(), Some(SEARCH_LIMIT), None)
+            .await?;
+        let published = found.posts.iter().find(|post| {
+            post.author_username.as_deref() == me
+                && post.title == draft.title
+                && post.content == draft.content
+                && post.created_at >= draft.queued_at
+        });
+        if let Some(post) = published {
+            return Ok(SyncOutcome::AlreadyPublished { id: post.id });
+        }
     }
 
-    Ok(())
+    let post = client
+        .create_post(draft.title.clone(), draft.content.clone())
+        .await?;
+    Ok(SyncOutcome::Published { id: post.id })
 }
 
-fn get_transport(grpc: bool, server: &Option<String>) -> Transport {
+const ALL_POSTS_PAGE_SIZE: u64 = 100;
+
+/// Fetches all posts page by page showing progress in terminal
+async fn fetch_all_posts(client: &BlogClient, page_size: u64) -> Result<Vec<Post>, CliError> {
+    let progress = ProgressBar::new_spinner();
+    progress.enable_steady_tick(Duration::from_millis(100));
+
+    let mut posts = Vec::new();
+    let mut stream = pin!(client.posts_stream(page_size));
+    while let Some(post) = stream.try_next().await? {
+        posts.push(post);
+        progress.set_message(format!("Fetched {} posts", posts.len()));
+    }
+
+    progress.finish_and_clear();
+    Ok(posts)
+}
+
+fn handle_config(
+    config: &mut Config,
+    profile: Option<&str>,
+    action: ConfigAction,
+) -> Result<String, CliError> {
+    match action {
+        ConfigAction::Path => Ok(Config::path()?.display().to_string()),
+        ConfigAction::Show => Ok(format!(
+            "Config {}:\n{}",
+            Config::path()?.display(),
+            toml::to_string_pretty(config)?
+        )),
+        ConfigAction::Set { key, value } => {
+            let name = config.profile_name(profile).to_string();
+            config
+                .profiles
+                .entry(name.clone())
+                .or_default()
+                .set(&key, Some(value))?;
+            config.save()?;
+            Ok(format!("Set {key} of profile \"{name}\""))
+        }
+        ConfigAction::Unset { key } => {
+            let name = config.profile_name(profile).to_string();
+            config
+                .profiles
+                .entry(name.clone())
+                .or_default()
+                .set(&key, None)?;
+            config.save()?;
+            Ok(format!("Removed {key} of profile \"{name}\""))
+        }
+        ConfigAction::Use { name } => {
+            if !config.profiles.contains_key(&name) {
+                return Err(CliError::UnknownProfile(name));
+            }
+            config.default_profile = Some(name.clone());
+            config.save()?;
+            Ok(format!("Profile \"{name}\" is used by default"))
+        }
+    }
+}
+
+fn require_token(client: &BlogClient) -> Result<(), CliError> {
+    match client.get_token() {
+        Some(_) => Ok(()),
+        None => Err(CliError::TokenNotFound),
+    }
+}
+
+/// Transport and server address, command line options take precedence over selected profile
+fn resolve_server(
+    grpc: bool,
+    server: Option<String>,
+    profile: &Profile,
+) -> (TransportKind, String) {
     const DEFAULT_ADDRESS: &str = "http://127.0.0.1";
     const DEFAULT_HTTP_PORT: &str = "8080";
     const DEFAULT_GRPC_PORT: &str = "50051";
 
-    if grpc {
-        Transport::Grpc(
-            server
-                .clone()
-                .unwrap_or(format!("{DEFAULT_ADDRESS}:{DEFAULT_GRPC_PORT}")),
-        )
+    let kind = if grpc {
+        TransportKind::Grpc
     } else {
-        Transport::Http(
-            server
-                .clone()
-                .unwrap_or(format!("{DEFAULT_ADDRESS}:{DEFAULT_HTTP_PORT}")),
-        )
+        profile.transport.unwrap_or_default()
+    };
+    let server = server
+        .or_else(|| profile.server.clone())
+        .unwrap_or_else(|| {
+            let port = match kind {
+                TransportKind::Grpc => DEFAULT_GRPC_PORT,
+                TransportKind::Http | TransportKind::Auto => DEFAULT_HTTP_PORT,
+            };
+            format!("{DEFAULT_ADDRESS}:{port}")
+        });
+
+    (kind, server)
+}
+
+fn get_transport(kind: TransportKind, server: String) -> Transport {
+    match kind {
+        TransportKind::Http => Transport::Http(server),
+        TransportKind::Grpc => Transport::Grpc(server),
+        TransportKind::Auto => Transport::Auto(server),
     }
 }
 
@@ -157,11 +593,17 @@ fn is_token_invalid(error: &CliError) -> bool {
     )
 }
 
-pub fn init_logging() {
+pub fn init_logging(verbose: u8) {
+    let filter = match verbose::log_filter(verbose) {
+        Some(filter) => EnvFilter::new(filter),
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
+    };
+
     tracing_subscriber::registry()
-        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
+        .with(filter)
         .with(
             tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
                 .with_target(false)
                 .with_timer(tracing_subscriber::fmt::time::ChronoUtc::rfc_3339()),
         )
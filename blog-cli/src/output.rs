@@ -0,0 +1,454 @@
+use std::{
+    io::{IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+use anstyle::{AnsiColor, Style};
+use blog_client::blog_client::{Post, PostsCollection, UserStats};
+use clap::ValueEnum;
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use termimad::MadSkin;
+
+use crate::{drafts::SyncOutcome, error::CliError};
+
+const TITLE_WIDTH: usize = 30;
+const AUTHOR_WIDTH: usize = 16;
+const CONTENT_WIDTH: usize = 40;
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+const ID_STYLE: Style = Style::new().bold();
+const TITLE_STYLE: Style = AnsiColor::Cyan.on_default().bold();
+const META_STYLE: Style = Style::new().dimmed();
+
+const DEFAULT_PAGER: &str = "less -R";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human readable text
+    #[default]
+    Plain,
+    /// JSON for scripts
+    Json,
+    /// Posts in aligned columns with long values truncated
+    Table,
+}
+
+/// How command output is printed, either in one of the formats or with user template
+pub enum Rendering {
+    Format(OutputFormat),
+    Template(Box<Template>),
+}
+
+impl Rendering {
+    pub fn render(&self, output: &CommandOutput, styling: Styling) -> Result<String, CliError> {
+        match self {
+            Rendering::Format(format) => output.render(*format, styling),
+            Rendering::Template(template) => template.render(output),
+        }
+    }
+}
+
+/// Handlebars template from `--format`, values are inserted as is without HTML escaping
+pub struct Template {
+    registry: Handlebars<'static>,
+}
+
+impl Template {
+    const NAME: &str = "format";
+
+    /// Compiles template, `\t` and `\n` sequences are replaced with tab and line break
+    pub fn parse(template: &str) -> Result<Self, CliError> {
+        let template = template.replace("\\t", "\t").replace("\\n", "\n");
+
+        let mut registry = Handlebars::new();
+        registry.register_escape_fn(handlebars::no_escape);
+        // typos in field names are reported instead of printing empty values
+        registry.set_strict_mode(true);
+        registry.register_template_string(Self::NAME, template)?;
+
+        Ok(Self { registry })
+    }
+
+    /// Renders template for every item of output with fields of its JSON representation,
+    /// one line per item
+    pub fn render(&self, output: &CommandOutput) -> Result<String, CliError> {
+        let lines = output
+            .items()?
+            .iter()
+            .map(|item| self.registry.render(Self::NAME, item))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Colors, wrapping and Markdown rendering of plain output, used only when stdout is a terminal
+#[derive(Debug, Clone, Copy)]
+pub struct Styling {
+    color: bool,
+    /// Render post content as Markdown
+    markdown: bool,
+    /// Width to wrap post content to
+    width: Option<usize>,
+}
+
+impl Styling {
+    /// No colors and no wrapping, e.g. for output written to file
+    pub const PLAIN: Self = Self {
+        color: false,
+        markdown: false,
+        width: None,
+    };
+
+    /// Colors are disabled by `--no-color` flag or non-empty `NO_COLOR` env var,
+    /// Markdown rendering by `--raw` flag
+    pub fn detect(no_color: bool, raw: bool) -> Self {
+        let terminal = std::io::stdout().is_terminal();
+        let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Self {
+            color: terminal && !no_color && !no_color_env,
+            markdown: terminal && !raw,
+            width: terminal.then(textwrap::termwidth),
+        }
+    }
+
+    /// Prints output, in terminal output taller than the window is shown
+    /// with `$PAGER` (`less -R` by default)
+    pub fn print(&self, text: &str) {
+        let (_, height) = termimad::terminal_size();
+        if self.width.is_some() && text.lines().count() >= usize::from(height) && page(text) {
+            return;
+        }
+        println!("{text}");
+    }
+
+    fn paint(&self, style: Style, text: impl std::fmt::Display) -> String {
+        if self.color {
+            format!("{style}{text}{style:#}")
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn wrap(&self, text: &str) -> String {
+        match self.width {
+            Some(width) => textwrap::fill(text, width),
+            None => text.to_string(),
+        }
+    }
+
+    /// Post content rendered as Markdown or wrapped as is
+    fn content(&self, text: &str) -> String {
+        if !self.markdown {
+            return self.wrap(text);
+        }
+
+        let skin = if self.color {
+            MadSkin::default()
+        } else {
+            MadSkin::no_style()
+        };
+        skin.text(text, self.width)
+            .to_string()
+            .trim_end()
+            .to_string()
+    }
+}
+
+/// Shows text with pager, `false` if pager can't be started
+fn page(text: &str) -> bool {
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| String::from(DEFAULT_PAGER));
+    let mut args = pager.split_whitespace();
+    let Some(program) = args.next() else {
+        return false;
+    };
+    let Ok(mut child) = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+    else {
+        return false;
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // Fails when pager is closed before reading everything
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    let _ = child.wait();
+    true
+}
+
+/// Result of a command printed to stdout
+pub enum CommandOutput {
+    Message(String),
+    Post(Post),
+    Posts(PostsCollection),
+    Deleted(i64),
+    /// Result of deletion for every post id
+    Deletions(Vec<(i64, Result<(), String>)>),
+    UserStats(UserStats),
+    /// Result of `sync` for every queued draft title
+    Synced(Vec<(String, SyncOutcome)>),
+}
+
+impl CommandOutput {
+    /// Number of failed items of batch operation
+    pub fn failures(&self) -> usize {
+        match self {
+            CommandOutput::Deletions(results) => {
+                results.iter().filter(|(_, result)| result.is_err()).count()
+            }
+            CommandOutput::Synced(results) => results
+                .iter()
+                .filter(|(_, outcome)| outcome.is_failure())
+                .count(),
+            _ => 0,
+        }
+    }
+
+    pub fn render(&self, format: OutputFormat, styling: Styling) -> Result<String, CliError> {
+        match format {
+            OutputFormat::Plain => Ok(self.plain(styling)),
+            OutputFormat::Json => self.json(),
+            OutputFormat::Table => Ok(self.table(styling)),
+        }
+    }
+
+    fn plain(&self, styling: Styling) -> String {
+        match self {
+            CommandOutput::Message(message) => message.clone(),
+            CommandOutput::Post(post) => format!(
+                "{} {}\n{}\n\n{}",
+                styling.paint(ID_STYLE, format_args!("#{}", post.id)),
+                styling.paint(TITLE_STYLE, &post.title),
+                styling.paint(
+                    META_STYLE,
+                    format_args!(
+                        "by {}, created {}, updated {}",
+                        author(post),
+                        post.created_at.format(DATE_FORMAT),
+                        post.updated_at.format(DATE_FORMAT)
+                    )
+                ),
+                styling.content(&post.content)
+            ),
+            CommandOutput::Posts(collection) if collection.posts.is_empty() => {
+                format!("No posts, {} in total", collection.total_posts)
+            }
+            CommandOutput::Posts(collection) => {
+                let mut lines = vec![format!(
+                    "Posts {}-{} of {}",
+                    collection.offset + 1,
+                    collection.offset + collection.posts.len() as u64,
+                    collection.total_posts
+                )];
+                lines.extend(collection.posts.iter().map(|post| {
+                    format!(
+                        "{} {} {}",
+                        styling.paint(ID_STYLE, format_args!("#{}", post.id)),
+                        styling.paint(TITLE_STYLE, &post.title),
+                        styling.paint(
+                            META_STYLE,
+                            format_args!(
+                                "(by {}, {})",
+                                author(post),
+                                post.created_at.format(DATE_FORMAT)
+                            )
+                        )
+                    )
+                }));
+                lines.join("\n")
+            }
+            CommandOutput::Deleted(id) => format!("Deleted post with id: {id}"),
+            CommandOutput::Deletions(results) => {
+                let mut lines: Vec<String> = results
+                    .iter()
+                    .map(|(id, result)| match result {
+                        Ok(()) => format!("Deleted post #{id}"),
+                        Err(e) => format!("Failed to delete post #{id}: {e}"),
+                    })
+                    .collect();
+                lines.push(format!(
+                    "Deleted {} of {} posts",
+                    results.len() - self.failures(),
+                    results.len()
+                ));
+                lines.join("\n")
+            }
+            CommandOutput::UserStats(stats) => {
+                let last_post = match stats.last_post_at {
+                    Some(time) => format!(", last post {}", time.format(DATE_FORMAT)),
+                    None => String::new(),
+                };
+                let active_days: Vec<_> = stats
+                    .posts_per_day
+                    .iter()
+                    .filter(|daily| daily.posts > 0)
+                    .collect();
+
+                let mut lines = vec![
+                    format!(
+                        "{} {}",
+                        styling.paint(TITLE_STYLE, &stats.username),
+                        styling.paint(META_STYLE, format_args!("(user {})", stats.user_id))
+                    ),
+                    format!("{} posts{last_post}", stats.posts),
+                    format!(
+                        "Last {} days: {} posts",
+                        stats.posts_per_day.len(),
+                        active_days.iter().map(|daily| daily.posts).sum::<u64>()
+                    ),
+                ];
+                lines.extend(
+                    active_days
+                        .iter()
+                        .map(|daily| format!("  {}  {}", daily.day, daily.posts)),
+                );
+                lines.join("\n")
+            }
+            CommandOutput::Synced(results) => {
+                let mut lines: Vec<String> = results
+                    .iter()
+                    .map(|(title, outcome)| match outcome {
+                        SyncOutcome::Published { id } => {
+                            format!("Published draft \"{title}\" as post #{id}")
+                        }
+                        SyncOutcome::AlreadyPublished { id } => {
+                            format!("Draft \"{title}\" was already published as post #{id}")
+                        }
+                        SyncOutcome::Failed { error } => {
+                            format!("Failed to publish draft \"{title}\": {error}")
+                        }
+                        SyncOutcome::Pending => {
+                            format!("Draft \"{title}\" is left in queue, server is unreachable")
+                        }
+                    })
+                    .collect();
+                lines.push(format!(
+                    "Published {} of {} drafts",
+                    results.len() - self.failures(),
+                    results.len()
+                ));
+                lines.join("\n")
+            }
+        }
+    }
+
+    fn json(&self) -> Result<String, CliError> {
+        Ok(serde_json::to_string(&self.json_value()?)?)
+    }
+
+    fn json_value(&self) -> Result<Value, serde_json::Error> {
+        match self {
+            CommandOutput::Message(message) => Ok(json!({ "message": message })),
+            CommandOutput::Post(post) => serde_json::to_value(post),
+            CommandOutput::Posts(collection) => serde_json::to_value(collection),
+            CommandOutput::Deleted(id) => Ok(json!({ "deleted": id })),
+            CommandOutput::Deletions(results) => Ok(results
+                .iter()
+                .map(|(id, result)| match result {
+                    Ok(()) => json!({ "id": id, "deleted": true }),
+                    Err(e) => json!({ "id": id, "deleted": false, "error": e }),
+                })
+                .collect()),
+            CommandOutput::UserStats(stats) => serde_json::to_value(stats),
+            CommandOutput::Synced(results) => results
+                .iter()
+                .map(|(title, outcome)| {
+                    let mut value = serde_json::to_value(outcome)?;
+                    value["title"] = json!(title);
+                    Ok(value)
+                })
+                .collect(),
+        }
+    }
+
+    /// JSON values rendered by `--format` template: posts of a list,
+    /// results of a batch operation, or the whole output otherwise
+    fn items(&self) -> Result<Vec<Value>, serde_json::Error> {
+        match self {
+            CommandOutput::Posts(collection) => {
+                collection.posts.iter().map(serde_json::to_value).collect()
+            }
+            _ => match self.json_value()? {
+                Value::Array(items) => Ok(items),
+                value => Ok(vec![value]),
+            },
+        }
+    }
+
+    fn table(&self, styling: Styling) -> String {
+        match self {
+            CommandOutput::Post(post) => posts_table(std::slice::from_ref(post)),
+            CommandOutput::Posts(collection) => posts_table(&collection.posts),
+            CommandOutput::Message(_)
+            | CommandOutput::Deleted(_)
+            | CommandOutput::Deletions(_)
+            | CommandOutput::UserStats(_)
+            | CommandOutput::Synced(_) => self.plain(styling),
+        }
+    }
+}
+
+fn author(post: &Post) -> String {
+    post.author_username
+        .clone()
+        .unwrap_or_else(|| format!("user {}", post.author_id))
+}
+
+fn posts_table(posts: &[Post]) -> String {
+    let header = [
+        String::from("ID"),
+        String::from("TITLE"),
+        String::from("AUTHOR"),
+        String::from("CREATED"),
+        String::from("CONTENT"),
+    ];
+    let rows: Vec<[String; 5]> = posts
+        .iter()
+        .map(|post| {
+            [
+                post.id.to_string(),
+                truncate(&post.title, TITLE_WIDTH),
+                truncate(&author(post), AUTHOR_WIDTH),
+                post.created_at.format(DATE_FORMAT).to_string(),
+                truncate(&post.content, CONTENT_WIDTH),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.each_ref().map(|cell| cell.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    std::iter::once(&header)
+        .chain(&rows)
+        .map(|row| {
+            row.iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Shortens value to `width` characters, line breaks are replaced by spaces
+fn truncate(value: &str, width: usize) -> String {
+    let value = value.replace(['\n', '\r'], " ");
+    if value.chars().count() <= width {
+        value
+    } else {
+        let mut truncated: String = value.chars().take(width - 1).collect();
+        truncated.push('…');
+        truncated
+    }
+}
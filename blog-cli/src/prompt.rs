@@ -0,0 +1,46 @@
+use std::io::{BufRead, IsTerminal, Write};
+
+use crate::error::CliError;
+
+/// Asks user to confirm destructive action, `yes` skips the question
+///
+/// Without a terminal the action is refused unless `yes` is set,
+/// so scripts never hang waiting for an answer
+pub fn confirm(question: &str, yes: bool) -> Result<bool, CliError> {
+    if yes {
+        return Ok(true);
+    }
+    if !is_interactive() {
+        return Err(CliError::ConfirmationRequired);
+    }
+
+    let mut stderr = std::io::stderr();
+    write!(stderr, "{question} [y/N] ")?;
+    stderr.flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Reads password from terminal without echoing it
+pub fn password(prompt: &str) -> Result<String, CliError> {
+    if !is_interactive() {
+        return Err(CliError::PasswordRequired);
+    }
+    Ok(rpassword::prompt_password(prompt)?)
+}
+
+/// Reads new password twice to catch typos
+pub fn new_password() -> Result<String, CliError> {
+    let password = password("Password: ")?;
+    if password != self::password("Repeat password: ")? {
+        return Err(CliError::PasswordMismatch);
+    }
+    Ok(password)
+}
+
+/// Whether user can answer prompts
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal()
+}
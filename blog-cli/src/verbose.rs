@@ -0,0 +1,61 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+    time::Instant,
+};
+
+use blog_client::{
+    error::BlogClientError,
+    middleware::{ClientMiddleware, RequestParts, ResponseParts, ResponseStatus},
+};
+use tracing::debug;
+
+/// Logs requests with response status and time, registered by `-v`
+///
+/// Headers are not logged, so tokens never reach the output
+#[derive(Default)]
+pub struct TrafficLogger {
+    /// Start of the last request of each operation
+    started: Mutex<HashMap<&'static str, Instant>>,
+}
+
+impl ClientMiddleware for TrafficLogger {
+    fn on_request(&self, request: &mut RequestParts<'_>) -> Result<(), BlogClientError> {
+        match request.target {
+            Some((method, url)) => debug!("--> {method} {url} ({})", request.operation),
+            None => debug!("--> gRPC {}", request.operation),
+        }
+
+        self.started
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(request.operation, Instant::now());
+        Ok(())
+    }
+
+    fn on_response(&self, response: &ResponseParts<'_>) {
+        let elapsed = self
+            .started
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(response.operation)
+            .map(|started| started.elapsed())
+            .unwrap_or_default();
+
+        let status = match response.status {
+            ResponseStatus::Http(code) => format!("HTTP {code}"),
+            ResponseStatus::Grpc(code) => format!("gRPC {code:?}"),
+        };
+        debug!("<-- {status} ({}) in {elapsed:.1?}", response.operation);
+    }
+}
+
+/// Log filter for `-v` count, `RUST_LOG` is used when no `-v` is given
+pub fn log_filter(verbose: u8) -> Option<&'static str> {
+    match verbose {
+        0 => None,
+        1 => Some("info,blog_cli::verbose=debug"),
+        2 => Some("info,blog_cli=debug,blog_client=debug"),
+        _ => Some("trace"),
+    }
+}
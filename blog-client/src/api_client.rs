@@ -1,16 +1,44 @@
+//! Transport-level interface of the blog client
+//!
+//! Implement [`BlogApiClient`] to plug a custom transport (mock, record/replay layer,
+//! in-process test server) into [`crate::blog_client::BlogClient`] through [`crate::Transport::Custom`]
+
 use enum_dispatch::enum_dispatch;
+#[cfg(not(target_arch = "wasm32"))]
+use futures_util::stream::BoxStream;
+#[cfg(target_arch = "wasm32")]
+use futures_util::stream::LocalBoxStream;
+
+/// Re-exported macro required to implement [`BlogApiClient`]
+pub use async_trait::async_trait;
 
+#[cfg(feature = "grpc")]
+use crate::{auto_client::AutoClient, grpc_client::GrpcClient};
 use crate::{
-    blog_client::{Post, PostsCollection},
+    blog_client::{Post, PostEvent, PostsCollection, ServerInfo, UpdatePostFields, UserStats},
     error::BlogClientError,
-    grpc_client::GrpcClient,
     http_client::HttpClient,
 };
 
+/// Stream of post changes returned by [`BlogApiClient::subscribe_posts`]
+#[cfg(not(target_arch = "wasm32"))]
+pub type PostEventStream = BoxStream<'static, Result<PostEvent, BlogClientError>>;
+/// Stream of post changes returned by [`BlogApiClient::subscribe_posts`]
+#[cfg(target_arch = "wasm32")]
+pub type PostEventStream = LocalBoxStream<'static, Result<PostEvent, BlogClientError>>;
+
 /// Trait for blog client interface
-#[async_trait::async_trait]
+///
+/// Methods receive already validated arguments, token-protected methods get JWT token
+/// stored in [`crate::blog_client::BlogClient`]
+///
+/// On `wasm32` targets futures are not `Send`, implementations there
+/// use `#[async_trait(?Send)]`
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
 #[enum_dispatch(ClientType)]
-pub(crate) trait BlogApiClient {
+pub trait BlogApiClient {
+    /// Registers a new user and returns JWT token
     async fn register(
         &self,
         username: String,
@@ -18,8 +46,10 @@ pub(crate) trait BlogApiClient {
         password: String,
     ) -> Result<String, BlogClientError>;
 
+    /// Logs in existing user and returns JWT token
     async fn login(&self, username: String, password: String) -> Result<String, BlogClientError>;
 
+    /// Creates a new post on behalf of token owner
     async fn create_post(
         &self,
         token: &str,
@@ -27,8 +57,10 @@ pub(crate) trait BlogApiClient {
         content: String,
     ) -> Result<Post, BlogClientError>;
 
+    /// Gets a post by id
     async fn get_post(&self, id: i64) -> Result<Post, BlogClientError>;
 
+    /// Updates the post with given id
     async fn update_post(
         &self,
         token: &str,
@@ -37,17 +69,171 @@ pub(crate) trait BlogApiClient {
         content: String,
     ) -> Result<Post, BlogClientError>;
 
+    /// Updates only the fields of the post with given id which are set in `fields`
+    async fn patch_post(
+        &self,
+        token: &str,
+        id: i64,
+        fields: UpdatePostFields,
+    ) -> Result<Post, BlogClientError>;
+
+    /// Deletes the post with given id
     async fn delete_post(&self, token: &str, id: i64) -> Result<(), BlogClientError>;
 
+    /// Gets a page of posts
     async fn get_posts(
         &self,
         limit: Option<u64>,
         offset: Option<u64>,
     ) -> Result<PostsCollection, BlogClientError>;
+
+    /// Gets a page of posts matching the query, most relevant first
+    async fn search_posts(
+        &self,
+        query: String,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<PostsCollection, BlogClientError>;
+
+    /// Subscribes to changes of posts made after the call
+    async fn subscribe_posts(&self) -> Result<PostEventStream, BlogClientError>;
+
+    /// Gets server version and API version
+    async fn server_info(&self) -> Result<ServerInfo, BlogClientError>;
+
+    /// Gets activity of the user owning the token
+    async fn user_stats(&self, token: &str) -> Result<UserStats, BlogClientError>;
+
+    /// Gets several posts in one request, returning result for every id in the same order
+    ///
+    /// Not supported by default, then [`crate::blog_client::BlogClient`] requests posts one by one
+    async fn get_posts_by_ids(
+        &self,
+        _ids: &[i64],
+    ) -> Result<Vec<Result<Post, BlogClientError>>, BlogClientError> {
+        Err(BlogClientError::Unsupported("get_posts_by_ids"))
+    }
+
+    /// Deletes several posts in one request, returning result for every id in the same order
+    ///
+    /// Not supported by default, then [`crate::blog_client::BlogClient`] deletes posts one by one
+    async fn delete_posts(
+        &self,
+        _token: &str,
+        _ids: &[i64],
+    ) -> Result<Vec<Result<(), BlogClientError>>, BlogClientError> {
+        Err(BlogClientError::Unsupported("delete_posts"))
+    }
+}
+
+/// Boxed user-provided transport
+pub type CustomClient = Box<dyn BlogApiClient + Send + Sync>;
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl BlogApiClient for CustomClient {
+    async fn register(
+        &self,
+        username: String,
+        email: String,
+        password: String,
+    ) -> Result<String, BlogClientError> {
+        (**self).register(username, email, password).await
+    }
+
+    async fn login(&self, username: String, password: String) -> Result<String, BlogClientError> {
+        (**self).login(username, password).await
+    }
+
+    async fn create_post(
+        &self,
+        token: &str,
+        title: String,
+        content: String,
+    ) -> Result<Post, BlogClientError> {
+        (**self).create_post(token, title, content).await
+    }
+
+    async fn get_post(&self, id: i64) -> Result<Post, BlogClientError> {
+        (**self).get_post(id).await
+    }
+
+    async fn update_post(
+        &self,
+        token: &str,
+        id: i64,
+        title: String,
+        content: String,
+    ) -> Result<Post, BlogClientError> {
+        (**self).update_post(token, id, title, content).await
+    }
+
+    async fn patch_post(
+        &self,
+        token: &str,
+        id: i64,
+        fields: UpdatePostFields,
+    ) -> Result<Post, BlogClientError> {
+        (**self).patch_post(token, id, fields).await
+    }
+
+    async fn delete_post(&self, token: &str, id: i64) -> Result<(), BlogClientError> {
+        (**self).delete_post(token, id).await
+    }
+
+    async fn get_posts(
+        &self,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<PostsCollection, BlogClientError> {
+        (**self).get_posts(limit, offset).await
+    }
+
+    async fn search_posts(
+        &self,
+        query: String,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<PostsCollection, BlogClientError> {
+        (**self).search_posts(query, limit, offset).await
+    }
+
+    async fn subscribe_posts(&self) -> Result<PostEventStream, BlogClientError> {
+        (**self).subscribe_posts().await
+    }
+
+    async fn server_info(&self) -> Result<ServerInfo, BlogClientError> {
+        (**self).server_info().await
+    }
+
+    async fn user_stats(&self, token: &str) -> Result<UserStats, BlogClientError> {
+        (**self).user_stats(token).await
+    }
+
+    async fn get_posts_by_ids(
+        &self,
+        ids: &[i64],
+    ) -> Result<Vec<Result<Post, BlogClientError>>, BlogClientError> {
+        (**self).get_posts_by_ids(ids).await
+    }
+
+    async fn delete_posts(
+        &self,
+        token: &str,
+        ids: &[i64],
+    ) -> Result<Vec<Result<(), BlogClientError>>, BlogClientError> {
+        (**self).delete_posts(token, ids).await
+    }
 }
 
+// BlogClient holds a single instance, so variant sizes do not matter
+#[allow(clippy::large_enum_variant)]
 #[enum_dispatch]
 pub(crate) enum ClientType {
     HttpClient,
+    #[cfg(feature = "grpc")]
     GrpcClient,
+    #[cfg(feature = "grpc")]
+    AutoClient,
+    Custom(CustomClient),
 }
@@ -0,0 +1,194 @@
+//! Client choosing between gRPC and HTTP at runtime
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use futures_util::future::BoxFuture;
+
+use crate::{
+    api_client::{BlogApiClient, PostEventStream},
+    blog_client::{Post, PostsCollection, ServerInfo, UpdatePostFields, UserStats},
+    builder::ConnectionOptions,
+    error::BlogClientError,
+    grpc_client::GrpcClient,
+    http_client::HttpClient,
+};
+
+type DynClient = dyn BlogApiClient + Send + Sync;
+
+/// Uses gRPC if server answers it on the given address, HTTP otherwise,
+/// switching to the other protocol when the active one becomes unreachable
+pub(crate) struct AutoClient {
+    grpc: Option<GrpcClient>,
+    http: HttpClient,
+    use_http: AtomicBool,
+}
+
+impl AutoClient {
+    pub(crate) async fn new(
+        url: String,
+        options: &ConnectionOptions,
+    ) -> Result<Self, BlogClientError> {
+        let http = HttpClient::new(&url, options)?;
+
+        let grpc = match GrpcClient::new(url, options).await {
+            Ok(grpc) if grpc.get_posts(Some(0), Some(0)).await.is_ok() => Some(grpc),
+            _ => None,
+        };
+
+        Ok(Self {
+            use_http: AtomicBool::new(grpc.is_none()),
+            grpc,
+            http,
+        })
+    }
+
+    /// Calls active client, on transport failure repeats the call with the other one
+    /// and keeps using it for next calls
+    async fn call<'a, T>(
+        &'a self,
+        request: impl Fn(&'a DynClient) -> BoxFuture<'a, Result<T, BlogClientError>>,
+    ) -> Result<T, BlogClientError> {
+        self.call_with_fallback(request, true).await
+    }
+
+    /// Like [`AutoClient::call`] for requests which must not be processed twice, e.g. creation:
+    /// the call is repeated only if it failed before being sent, after other transport
+    /// failures the error is returned and the other client is used for next calls
+    async fn call_once<'a, T>(
+        &'a self,
+        request: impl Fn(&'a DynClient) -> BoxFuture<'a, Result<T, BlogClientError>>,
+    ) -> Result<T, BlogClientError> {
+        self.call_with_fallback(request, false).await
+    }
+
+    async fn call_with_fallback<'a, T>(
+        &'a self,
+        request: impl Fn(&'a DynClient) -> BoxFuture<'a, Result<T, BlogClientError>>,
+        idempotent: bool,
+    ) -> Result<T, BlogClientError> {
+        let use_http = self.use_http.load(Ordering::Relaxed);
+
+        let result = request(self.client(use_http)).await;
+
+        match result {
+            Err(e) if e.is_transport_failure() && (use_http || self.grpc.is_some()) => {
+                self.use_http.store(!use_http, Ordering::Relaxed);
+                if !idempotent && !e.is_connect_failure() {
+                    return Err(e);
+                }
+                request(self.client(!use_http)).await
+            }
+            result => result,
+        }
+    }
+
+    fn client(&self, use_http: bool) -> &DynClient {
+        match (&self.grpc, use_http) {
+            (Some(grpc), false) => grpc,
+            _ => &self.http,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BlogApiClient for AutoClient {
+    async fn register(
+        &self,
+        username: String,
+        email: String,
+        password: String,
+    ) -> Result<String, BlogClientError> {
+        self.call_once(|client| client.register(username.clone(), email.clone(), password.clone()))
+            .await
+    }
+
+    async fn login(&self, username: String, password: String) -> Result<String, BlogClientError> {
+        self.call_once(|client| client.login(username.clone(), password.clone()))
+            .await
+    }
+
+    async fn create_post(
+        &self,
+        token: &str,
+        title: String,
+        content: String,
+    ) -> Result<Post, BlogClientError> {
+        self.call_once(|client| client.create_post(token, title.clone(), content.clone()))
+            .await
+    }
+
+    async fn get_post(&self, id: i64) -> Result<Post, BlogClientError> {
+        self.call(|client| client.get_post(id)).await
+    }
+
+    async fn update_post(
+        &self,
+        token: &str,
+        id: i64,
+        title: String,
+        content: String,
+    ) -> Result<Post, BlogClientError> {
+        self.call(|client| client.update_post(token, id, title.clone(), content.clone()))
+            .await
+    }
+
+    async fn patch_post(
+        &self,
+        token: &str,
+        id: i64,
+        fields: UpdatePostFields,
+    ) -> Result<Post, BlogClientError> {
+        self.call(|client| client.patch_post(token, id, fields.clone()))
+            .await
+    }
+
+    async fn delete_post(&self, token: &str, id: i64) -> Result<(), BlogClientError> {
+        self.call(|client| client.delete_post(token, id)).await
+    }
+
+    async fn get_posts(
+        &self,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<PostsCollection, BlogClientError> {
+        self.call(|client| client.get_posts(limit, offset)).await
+    }
+
+    async fn search_posts(
+        &self,
+        query: String,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<PostsCollection, BlogClientError> {
+        self.call(|client| client.search_posts(query.clone(), limit, offset))
+            .await
+    }
+
+    async fn subscribe_posts(&self) -> Result<PostEventStream, BlogClientError> {
+        self.call(|client| client.subscribe_posts()).await
+    }
+
+    async fn server_info(&self) -> Result<ServerInfo, BlogClientError> {
+        self.call(|client| client.server_info()).await
+    }
+
+    async fn user_stats(&self, token: &str) -> Result<UserStats, BlogClientError> {
+        self.call(|client| client.user_stats(token)).await
+    }
+
+    async fn get_posts_by_ids(
+        &self,
+        ids: &[i64],
+    ) -> Result<Vec<Result<Post, BlogClientError>>, BlogClientError> {
+        self.call(|client| client.get_posts_by_ids(ids)).await
+    }
+
+    async fn delete_posts(
+        &self,
+        token: &str,
+        ids: &[i64],
+    ) -> Result<Vec<Result<(), BlogClientError>>, BlogClientError> {
+        self.call_once(|client| client.delete_posts(token, ids))
+            .await
+    }
+}
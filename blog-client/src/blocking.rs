@@ -0,0 +1,166 @@
+//! Blocking wrapper around [`crate::blog_client::BlogClient`]
+//!
+//! Every call is executed on an internal single-threaded tokio runtime,
+//! so the client can be used from regular synchronous code
+
+use futures_util::StreamExt;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{
+    Transport,
+    blog_client::{
+        self, Post, PostEvent, PostsCollection, ServerInfo, UpdatePostFields, UserStats,
+    },
+    builder::BlogClientBuilder,
+    error::BlogClientError,
+};
+
+/// Synchronous client for blog backend interaction
+///
+/// Must not be used from within an async runtime. Like the async client it is `Send + Sync`
+/// and can be shared between threads
+pub struct BlogClient {
+    inner: blog_client::BlogClient,
+    runtime: Runtime,
+}
+
+impl BlogClient {
+    /// Creates client with default options, see [`blog_client::BlogClient::new`]
+    pub fn new(transport: Transport) -> Result<Self, BlogClientError> {
+        Self::from_builder(blog_client::BlogClient::builder(transport))
+    }
+
+    /// Creates client from configured builder
+    pub fn from_builder(builder: BlogClientBuilder) -> Result<Self, BlogClientError> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let inner = runtime.block_on(builder.build())?;
+
+        Ok(Self { inner, runtime })
+    }
+
+    /// Sets JWT token, see [`blog_client::BlogClient::set_token`]
+    pub fn set_token(&self, token: String) -> Result<(), BlogClientError> {
+        self.inner.set_token(token)
+    }
+
+    /// Removes stored JWT token
+    pub fn clear_token(&self) -> Result<(), BlogClientError> {
+        self.inner.clear_token()
+    }
+
+    /// Returns stored JWT token if it is set
+    pub fn get_token(&self) -> Option<String> {
+        self.inner.get_token()
+    }
+
+    /// Registers a new user, see [`blog_client::BlogClient::register`]
+    pub fn register(
+        &self,
+        username: String,
+        email: String,
+        password: String,
+    ) -> Result<String, BlogClientError> {
+        self.runtime
+            .block_on(self.inner.register(username, email, password))
+    }
+
+    /// Logs in existing user, see [`blog_client::BlogClient::login`]
+    pub fn login(&self, username: String, password: String) -> Result<String, BlogClientError> {
+        self.runtime.block_on(self.inner.login(username, password))
+    }
+
+    /// Creates a new post, see [`blog_client::BlogClient::create_post`]
+    pub fn create_post(&self, title: String, content: String) -> Result<Post, BlogClientError> {
+        self.runtime
+            .block_on(self.inner.create_post(title, content))
+    }
+
+    /// Creates several posts, see [`blog_client::BlogClient::create_posts`]
+    pub fn create_posts(&self, posts: Vec<(String, String)>) -> Vec<Result<Post, BlogClientError>> {
+        self.runtime.block_on(self.inner.create_posts(posts))
+    }
+
+    /// Gets several posts by ids, see [`blog_client::BlogClient::get_posts_by_ids`]
+    pub fn get_posts_by_ids(&self, ids: Vec<i64>) -> Vec<Result<Post, BlogClientError>> {
+        self.runtime.block_on(self.inner.get_posts_by_ids(ids))
+    }
+
+    /// Deletes several posts, see [`blog_client::BlogClient::delete_posts`]
+    pub fn delete_posts(&self, ids: Vec<i64>) -> Vec<Result<(), BlogClientError>> {
+        self.runtime.block_on(self.inner.delete_posts(ids))
+    }
+
+    /// Gets a post by id, see [`blog_client::BlogClient::get_post`]
+    pub fn get_post(&self, id: i64) -> Result<Post, BlogClientError> {
+        self.runtime.block_on(self.inner.get_post(id))
+    }
+
+    /// Updates the post, see [`blog_client::BlogClient::update_post`]
+    pub fn update_post(
+        &self,
+        id: i64,
+        title: String,
+        content: String,
+    ) -> Result<Post, BlogClientError> {
+        self.runtime
+            .block_on(self.inner.update_post(id, title, content))
+    }
+
+    /// Updates only set fields of the post, see [`blog_client::BlogClient::update_post_partial`]
+    pub fn update_post_partial(
+        &self,
+        id: i64,
+        fields: UpdatePostFields,
+    ) -> Result<Post, BlogClientError> {
+        self.runtime
+            .block_on(self.inner.update_post_partial(id, fields))
+    }
+
+    /// Deletes the post, see [`blog_client::BlogClient::delete_post`]
+    pub fn delete_post(&self, id: i64) -> Result<(), BlogClientError> {
+        self.runtime.block_on(self.inner.delete_post(id))
+    }
+
+    /// Gets list of posts, see [`blog_client::BlogClient::get_posts`]
+    pub fn get_posts(
+        &self,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<PostsCollection, BlogClientError> {
+        self.runtime.block_on(self.inner.get_posts(limit, offset))
+    }
+
+    /// Gets server version, see [`blog_client::BlogClient::server_info`]
+    pub fn server_info(&self) -> Result<ServerInfo, BlogClientError> {
+        self.runtime.block_on(self.inner.server_info())
+    }
+
+    /// Gets activity of the logged in user, see [`blog_client::BlogClient::user_stats`]
+    pub fn user_stats(&self) -> Result<UserStats, BlogClientError> {
+        self.runtime.block_on(self.inner.user_stats())
+    }
+
+    /// Searches posts, see [`blog_client::BlogClient::search_posts`]
+    pub fn search_posts(
+        &self,
+        query: String,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<PostsCollection, BlogClientError> {
+        self.runtime
+            .block_on(self.inner.search_posts(query, limit, offset))
+    }
+
+    /// Subscribes to changes of posts, see [`blog_client::BlogClient::subscribe_posts`]
+    ///
+    /// Every call to `next` of the returned iterator blocks until the next event arrives
+    pub fn subscribe_posts(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<PostEvent, BlogClientError>> + '_, BlogClientError>
+    {
+        let mut events = self.runtime.block_on(self.inner.subscribe_posts())?;
+        Ok(std::iter::from_fn(move || {
+            self.runtime.block_on(events.next())
+        }))
+    }
+}
@@ -1,44 +1,92 @@
 //! Module containing description of blog client interface and related structures
 
-use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use std::sync::{PoisonError, RwLock};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use futures_util::{Stream, StreamExt, TryStreamExt, stream};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     Transport,
-    api_client::{BlogApiClient, ClientType},
+    api_client::{BlogApiClient, ClientType, PostEventStream},
+    builder::{BlogClientBuilder, ClientSettings},
     error::BlogClientError,
-    grpc_client::GrpcClient,
-    http_client::HttpClient,
+    refresh::TokenRefresher,
+    retry::RetryPolicy,
+    token_store::TokenStore,
 };
 
 /// Client for blog backend interation
+///
+/// Client is `Send + Sync`: every method takes `&self`, so a single instance can be
+/// wrapped in [`std::sync::Arc`] and used concurrently from multiple tasks or threads.
+/// Token is kept behind a lock, each request uses a snapshot of the token taken
+/// when the request starts, so changing the token does not affect requests in flight
 pub struct BlogClient {
     inner: ClientType,
-    token: Option<String>,
+    token: RwLock<Option<String>>,
+    retry_policy: RetryPolicy,
+    token_refresher: Option<Box<dyn TokenRefresher>>,
+    token_store: Option<Box<dyn TokenStore>>,
+    batch_concurrency: usize,
 }
 
 impl BlogClient {
-    /// Creates client with inner api client based on transport parameter
+    /// Creates client with inner api client based on transport parameter and default options
     pub async fn new(transport: Transport) -> Result<Self, BlogClientError> {
-        let inner = match transport {
-            Transport::Http(url) => ClientType::HttpClient(HttpClient::new(url.as_str())?),
-            Transport::Grpc(url) => ClientType::GrpcClient(GrpcClient::new(url).await?),
+        Self::builder(transport).build().await
+    }
+
+    /// Creates builder to configure timeouts, headers and retries
+    pub fn builder(transport: Transport) -> BlogClientBuilder {
+        BlogClientBuilder::new(transport)
+    }
+
+    pub(crate) fn from_parts(
+        inner: ClientType,
+        settings: ClientSettings,
+    ) -> Result<Self, BlogClientError> {
+        let token = match &settings.token_store {
+            Some(store) => store.load()?,
+            None => None,
         };
 
-        Ok(Self { inner, token: None })
+        Ok(Self {
+            inner,
+            token: RwLock::new(token),
+            retry_policy: settings.retry_policy,
+            token_refresher: settings.token_refresher,
+            token_store: settings.token_store,
+            batch_concurrency: settings.batch_concurrency.max(1),
+        })
     }
 
-    /// Sets JWT token
+    /// Sets JWT token, saving it to token store if it is set
     ///
     /// # Arguments
     /// * `token` - JWT token, returned from `register` or `login` functions
-    pub fn set_token(&mut self, token: String) {
-        self.token = Some(token)
+    pub fn set_token(&self, token: String) -> Result<(), BlogClientError> {
+        self.store_token(token)
+    }
+
+    /// Removes JWT token, also from token store if it is set
+    pub fn clear_token(&self) -> Result<(), BlogClientError> {
+        *self.token.write().unwrap_or_else(PoisonError::into_inner) = None;
+
+        match &self.token_store {
+            Some(store) => store.clear(),
+            None => Ok(()),
+        }
     }
 
     /// Returns stored JWT token if it is set
-    pub fn get_token(&self) -> Option<&str> {
-        self.token.as_deref()
+    ///
+    /// Token may change after automatic refresh, see [`TokenRefresher`]
+    pub fn get_token(&self) -> Option<String> {
+        self.token
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
     }
 
     /// Register a new user
@@ -93,9 +141,12 @@ impl BlogClient {
         title: String,
         content: String,
     ) -> Result<Post, BlogClientError> {
-        self.inner
-            .create_post(self.require_token()?, title, content)
-            .await
+        self.with_token(async |token| {
+            self.inner
+                .create_post(token, title.clone(), content.clone())
+                .await
+        })
+        .await
     }
 
     /// Gets a post by id
@@ -107,7 +158,7 @@ impl BlogClient {
     /// # Returns Ok(Post) contatining the requested post if the post fetched successfully
     /// # Returns Err(BlogClientError) otherwise
     pub async fn get_post(&self, id: i64) -> Result<Post, BlogClientError> {
-        self.inner.get_post(id).await
+        self.retry_policy.retry(|| self.inner.get_post(id)).await
     }
 
     /// Updates the post with given id
@@ -129,9 +180,40 @@ impl BlogClient {
         title: String,
         content: String,
     ) -> Result<Post, BlogClientError> {
-        self.inner
-            .update_post(self.require_token()?, id, title, content)
-            .await
+        self.with_token(async |token| {
+            self.retry_policy
+                .retry(|| {
+                    self.inner
+                        .update_post(token, id, title.clone(), content.clone())
+                })
+                .await
+        })
+        .await
+    }
+
+    /// Updates only the fields of the post which are set in `fields`
+    ///
+    /// requires token to be set through `set_token`
+    /// only original author can edit the post
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - requested post id
+    /// * `fields` - new values of the fields to change, unset fields are kept unchanged
+    ///
+    /// # Returns Ok(Post) with the updated post if it is updated successfully
+    /// # Returns Err(BlogClientError) otherwise
+    pub async fn update_post_partial(
+        &self,
+        id: i64,
+        fields: UpdatePostFields,
+    ) -> Result<Post, BlogClientError> {
+        self.with_token(async |token| {
+            self.retry_policy
+                .retry(|| self.inner.patch_post(token, id, fields.clone()))
+                .await
+        })
+        .await
     }
 
     /// Deletes the post with given id
@@ -146,7 +228,12 @@ impl BlogClient {
     /// # Returns Ok(()) if it is deleted successfully
     /// # Returns Err(BlogClientError) otherwise
     pub async fn delete_post(&self, id: i64) -> Result<(), BlogClientError> {
-        self.inner.delete_post(self.require_token()?, id).await
+        self.with_token(async |token| {
+            self.retry_policy
+                .retry(|| self.inner.delete_post(token, id))
+                .await
+        })
+        .await
     }
 
     /// Gets list of posts
@@ -163,16 +250,219 @@ impl BlogClient {
         limit: Option<u64>,
         offset: Option<u64>,
     ) -> Result<PostsCollection, BlogClientError> {
-        self.inner.get_posts(limit, offset).await
+        self.retry_policy
+            .retry(|| self.inner.get_posts(limit, offset))
+            .await
+    }
+
+    /// Creates several posts concurrently
+    ///
+    /// requires token to be set through `set_token`,
+    /// number of requests in flight is limited by builder's `batch_concurrency`
+    ///
+    /// # Arguments
+    ///
+    /// * `posts` - pairs of title and content
+    ///
+    /// # Returns result for every post in the same order
+    pub async fn create_posts(
+        &self,
+        posts: Vec<(String, String)>,
+    ) -> Vec<Result<Post, BlogClientError>> {
+        stream::iter(posts)
+            .map(|(title, content)| self.create_post(title, content))
+            .buffered(self.batch_concurrency)
+            .collect()
+            .await
+    }
+
+    /// Gets several posts by ids
+    ///
+    /// uses a single batch request when the transport supports it (gRPC),
+    /// otherwise posts are requested concurrently and number of requests
+    /// in flight is limited by builder's `batch_concurrency`
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - requested post ids
+    ///
+    /// # Returns result for every id in the same order,
+    /// error of a failed batch request is returned for every id
+    pub async fn get_posts_by_ids(&self, ids: Vec<i64>) -> Vec<Result<Post, BlogClientError>> {
+        match self.inner.get_posts_by_ids(&ids).await {
+            Ok(results) => return results,
+            Err(BlogClientError::Unsupported(_)) => {}
+            Err(error) => return error_for_every_id(error, ids.len()),
+        }
+
+        stream::iter(ids)
+            .map(|id| self.get_post(id))
+            .buffered(self.batch_concurrency)
+            .collect()
+            .await
+    }
+
+    /// Gets server version and API version
+    ///
+    /// # Returns Ok(ServerInfo) if fetched successfully
+    /// # Returns Err(BlogClientError) otherwise
+    pub async fn server_info(&self) -> Result<ServerInfo, BlogClientError> {
+        self.retry_policy.retry(|| self.inner.server_info()).await
+    }
+
+    /// Gets post count and recent activity of the logged in user
+    ///
+    /// # Returns Ok(UserStats) if fetched successfully
+    /// # Returns Err(BlogClientError) otherwise, e.g. if token is not set
+    pub async fn user_stats(&self) -> Result<UserStats, BlogClientError> {
+        self.with_token(async |token| {
+            self.retry_policy
+                .retry(|| self.inner.user_stats(token))
+                .await
+        })
+        .await
+    }
+
+    /// Deletes several posts
+    ///
+    /// requires token to be set through `set_token`,
+    /// uses a single batch request when the transport supports it (gRPC),
+    /// otherwise posts are deleted concurrently and number of requests
+    /// in flight is limited by builder's `batch_concurrency`
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - ids of posts to delete
+    ///
+    /// # Returns result for every id in the same order,
+    /// error of a failed batch request is returned for every id
+    pub async fn delete_posts(&self, ids: Vec<i64>) -> Vec<Result<(), BlogClientError>> {
+        let batch = self
+            .with_token(async |token| self.inner.delete_posts(token, &ids).await)
+            .await;
+        match batch {
+            Ok(results) => return results,
+            Err(BlogClientError::Unsupported(_)) => {}
+            Err(error) => return error_for_every_id(error, ids.len()),
+        }
+
+        stream::iter(ids)
+            .map(|id| self.delete_post(id))
+            .buffered(self.batch_concurrency)
+            .collect()
+            .await
+    }
+
+    /// Searches posts by words in title and content
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - search words, must not be empty
+    /// * `limit` - optional number of posts to fetch
+    /// * `offset` - optional offset of first fetched post
+    ///
+    /// # Returns Ok(PostsCollection) with the most relevant posts first if fetched successfully
+    /// # Returns Err(BlogClientError) otherwise
+    pub async fn search_posts(
+        &self,
+        query: String,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<PostsCollection, BlogClientError> {
+        self.retry_policy
+            .retry(|| self.inner.search_posts(query.clone(), limit, offset))
+            .await
+    }
+
+    /// Subscribes to changes of posts
+    ///
+    /// Stream yields events for posts created, updated or deleted after the call
+    /// and ends when server closes the connection. Request timeout is not applied
+    /// to the stream, so it stays open while there are no changes
+    ///
+    /// # Returns Ok(PostEventStream) if subscribed successfully
+    /// # Returns Err(BlogClientError) otherwise
+    pub async fn subscribe_posts(&self) -> Result<PostEventStream, BlogClientError> {
+        self.retry_policy
+            .retry(|| self.inner.subscribe_posts())
+            .await
+    }
+
+    /// Streams all posts page by page
+    ///
+    /// Issues successive `get_posts` calls until every post is fetched,
+    /// the stream ends after the first error
+    ///
+    /// # Arguments
+    ///
+    /// * `page_size` - number of posts requested in a single call,
+    ///   server may return less if the value exceeds its maximum page size
+    pub fn posts_stream(
+        &self,
+        page_size: u64,
+    ) -> impl Stream<Item = Result<Post, BlogClientError>> + '_ {
+        stream::try_unfold(Some(0), move |offset| async move {
+            let Some(offset) = offset else {
+                return Ok(None);
+            };
+
+            let page = self.get_posts(Some(page_size), Some(offset)).await?;
+            let next_offset = offset + page.posts.len() as u64;
+            let next_offset =
+                (!page.posts.is_empty() && next_offset < page.total_posts).then_some(next_offset);
+
+            Ok::<_, BlogClientError>(Some((
+                stream::iter(page.posts.into_iter().map(Ok)),
+                next_offset,
+            )))
+        })
+        .try_flatten()
+    }
+
+    /// Runs token-protected request, refreshing token and repeating request once
+    /// if server rejects the token and refresher is configured
+    async fn with_token<T>(
+        &self,
+        request: impl AsyncFn(&str) -> Result<T, BlogClientError>,
+    ) -> Result<T, BlogClientError> {
+        let token = self.get_token().ok_or(BlogClientError::TokenNotSet)?;
+
+        match (request(&token).await, &self.token_refresher) {
+            (Err(BlogClientError::InvalidToken), Some(refresher)) => {
+                let token = refresher.refresh(&self.inner).await?;
+                self.store_token(token.clone())?;
+                request(&token).await
+            }
+            (result, _) => result,
+        }
     }
 
-    fn require_token(&self) -> Result<&str, BlogClientError> {
-        self.get_token().ok_or(BlogClientError::TokenNotSet)
+    fn store_token(&self, token: String) -> Result<(), BlogClientError> {
+        if let Some(store) = &self.token_store {
+            store.save(&token)?;
+        }
+
+        *self.token.write().unwrap_or_else(PoisonError::into_inner) = Some(token);
+        Ok(())
     }
 }
 
+/// Results of a batch request which failed as a whole
+fn error_for_every_id<T>(error: BlogClientError, count: usize) -> Vec<Result<T, BlogClientError>> {
+    let mut results: Vec<_> = (1..count).map(|_| Err(error.replicate())).collect();
+    if count > 0 {
+        results.push(Err(error));
+    }
+    results
+}
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<BlogClient>();
+};
+
 /// Response for list of posts
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PostsCollection {
     /// List of posts
     pub posts: Vec<Post>,
@@ -184,8 +474,99 @@ pub struct PostsCollection {
     pub total_posts: u64,
 }
 
+/// Fields of a post changed by [`BlogClient::update_post_partial`], `None` keeps the field unchanged
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UpdatePostFields {
+    /// new post title
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// new post content
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+/// Change of a post delivered by [`BlogClient::subscribe_posts`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PostEvent {
+    /// Post was created
+    Created(Post),
+    /// Post was updated
+    Updated(Post),
+    /// Post was deleted
+    Deleted {
+        /// id of the deleted post
+        id: i64,
+    },
+}
+
+/// Version of the server and its API
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerInfo {
+    /// server package version, e.g. `0.1.0`
+    pub version: String,
+    /// API version, newer than [`crate::API_VERSION`] means the server may be
+    /// incompatible with this library
+    pub api_version: u32,
+    /// optional features provided by the server, e.g. `search`, see [`ServerInfo::supports`]
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// server is in maintenance mode and rejects writes
+    #[serde(default)]
+    pub read_only: bool,
+    /// limits of the server, `None` for servers which don't report them
+    #[serde(default)]
+    pub limits: Option<ServerLimits>,
+}
+
+impl ServerInfo {
+    /// Check if the server provides optional feature, e.g. `search` or `post_events`
+    pub fn supports(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+}
+
+/// Limits of the server, requests exceeding them are rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerLimits {
+    /// max length of post title in characters
+    pub max_title_length: u64,
+    /// max length of post content in characters
+    pub max_content_length: u64,
+    /// max number of posts per page
+    pub max_page_limit: u64,
+    /// largest body of HTTP request in bytes
+    pub max_request_size: u64,
+    /// largest gRPC message in bytes
+    pub max_grpc_message_size: u64,
+}
+
+/// Activity of the logged in user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserStats {
+    /// user id
+    pub user_id: i64,
+    /// user name
+    pub username: String,
+    /// number of posts written by the user
+    pub posts: u64,
+    /// creation time of the latest post, `None` if user has no posts
+    pub last_post_at: Option<DateTime<Utc>>,
+    /// posts created by the user during last 30 days, oldest day first
+    pub posts_per_day: Vec<DailyPosts>,
+}
+
+/// Number of posts created on a day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyPosts {
+    /// day in server time zone
+    pub day: NaiveDate,
+    /// number of posts
+    pub posts: u64,
+}
+
 /// Post structure
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Post {
     /// post id
     pub id: i64,
@@ -195,6 +576,9 @@ pub struct Post {
     pub content: String,
     /// user id of post author
     pub author_id: i64,
+    /// user name of post author, `None` if server does not provide it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_username: Option<String>,
     /// when post was created
     pub created_at: DateTime<Utc>,
     /// when post was updated last time
@@ -0,0 +1,271 @@
+//! Builder for [`BlogClient`] with connection options
+
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    Transport,
+    api_client::ClientType,
+    blog_client::BlogClient,
+    error::BlogClientError,
+    http_client::HttpClient,
+    middleware::{ClientMiddleware, Middlewares},
+    refresh::TokenRefresher,
+    retry::RetryPolicy,
+    token_store::TokenStore,
+};
+#[cfg(feature = "grpc")]
+use crate::{auto_client::AutoClient, grpc_client::GrpcClient};
+
+const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+
+/// Connection settings shared by HTTP and gRPC transports
+///
+/// In browser (`wasm32`) connection is managed by `fetch`, only timeouts,
+/// headers and middlewares are used
+#[derive(Clone)]
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub(crate) struct ConnectionOptions {
+    pub(crate) connect_timeout: Duration,
+    pub(crate) request_timeout: Duration,
+    pub(crate) keepalive: Option<Duration>,
+    pub(crate) user_agent: Option<String>,
+    pub(crate) default_headers: Vec<(String, String)>,
+    pub(crate) middlewares: Middlewares,
+    pub(crate) proxy: ProxyMode,
+    pub(crate) etag_cache: bool,
+    pub(crate) rate_limit_budget: Duration,
+    pub(crate) api_prefix: String,
+    pub(crate) compression: bool,
+    pub(crate) cookie_session: bool,
+    #[cfg_attr(not(feature = "grpc"), allow(dead_code))]
+    pub(crate) grpc_max_message_size: usize,
+}
+
+/// Proxy used by HTTP transport
+#[derive(Clone)]
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub(crate) enum ProxyMode {
+    /// Proxy from `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY` env vars
+    System,
+    /// Explicit proxy URL
+    Url(String),
+    /// Direct connection
+    Disabled,
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(10),
+            keepalive: None,
+            user_agent: None,
+            default_headers: Vec::new(),
+            middlewares: Middlewares::default(),
+            proxy: ProxyMode::System,
+            etag_cache: false,
+            rate_limit_budget: Duration::ZERO,
+            api_prefix: String::from("/api"),
+            compression: true,
+            cookie_session: false,
+            grpc_max_message_size: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Transport-independent settings of [`BlogClient`]
+pub(crate) struct ClientSettings {
+    pub(crate) retry_policy: RetryPolicy,
+    pub(crate) token_refresher: Option<Box<dyn TokenRefresher>>,
+    pub(crate) token_store: Option<Box<dyn TokenStore>>,
+    pub(crate) batch_concurrency: usize,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        Self {
+            retry_policy: RetryPolicy::none(),
+            token_refresher: None,
+            token_store: None,
+            batch_concurrency: DEFAULT_BATCH_CONCURRENCY,
+        }
+    }
+}
+
+/// Builder for [`BlogClient`], created with [`BlogClient::builder`]
+///
+/// Connection options are ignored for [`Transport::Custom`]
+pub struct BlogClientBuilder {
+    transport: Transport,
+    options: ConnectionOptions,
+    settings: ClientSettings,
+}
+
+impl BlogClientBuilder {
+    pub(crate) fn new(transport: Transport) -> Self {
+        Self {
+            transport,
+            options: ConnectionOptions::default(),
+            settings: ClientSettings::default(),
+        }
+    }
+
+    /// Timeout for establishing connection, 5 seconds by default
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.options.connect_timeout = timeout;
+        self
+    }
+
+    /// Timeout for a whole request, 10 seconds by default
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.options.request_timeout = timeout;
+        self
+    }
+
+    /// Enables TCP keepalive with given interval
+    pub fn keepalive(mut self, interval: Duration) -> Self {
+        self.options.keepalive = Some(interval);
+        self
+    }
+
+    /// Value of `User-Agent` header sent with every request
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.options.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Adds header (gRPC metadata entry) sent with every request
+    ///
+    /// Header name must be lowercase ASCII for gRPC transport
+    pub fn default_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options
+            .default_headers
+            .push((name.into(), value.into()));
+        self
+    }
+
+    /// Sends HTTP requests through given proxy, e.g. `http://proxy:3128` or `socks5://proxy:1080`
+    ///
+    /// By default proxy is taken from standard `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY`
+    /// and `NO_PROXY` env vars. gRPC transport always connects directly
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.options.proxy = ProxyMode::Url(url.into());
+        self
+    }
+
+    /// Disables proxy for HTTP transport including proxy from env vars
+    pub fn no_proxy(mut self) -> Self {
+        self.options.proxy = ProxyMode::Disabled;
+        self
+    }
+
+    /// Path under which HTTP API is mounted, `/api` by default
+    ///
+    /// E.g. `/blog/api` when the server is behind a reverse proxy. Ignored for gRPC transport
+    pub fn api_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.options.api_prefix = prefix.into();
+        self
+    }
+
+    /// Requests gzip or deflate compressed HTTP responses and decompresses them transparently,
+    /// enabled by default
+    ///
+    /// gRPC transport sends and accepts gzip compressed messages.
+    /// In browser compression is always handled by `fetch`
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.options.compression = enabled;
+        self
+    }
+
+    /// Largest gRPC message sent or received, 16 MiB by default
+    ///
+    /// Ignored for HTTP transport
+    pub fn grpc_max_message_size(mut self, bytes: usize) -> Self {
+        self.options.grpc_max_message_size = bytes;
+        self
+    }
+
+    /// Enables cache of HTTP GET responses (posts, post lists and search results)
+    ///
+    /// Cached response ETag is sent in `If-None-Match` header and the cached body
+    /// is reused when server answers `304 Not Modified`. Ignored for gRPC transport
+    pub fn etag_cache(mut self, enabled: bool) -> Self {
+        self.options.etag_cache = enabled;
+        self
+    }
+
+    /// Authorizes requests with the session cookie set by the server instead of bearer token
+    ///
+    /// Token passed to [`crate::blog_client::BlogClient::set_token`] is then the CSRF token
+    /// of the session, sent in `X-CSRF-Token` header. In browser requests are sent with
+    /// credentials, so the cookie reaches the server; native clients don't keep cookies.
+    /// Ignored for gRPC transport
+    pub fn cookie_session(mut self, enabled: bool) -> Self {
+        self.options.cookie_session = enabled;
+        self
+    }
+
+    /// Total time a call may spend waiting when HTTP server answers `429 Too Many Requests`
+    ///
+    /// Request is repeated after the delay from `Retry-After` header while it fits into
+    /// the budget, otherwise [`BlogClientError::RateLimited`] is returned.
+    /// Zero by default, i.e. the error is returned at once
+    pub fn rate_limit_budget(mut self, budget: Duration) -> Self {
+        self.options.rate_limit_budget = budget;
+        self
+    }
+
+    /// Adds middleware called for every request and response, see [`ClientMiddleware`]
+    pub fn middleware(mut self, middleware: impl ClientMiddleware + 'static) -> Self {
+        self.options.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Sets retry policy for idempotent requests (get, list, update, delete)
+    ///
+    /// By default requests are not retried
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.settings.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sets hook used to get a new token when server rejects the stored one
+    pub fn token_refresher(mut self, refresher: impl TokenRefresher + 'static) -> Self {
+        self.settings.token_refresher = Some(Box::new(refresher));
+        self
+    }
+
+    /// Sets storage used to load token on build and persist token changes
+    pub fn token_store(mut self, store: impl TokenStore + 'static) -> Self {
+        self.settings.token_store = Some(Box::new(store));
+        self
+    }
+
+    /// Maximum number of concurrent requests made by batch methods
+    /// such as `create_posts`, 4 by default
+    pub fn batch_concurrency(mut self, limit: usize) -> Self {
+        self.settings.batch_concurrency = limit;
+        self
+    }
+
+    /// Creates client, connecting to server for gRPC transport
+    /// and loading token from token store if it is set
+    pub async fn build(self) -> Result<BlogClient, BlogClientError> {
+        let inner = match self.transport {
+            Transport::Http(url) => {
+                ClientType::HttpClient(HttpClient::new(url.as_str(), &self.options)?)
+            }
+            #[cfg(feature = "grpc")]
+            Transport::Grpc(url) => {
+                ClientType::GrpcClient(GrpcClient::new(url, &self.options).await?)
+            }
+            #[cfg(feature = "grpc")]
+            Transport::Auto(url) => {
+                ClientType::AutoClient(AutoClient::new(url, &self.options).await?)
+            }
+            Transport::Custom(client) => ClientType::Custom(client),
+        };
+
+        BlogClient::from_parts(inner, self.settings)
+    }
+}
@@ -0,0 +1,125 @@
+//! Debug logging of HTTP requests and responses, enabled by `debug-logging` feature
+//!
+//! Credentials never reach the log: authorization and cookie headers and
+//! password or token fields of JSON bodies are replaced with a placeholder
+
+use reqwest::{
+    Request, Response,
+    header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, HeaderMap, PROXY_AUTHORIZATION, SET_COOKIE},
+};
+use serde_json::Value;
+
+use crate::error::BlogClientError;
+
+const REDACTED: &str = "<redacted>";
+/// JSON fields with any of these words in the name are redacted, e.g. `refresh_token`
+const SENSITIVE_FIELDS: [&str; 2] = ["password", "token"];
+/// Longer bodies are truncated in the log
+const MAX_BODY_LENGTH: usize = 4096;
+
+/// Logs request line, headers and body
+pub(crate) fn log_request(operation: &str, request: &Request, body: Option<&[u8]>) {
+    tracing::debug!(
+        operation,
+        method = %request.method(),
+        url = %request.url(),
+        headers = ?sanitize_headers(request.headers()),
+        body = body.map(sanitize_body).unwrap_or_default(),
+        "HTTP request"
+    );
+}
+
+/// Logs response status, headers and body
+///
+/// The body is read into memory and put back into the returned response.
+/// Event streams are not buffered, and in browser only status and headers are logged
+/// since `fetch` responses can't be rebuilt
+pub(crate) async fn log_response(
+    operation: &str,
+    response: Response,
+) -> Result<Response, BlogClientError> {
+    let status = response.status();
+    let headers = sanitize_headers(response.headers());
+
+    if cfg!(target_arch = "wasm32") || is_event_stream(response.headers()) {
+        tracing::debug!(operation, %status, ?headers, "HTTP response");
+        return Ok(response);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let response = {
+        let version = response.version();
+        let response_headers = response.headers().clone();
+        let body = response.bytes().await?;
+        tracing::debug!(
+            operation,
+            %status,
+            ?headers,
+            body = sanitize_body(&body),
+            "HTTP response"
+        );
+
+        let mut rebuilt = http::Response::new(body);
+        *rebuilt.status_mut() = status;
+        *rebuilt.version_mut() = version;
+        *rebuilt.headers_mut() = response_headers;
+        Response::from(rebuilt)
+    };
+
+    Ok(response)
+}
+
+fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"))
+}
+
+fn sanitize_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE, SET_COOKIE].contains(name) {
+                String::from(REDACTED)
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+fn sanitize_body(body: &[u8]) -> String {
+    let mut body = match serde_json::from_slice::<Value>(body) {
+        Ok(mut json) => {
+            redact(&mut json);
+            json.to_string()
+        }
+        Err(_) => String::from_utf8_lossy(body).into_owned(),
+    };
+
+    if body.len() > MAX_BODY_LENGTH {
+        let length = body.floor_char_boundary(MAX_BODY_LENGTH);
+        body.truncate(length);
+        body.push_str("...");
+    }
+    body
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, value) in fields.iter_mut() {
+                let name = name.to_ascii_lowercase();
+                if SENSITIVE_FIELDS.iter().any(|field| name.contains(field)) {
+                    *value = Value::String(String::from(REDACTED));
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
@@ -1,6 +1,17 @@
 //! Blog client library errors
 
+use std::time::Duration;
+
+#[cfg(feature = "grpc")]
 use tonic::metadata::errors::InvalidMetadataValue;
+#[cfg(feature = "grpc")]
+use tonic_types::StatusExt;
+
+/// Domain of `google.rpc.ErrorInfo` details attached by blog server
+#[cfg(feature = "grpc")]
+const SERVER_ERROR_DOMAIN: &str = "blog-server";
+
+pub use blog_types::FieldViolation;
 
 /// Error variants
 #[derive(Debug, thiserror::Error)]
@@ -15,17 +26,25 @@ pub enum BlogClientError {
     #[error("Token is not set")]
     TokenNotSet,
     /// GRPC transport error
+    #[cfg(feature = "grpc")]
     #[error("GRPC transport error: {0}")]
     GrpcTransport(#[from] tonic::transport::Error),
     /// GRPC expected field not set in response
     #[error("GRPC field not set: {0}")]
     GrpcFieldNotSet(String),
-    /// Can't create timestamp from millis
-    #[error("Unable to create Datetime from: {0}")]
-    IncorrectTimestamp(i64),
+    /// Can't parse date from GRPC response
+    #[error("Unable to parse date: {0}")]
+    IncorrectDate(String),
     /// Can't create GRPC metadata from token
+    #[cfg(feature = "grpc")]
     #[error("Unable to create GRPC metadata from token: {0}")]
     InvalidMetadata(#[from] InvalidMetadataValue),
+    /// I/O error, e.g. while accessing token file or starting runtime of blocking client
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Default header name or value can't be used in request
+    #[error("Invalid header {0}")]
+    InvalidHeader(String),
     /// User with provided username or email already exists
     #[error("User with provided username or email already exists")]
     UserAlreadyExists,
@@ -57,15 +76,100 @@ pub enum BlogClientError {
     /// Not found
     #[error("Resource not found")]
     NotFound,
+    /// gRPC server could not be reached
+    #[error("Unable to connect to gRPC server: {0}")]
+    GrpcConnection(String),
+    /// Connection to gRPC server failed after request was sent, it may have been processed
+    #[cfg(feature = "grpc")]
+    #[error("Connection to gRPC server failed during request: {0}")]
+    GrpcInterrupted(String),
+    /// Call did not finish within timeout, e.g. set in [`crate::options::RequestOptions`]
+    #[error("Request timed out")]
+    Timeout,
+    /// Call was cancelled through [`crate::options::CancellationToken`]
+    #[error("Request cancelled")]
+    Cancelled,
+    /// Server rejected request because of rate limit (HTTP 429, gRPC `RESOURCE_EXHAUSTED`)
+    #[error(
+        "Rate limit exceeded{}",
+        .retry_after.map(|delay| format!(", retry after {delay:?}")).unwrap_or_default()
+    )]
+    RateLimited {
+        /// Delay requested by server before the next attempt
+        retry_after: Option<Duration>,
+    },
+    /// Event received from subscription can't be parsed
+    #[error("Unable to parse post event: {0}")]
+    InvalidEvent(serde_json::Error),
+    /// Server rejected values of request fields
+    #[error("Validation failed: {message}")]
+    Validation {
+        /// Error message
+        message: String,
+        /// Invalid fields
+        fields: Vec<FieldViolation>,
+    },
+    /// Transport has no such request, e.g. batch requests over HTTP
+    #[error("Operation {0} is not supported by transport")]
+    Unsupported(&'static str),
+    /// Server could not be reached, copy of a connection error of another crate
+    /// made by reporting it for every item of a batch request
+    #[error("Unable to connect to server: {0}")]
+    Connection(String),
+    /// Server is in read-only maintenance mode and rejects changes, reads still work
+    #[error("Server is in read-only mode: {0}")]
+    ReadOnly(String),
 }
 
+#[cfg(feature = "grpc")]
 impl From<tonic::Status> for BlogClientError {
     fn from(status: tonic::Status) -> Self {
+        let source = std::error::Error::source(&status);
+        if source.is_some_and(|e| e.is::<tonic::TimeoutExpired>()) {
+            return BlogClientError::Timeout;
+        }
+        if source.is_some_and(|e| e.is::<tonic::transport::Error>()) {
+            let message = status.message().to_string();
+            let not_connected = std::iter::successors(source, |e| e.source())
+                .any(|e| e.is::<tonic::ConnectError>());
+            if not_connected {
+                return BlogClientError::GrpcConnection(message);
+            }
+            return BlogClientError::GrpcInterrupted(message);
+        }
+
+        if let Some(bad_request) = status.get_details_bad_request() {
+            return BlogClientError::Validation {
+                message: status.message().to_string(),
+                fields: bad_request
+                    .field_violations
+                    .into_iter()
+                    .map(|violation| FieldViolation {
+                        field: violation.field,
+                        message: violation.description,
+                    })
+                    .collect(),
+            };
+        }
+
+        if let Some(error) = status
+            .get_details_error_info()
+            .filter(|info| info.domain == SERVER_ERROR_DOMAIN)
+            .and_then(|info| error_from_reason(&info.reason, status.message()))
+        {
+            return error;
+        }
+
         match status.code() {
             tonic::Code::AlreadyExists => BlogClientError::UserAlreadyExists,
             tonic::Code::NotFound => BlogClientError::NotFound,
             tonic::Code::Unauthenticated => BlogClientError::InvalidToken,
             tonic::Code::PermissionDenied => BlogClientError::Forbidden,
+            tonic::Code::ResourceExhausted => BlogClientError::RateLimited {
+                retry_after: status
+                    .get_details_retry_info()
+                    .and_then(|info| info.retry_delay),
+            },
             other => BlogClientError::UnexpectedGrpcResponse {
                 status_code: other as u16,
                 message: status.message().to_string(),
@@ -73,3 +177,199 @@ impl From<tonic::Status> for BlogClientError {
         }
     }
 }
+
+/// Error for reason code of `google.rpc.ErrorInfo` sent by server, which tells apart
+/// errors sharing a gRPC status, e.g. wrong password and expired token
+#[cfg(feature = "grpc")]
+pub(crate) fn error_from_reason(reason: &str, message: &str) -> Option<BlogClientError> {
+    let error = match reason {
+        // unknown user is reported by login only, like in HTTP API
+        "INVALID_CREDENTIALS" | "USER_NOT_FOUND" => BlogClientError::InvalidCredentials,
+        "INVALID_TOKEN" => BlogClientError::InvalidToken,
+        "USER_ALREADY_EXISTS" => BlogClientError::UserAlreadyExists,
+        "POST_NOT_FOUND" => BlogClientError::NotFound,
+        "FORBIDDEN" => BlogClientError::Forbidden,
+        "MAINTENANCE" => BlogClientError::ReadOnly(message.to_string()),
+        _ => return None,
+    };
+    Some(error)
+}
+
+impl BlogClientError {
+    /// Whether server could not be reached or did not answer in time,
+    /// e.g. to keep the request and repeat it later
+    pub fn is_unreachable(&self) -> bool {
+        match self {
+            BlogClientError::Reqwest(e) => is_connect_error(e) || e.is_timeout(),
+            BlogClientError::Connection(_)
+            | BlogClientError::GrpcConnection(_)
+            | BlogClientError::Timeout => true,
+            #[cfg(feature = "grpc")]
+            BlogClientError::GrpcTransport(_) | BlogClientError::GrpcInterrupted(_) => true,
+            #[cfg(feature = "grpc")]
+            BlogClientError::UnexpectedGrpcResponse { status_code, .. } => {
+                tonic::Code::from(*status_code as i32) == tonic::Code::Unavailable
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether server could not be reached with the used protocol
+    #[cfg(feature = "grpc")]
+    pub(crate) fn is_transport_failure(&self) -> bool {
+        self.is_connect_failure() || matches!(self, BlogClientError::GrpcInterrupted(_))
+    }
+
+    /// Whether request failed before it was sent, so it can't have been processed by server
+    #[cfg(feature = "grpc")]
+    pub(crate) fn is_connect_failure(&self) -> bool {
+        match self {
+            BlogClientError::Reqwest(e) => is_connect_error(e),
+            BlogClientError::Connection(_)
+            | BlogClientError::GrpcTransport(_)
+            | BlogClientError::GrpcConnection(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the request may succeed if repeated
+    pub(crate) fn is_retryable(&self) -> bool {
+        match self {
+            BlogClientError::Reqwest(e) => is_connect_error(e) || e.is_timeout(),
+            BlogClientError::Connection(_)
+            | BlogClientError::GrpcConnection(_)
+            | BlogClientError::Timeout => true,
+            #[cfg(feature = "grpc")]
+            BlogClientError::GrpcTransport(_) | BlogClientError::GrpcInterrupted(_) => true,
+            BlogClientError::UnexpectedHttpResponse { code, .. } => *code >= 500,
+            #[cfg(feature = "grpc")]
+            BlogClientError::UnexpectedGrpcResponse { status_code, .. } => [
+                tonic::Code::Internal,
+                tonic::Code::Unavailable,
+                tonic::Code::DeadlineExceeded,
+            ]
+            .contains(&tonic::Code::from(*status_code as i32)),
+            _ => false,
+        }
+    }
+
+    /// Copy of the error for reporting it more than once, e.g. for every id of a failed
+    /// batch request
+    ///
+    /// Errors of other crates are not `Clone` and are replaced with the closest variant
+    /// carrying their message
+    pub(crate) fn replicate(&self) -> Self {
+        match self {
+            BlogClientError::InvalidUrl(e) => BlogClientError::InvalidUrl(*e),
+            BlogClientError::Reqwest(e) if e.is_timeout() => BlogClientError::Timeout,
+            BlogClientError::Reqwest(e) if is_connect_error(e) => {
+                BlogClientError::Connection(e.to_string())
+            }
+            BlogClientError::Reqwest(e) => BlogClientError::UnexpectedHttpResponse {
+                code: e.status().map(|status| status.as_u16()).unwrap_or_default(),
+                message: e.to_string(),
+            },
+            BlogClientError::TokenNotSet => BlogClientError::TokenNotSet,
+            #[cfg(feature = "grpc")]
+            BlogClientError::GrpcTransport(e) => BlogClientError::GrpcConnection(e.to_string()),
+            BlogClientError::GrpcFieldNotSet(field) => {
+                BlogClientError::GrpcFieldNotSet(field.clone())
+            }
+            BlogClientError::IncorrectDate(date) => BlogClientError::IncorrectDate(date.clone()),
+            #[cfg(feature = "grpc")]
+            BlogClientError::InvalidMetadata(e) => BlogClientError::InvalidHeader(e.to_string()),
+            BlogClientError::Io(e) => {
+                BlogClientError::Io(std::io::Error::new(e.kind(), e.to_string()))
+            }
+            BlogClientError::InvalidHeader(header) => {
+                BlogClientError::InvalidHeader(header.clone())
+            }
+            BlogClientError::UserAlreadyExists => BlogClientError::UserAlreadyExists,
+            BlogClientError::UnexpectedHttpResponse { code, message } => {
+                BlogClientError::UnexpectedHttpResponse {
+                    code: *code,
+                    message: message.clone(),
+                }
+            }
+            BlogClientError::UnexpectedGrpcResponse {
+                status_code,
+                message,
+            } => BlogClientError::UnexpectedGrpcResponse {
+                status_code: *status_code,
+                message: message.clone(),
+            },
+            BlogClientError::InvalidCredentials => BlogClientError::InvalidCredentials,
+            BlogClientError::InvalidToken => BlogClientError::InvalidToken,
+            BlogClientError::Forbidden => BlogClientError::Forbidden,
+            BlogClientError::NotFound => BlogClientError::NotFound,
+            BlogClientError::GrpcConnection(message) => {
+                BlogClientError::GrpcConnection(message.clone())
+            }
+            #[cfg(feature = "grpc")]
+            BlogClientError::GrpcInterrupted(message) => {
+                BlogClientError::GrpcInterrupted(message.clone())
+            }
+            BlogClientError::Timeout => BlogClientError::Timeout,
+            BlogClientError::Cancelled => BlogClientError::Cancelled,
+            BlogClientError::RateLimited { retry_after } => BlogClientError::RateLimited {
+                retry_after: *retry_after,
+            },
+            BlogClientError::InvalidEvent(e) => {
+                BlogClientError::InvalidEvent(serde::de::Error::custom(e))
+            }
+            BlogClientError::Validation { message, fields } => BlogClientError::Validation {
+                message: message.clone(),
+                fields: fields.clone(),
+            },
+            BlogClientError::Unsupported(operation) => BlogClientError::Unsupported(operation),
+            BlogClientError::Connection(message) => BlogClientError::Connection(message.clone()),
+            BlogClientError::ReadOnly(message) => BlogClientError::ReadOnly(message.clone()),
+        }
+    }
+}
+
+/// Whether request failed before reaching the server
+fn is_connect_error(error: &reqwest::Error) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    return error.is_connect();
+    // fetch does not distinguish connection errors from other request failures
+    #[cfg(target_arch = "wasm32")]
+    return error.is_request();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn replicated_connection_error_keeps_classification() {
+        // nothing listens on port 1
+        let error: BlogClientError = reqwest::get("http://127.0.0.1:1").await.unwrap_err().into();
+        assert!(error.is_unreachable());
+
+        let copy = error.replicate();
+        assert!(matches!(copy, BlogClientError::Connection(_)));
+        assert_eq!(copy.is_unreachable(), error.is_unreachable());
+        assert_eq!(copy.is_retryable(), error.is_retryable());
+        #[cfg(feature = "grpc")]
+        assert_eq!(copy.is_transport_failure(), error.is_transport_failure());
+    }
+
+    #[test]
+    fn replicated_errors_keep_classification() {
+        let errors = [
+            BlogClientError::Timeout,
+            BlogClientError::Connection(String::from("refused")),
+            BlogClientError::UnexpectedHttpResponse {
+                code: 503,
+                message: String::new(),
+            },
+            BlogClientError::NotFound,
+        ];
+        for error in errors {
+            let copy = error.replicate();
+            assert_eq!(copy.is_unreachable(), error.is_unreachable(), "{error:?}");
+            assert_eq!(copy.is_retryable(), error.is_retryable(), "{error:?}");
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Cache of GET responses validated with ETags
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+};
+
+use reqwest::{
+    Response, StatusCode,
+    header::{ETAG, HeaderMap, HeaderValue},
+};
+
+use crate::error::BlogClientError;
+
+/// Cached entries are dropped all at once when this number is reached
+const CAPACITY: usize = 256;
+
+/// Responses keyed by URL, sent back to caller when server answers `304 Not Modified`
+#[derive(Default)]
+pub(crate) struct EtagCache {
+    entries: Mutex<HashMap<String, CachedResponse>>,
+}
+
+struct CachedResponse {
+    etag: HeaderValue,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl EtagCache {
+    /// ETag of cached response for the URL, to be sent in `If-None-Match`
+    pub(crate) fn etag(&self, url: &str) -> Option<HeaderValue> {
+        self.entries().get(url).map(|cached| cached.etag.clone())
+    }
+
+    /// Stores tagged successful response or replaces `304 Not Modified` with cached one
+    pub(crate) async fn process(
+        &self,
+        url: String,
+        response: Response,
+    ) -> Result<Response, BlogClientError> {
+        match response.status() {
+            StatusCode::NOT_MODIFIED => match self.entries().get(&url) {
+                Some(cached) => Ok(to_response(cached.headers.clone(), cached.body.clone())),
+                None => Ok(response),
+            },
+            StatusCode::OK => {
+                let Some(etag) = response.headers().get(ETAG).cloned() else {
+                    return Ok(response);
+                };
+
+                let headers = response.headers().clone();
+                let body = response.bytes().await?.to_vec();
+
+                let mut entries = self.entries();
+                if entries.len() >= CAPACITY {
+                    entries.clear();
+                }
+                entries.insert(
+                    url,
+                    CachedResponse {
+                        etag,
+                        headers: headers.clone(),
+                        body: body.clone(),
+                    },
+                );
+
+                Ok(to_response(headers, body))
+            }
+            _ => Ok(response),
+        }
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<String, CachedResponse>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn to_response(headers: HeaderMap, body: Vec<u8>) -> Response {
+    let mut response = http::Response::new(body);
+    *response.headers_mut() = headers;
+    Response::from(response)
+}
@@ -1,38 +1,102 @@
 //! Blog client using GRPC protocol
 
-use std::time::Duration;
+use std::time::SystemTime;
 
 use blog_grpc_api::{
-    CreatePostRequest, DeletePostRequest, GetPostRequest, GetPostsRequest, LoginRequest,
-    RegisterRequest, UpdatePostRequest, blog_service_client::BlogServiceClient,
+    BatchDeletePostsRequest, BatchError, BatchGetPostsRequest, CreatePostRequest,
+    DeletePostRequest, GetPostRequest, GetPostsRequest, LoginRequest, PatchPostRequest,
+    PostEventKind, RegisterRequest, SearchPostsRequest, SubscribePostsRequest, Timestamp,
+    UpdatePostRequest, batch_post_result, blog_service_client::BlogServiceClient,
 };
 use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
 use tonic::{
-    IntoRequest, Request,
-    metadata::MetadataValue,
+    IntoRequest, Request, Response, Status,
+    codec::CompressionEncoding,
+    metadata::{AsciiMetadataKey, AsciiMetadataValue, MetadataMap, MetadataValue},
+    service::{Interceptor, interceptor::InterceptedService},
     transport::{Channel, Endpoint},
 };
 
 use crate::{
-    api_client::BlogApiClient,
-    blog_client::{Post, PostsCollection},
-    error::BlogClientError,
+    api_client::{BlogApiClient, PostEventStream},
+    blog_client::{
+        DailyPosts, Post, PostEvent, PostsCollection, ServerInfo, ServerLimits, UpdatePostFields,
+        UserStats,
+    },
+    builder::ConnectionOptions,
+    error::{BlogClientError, error_from_reason},
+    middleware::{Middlewares, ResponseStatus},
+    options::current_timeout,
 };
 
 /// GRPC client for blog-server
 pub(crate) struct GrpcClient {
-    client: BlogServiceClient<Channel>,
+    client: BlogServiceClient<InterceptedService<Channel, DefaultMetadata>>,
+    middlewares: Middlewares,
 }
 
 impl GrpcClient {
-    pub(crate) async fn new(url: String) -> Result<Self, BlogClientError> {
-        let endpoint = Endpoint::from_shared(url)?;
-        let channel = endpoint
-            .connect_timeout(Duration::from_secs(5))
-            .connect()
-            .await?;
-        let client = BlogServiceClient::new(channel);
-        Ok(Self { client })
+    pub(crate) async fn new(
+        url: String,
+        options: &ConnectionOptions,
+    ) -> Result<Self, BlogClientError> {
+        let mut endpoint = Endpoint::from_shared(url)?
+            .connect_timeout(options.connect_timeout)
+            .timeout(options.request_timeout)
+            .tcp_keepalive(options.keepalive);
+
+        if let Some(user_agent) = &options.user_agent {
+            endpoint = endpoint.user_agent(user_agent.as_str())?;
+        }
+
+        let metadata = DefaultMetadata::new(&options.default_headers)?;
+
+        let channel = endpoint.connect().await?;
+        let mut client = BlogServiceClient::with_interceptor(channel, metadata)
+            .max_decoding_message_size(options.grpc_max_message_size)
+            .max_encoding_message_size(options.grpc_max_message_size);
+        if options.compression {
+            client = client
+                .send_compressed(CompressionEncoding::Gzip)
+                .accept_compressed(CompressionEncoding::Gzip);
+        }
+        Ok(Self {
+            client,
+            middlewares: options.middlewares.clone(),
+        })
+    }
+}
+
+/// Adds configured metadata entries to every request without overriding existing ones
+#[derive(Clone)]
+pub(crate) struct DefaultMetadata(Vec<(AsciiMetadataKey, AsciiMetadataValue)>);
+
+impl DefaultMetadata {
+    fn new(headers: &[(String, String)]) -> Result<Self, BlogClientError> {
+        let entries = headers
+            .iter()
+            .map(|(name, value)| {
+                let key = AsciiMetadataKey::from_bytes(name.as_bytes())
+                    .map_err(|_| BlogClientError::InvalidHeader(name.clone()))?;
+                let value = AsciiMetadataValue::try_from(value.as_str())?;
+                Ok((key, value))
+            })
+            .collect::<Result<_, BlogClientError>>()?;
+
+        Ok(Self(entries))
+    }
+}
+
+impl Interceptor for DefaultMetadata {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let metadata = request.metadata_mut();
+        for (key, value) in &self.0 {
+            if !metadata.contains_key(key) {
+                metadata.insert(key.clone(), value.clone());
+            }
+        }
+        Ok(request)
     }
 }
 
@@ -53,9 +117,11 @@ impl BlogApiClient for GrpcClient {
                     email,
                     password,
                 }
-                .into_request(),
+                .into_request()
+                .prepared(&self.middlewares, "register")?,
             )
-            .await?;
+            .await
+            .observed(&self.middlewares, "register")?;
 
         Ok(response.into_inner().token)
     }
@@ -64,8 +130,13 @@ impl BlogApiClient for GrpcClient {
         let mut client = self.client.clone();
 
         let response = client
-            .login(LoginRequest { username, password }.into_request())
-            .await?;
+            .login(
+                LoginRequest { username, password }
+                    .into_request()
+                    .prepared(&self.middlewares, "login")?,
+            )
+            .await
+            .observed(&self.middlewares, "login")?;
 
         Ok(response.into_inner().token)
     }
@@ -82,9 +153,11 @@ impl BlogApiClient for GrpcClient {
             .create_post(
                 CreatePostRequest { title, content }
                     .into_request()
-                    .with_token_auth(token)?,
+                    .with_token_auth(token)?
+                    .prepared(&self.middlewares, "create_post")?,
             )
-            .await?;
+            .await
+            .observed(&self.middlewares, "create_post")?;
 
         let post = response
             .into_inner()
@@ -97,8 +170,13 @@ impl BlogApiClient for GrpcClient {
         let mut client = self.client.clone();
 
         let response = client
-            .get_post(GetPostRequest { post_id: id }.into_request())
-            .await?;
+            .get_post(
+                GetPostRequest { post_id: id }
+                    .into_request()
+                    .prepared(&self.middlewares, "get_post")?,
+            )
+            .await
+            .observed(&self.middlewares, "get_post")?;
 
         let post = response
             .into_inner()
@@ -122,11 +200,43 @@ impl BlogApiClient for GrpcClient {
                     post_id: id,
                     title,
                     content,
+                    update_mask: None,
                 }
                 .into_request()
-                .with_token_auth(token)?,
+                .with_token_auth(token)?
+                .prepared(&self.middlewares, "update_post")?,
             )
-            .await?;
+            .await
+            .observed(&self.middlewares, "update_post")?;
+
+        let post = response
+            .into_inner()
+            .post
+            .ok_or_else(|| BlogClientError::GrpcFieldNotSet(String::from("post")))?;
+        into_domain_post(post)
+    }
+
+    async fn patch_post(
+        &self,
+        token: &str,
+        id: i64,
+        fields: UpdatePostFields,
+    ) -> Result<crate::blog_client::Post, BlogClientError> {
+        let mut client = self.client.clone();
+
+        let response = client
+            .patch_post(
+                PatchPostRequest {
+                    post_id: id,
+                    title: fields.title,
+                    content: fields.content,
+                }
+                .into_request()
+                .with_token_auth(token)?
+                .prepared(&self.middlewares, "patch_post")?,
+            )
+            .await
+            .observed(&self.middlewares, "patch_post")?;
 
         let post = response
             .into_inner()
@@ -142,9 +252,11 @@ impl BlogApiClient for GrpcClient {
             .delete_post(
                 DeletePostRequest { post_id: id }
                     .into_request()
-                    .with_token_auth(token)?,
+                    .with_token_auth(token)?
+                    .prepared(&self.middlewares, "delete_post")?,
             )
-            .await?;
+            .await
+            .observed(&self.middlewares, "delete_post")?;
 
         Ok(())
     }
@@ -162,22 +274,226 @@ impl BlogApiClient for GrpcClient {
                     limit: limit.map(|l| l as i64),
                     offset: offset.map(|o| o as i64),
                 }
-                .into_request(),
+                .into_request()
+                .prepared(&self.middlewares, "get_posts")?,
+            )
+            .await
+            .observed(&self.middlewares, "get_posts")?
+            .into_inner();
+
+        into_posts_collection(response)
+    }
+
+    async fn search_posts(
+        &self,
+        query: String,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<PostsCollection, BlogClientError> {
+        let mut client = self.client.clone();
+
+        let response = client
+            .search_posts(
+                SearchPostsRequest {
+                    query,
+                    limit: limit.map(|l| l as i64),
+                    offset: offset.map(|o| o as i64),
+                }
+                .into_request()
+                .prepared(&self.middlewares, "search_posts")?,
+            )
+            .await
+            .observed(&self.middlewares, "search_posts")?
+            .into_inner();
+
+        into_posts_collection(response)
+    }
+
+    async fn subscribe_posts(&self) -> Result<PostEventStream, BlogClientError> {
+        let mut client = self.client.clone();
+
+        let events = client
+            .subscribe_posts(
+                SubscribePostsRequest {}
+                    .into_request()
+                    .prepared(&self.middlewares, "subscribe_posts")?,
             )
-            .await?
+            .await
+            .observed(&self.middlewares, "subscribe_posts")?
             .into_inner();
 
-        Ok(PostsCollection {
-            posts: response
-                .posts
-                .into_iter()
-                .map(into_domain_post)
-                .collect::<Result<Vec<_>, BlogClientError>>()?,
-            limit: response.limit as u64,
-            offset: response.offset as u64,
-            total_posts: response.total_posts_count as u64,
+        Ok(Box::pin(events.map(|event| {
+            event
+                .map_err(BlogClientError::from)
+                .and_then(into_post_event)
+        })))
+    }
+
+    async fn server_info(&self) -> Result<ServerInfo, BlogClientError> {
+        let mut client = self.client.clone();
+
+        let response = client
+            .get_server_info(
+                ().into_request()
+                    .prepared(&self.middlewares, "server_info")?,
+            )
+            .await
+            .observed(&self.middlewares, "server_info")?
+            .into_inner();
+
+        Ok(ServerInfo {
+            version: response.version,
+            api_version: response.api_version,
+            features: response.features,
+            read_only: response.read_only,
+            limits: response.limits.map(|limits| ServerLimits {
+                max_title_length: limits.max_title_length,
+                max_content_length: limits.max_content_length,
+                max_page_limit: limits.max_page_limit,
+                max_request_size: limits.max_request_size,
+                max_grpc_message_size: limits.max_grpc_message_size,
+            }),
         })
     }
+
+    async fn user_stats(&self, token: &str) -> Result<UserStats, BlogClientError> {
+        let mut client = self.client.clone();
+
+        let response = client
+            .get_user_stats(
+                ().into_request()
+                    .with_token_auth(token)?
+                    .prepared(&self.middlewares, "user_stats")?,
+            )
+            .await
+            .observed(&self.middlewares, "user_stats")?
+            .into_inner();
+
+        into_user_stats(response)
+    }
+
+    async fn get_posts_by_ids(
+        &self,
+        ids: &[i64],
+    ) -> Result<Vec<Result<Post, BlogClientError>>, BlogClientError> {
+        let mut client = self.client.clone();
+
+        let response = client
+            .batch_get_posts(
+                BatchGetPostsRequest {
+                    post_ids: ids.to_vec(),
+                }
+                .into_request()
+                .prepared(&self.middlewares, "get_posts_by_ids")?,
+            )
+            .await
+            .observed(&self.middlewares, "get_posts_by_ids")?
+            .into_inner();
+
+        Ok(response
+            .results
+            .into_iter()
+            .map(|item| match item.result {
+                Some(batch_post_result::Result::Post(post)) => into_domain_post(post),
+                Some(batch_post_result::Result::Error(error)) => Err(from_batch_error(error)),
+                None => Err(BlogClientError::GrpcFieldNotSet(String::from("result"))),
+            })
+            .collect())
+    }
+
+    async fn delete_posts(
+        &self,
+        token: &str,
+        ids: &[i64],
+    ) -> Result<Vec<Result<(), BlogClientError>>, BlogClientError> {
+        let mut client = self.client.clone();
+
+        let response = client
+            .batch_delete_posts(
+                BatchDeletePostsRequest {
+                    post_ids: ids.to_vec(),
+                }
+                .into_request()
+                .with_token_auth(token)?
+                .prepared(&self.middlewares, "delete_posts")?,
+            )
+            .await
+            .observed(&self.middlewares, "delete_posts")?
+            .into_inner();
+
+        Ok(response
+            .results
+            .into_iter()
+            .map(|item| {
+                item.error
+                    .map_or(Ok(()), |error| Err(from_batch_error(error)))
+            })
+            .collect())
+    }
+}
+
+/// Error of a single item of batch request, codes unknown to the client are kept with message
+fn from_batch_error(error: BatchError) -> BlogClientError {
+    error_from_reason(&error.code, &error.message).unwrap_or(
+        BlogClientError::UnexpectedGrpcResponse {
+            status_code: tonic::Code::Unknown as u16,
+            message: format!("{}: {}", error.code, error.message),
+        },
+    )
+}
+
+fn into_posts_collection(
+    response: blog_grpc_api::GetPostsResponse,
+) -> Result<PostsCollection, BlogClientError> {
+    Ok(PostsCollection {
+        posts: response
+            .posts
+            .into_iter()
+            .map(into_domain_post)
+            .collect::<Result<Vec<_>, BlogClientError>>()?,
+        limit: response.limit as u64,
+        offset: response.offset as u64,
+        total_posts: response.total_posts_count as u64,
+    })
+}
+
+fn into_post_event(event: blog_grpc_api::PostEvent) -> Result<PostEvent, BlogClientError> {
+    let kind = event.kind();
+    if kind == PostEventKind::Deleted {
+        return Ok(PostEvent::Deleted { id: event.post_id });
+    }
+
+    let post = event
+        .post
+        .ok_or_else(|| BlogClientError::GrpcFieldNotSet(String::from("post")))?;
+    let post = into_domain_post(post)?;
+
+    Ok(match kind {
+        PostEventKind::Created => PostEvent::Created(post),
+        _ => PostEvent::Updated(post),
+    })
+}
+
+fn into_user_stats(stats: blog_grpc_api::UserStats) -> Result<UserStats, BlogClientError> {
+    Ok(UserStats {
+        user_id: stats.user_id,
+        username: stats.username,
+        posts: stats.posts as u64,
+        last_post_at: stats.last_post_at.map(timestamp_to_datetime).transpose()?,
+        posts_per_day: stats
+            .posts_per_day
+            .into_iter()
+            .map(|daily| {
+                Ok(DailyPosts {
+                    day: daily
+                        .day
+                        .parse()
+                        .map_err(|_| BlogClientError::IncorrectDate(daily.day))?,
+                    posts: daily.posts as u64,
+                })
+            })
+            .collect::<Result<_, BlogClientError>>()?,
+    })
 }
 
 fn into_domain_post(post: blog_grpc_api::Post) -> Result<Post, BlogClientError> {
@@ -186,13 +502,23 @@ fn into_domain_post(post: blog_grpc_api::Post) -> Result<Post, BlogClientError>
         title: post.title,
         content: post.content,
         author_id: post.author_id,
-        created_at: timestamp_to_datetime(post.created_at)?,
-        updated_at: timestamp_to_datetime(post.updated_at)?,
+        author_username: post.author_username,
+        created_at: post
+            .created_at
+            .ok_or_else(|| BlogClientError::GrpcFieldNotSet(String::from("created_at")))
+            .and_then(timestamp_to_datetime)?,
+        updated_at: post
+            .updated_at
+            .ok_or_else(|| BlogClientError::GrpcFieldNotSet(String::from("updated_at")))
+            .and_then(timestamp_to_datetime)?,
     })
 }
 
-fn timestamp_to_datetime(ts: i64) -> Result<DateTime<Utc>, BlogClientError> {
-    DateTime::from_timestamp_millis(ts).ok_or_else(|| BlogClientError::IncorrectTimestamp(ts))
+/// Timestamp outside of years 1-9999 supported by protobuf is reported as incorrect date
+fn timestamp_to_datetime(ts: Timestamp) -> Result<DateTime<Utc>, BlogClientError> {
+    SystemTime::try_from(ts)
+        .map(DateTime::<Utc>::from)
+        .map_err(|e| BlogClientError::IncorrectDate(e.to_string()))
 }
 
 trait WithTokenAuth {
@@ -211,3 +537,50 @@ impl<T> WithTokenAuth for Request<T> {
         Ok(self)
     }
 }
+
+trait Prepared {
+    fn prepared(
+        self,
+        middlewares: &Middlewares,
+        operation: &'static str,
+    ) -> Result<Self, BlogClientError>
+    where
+        Self: Sized;
+}
+
+impl<T> Prepared for Request<T> {
+    fn prepared(
+        mut self,
+        middlewares: &Middlewares,
+        operation: &'static str,
+    ) -> Result<Self, BlogClientError> {
+        let mut headers = std::mem::take(self.metadata_mut()).into_headers();
+        middlewares.on_request(operation, None, &mut headers, None)?;
+        *self.metadata_mut() = MetadataMap::from_headers(headers);
+
+        if let Some(timeout) = current_timeout() {
+            self.set_timeout(timeout);
+        }
+
+        Ok(self)
+    }
+}
+
+trait Observed {
+    fn observed(self, middlewares: &Middlewares, operation: &'static str) -> Self;
+}
+
+impl<T> Observed for Result<Response<T>, Status> {
+    fn observed(self, middlewares: &Middlewares, operation: &'static str) -> Self {
+        let (code, metadata) = match &self {
+            Ok(response) => (tonic::Code::Ok, response.metadata()),
+            Err(status) => (status.code(), status.metadata()),
+        };
+        middlewares.on_response(
+            operation,
+            ResponseStatus::Grpc(code),
+            &metadata.clone().into_headers(),
+        );
+        self
+    }
+}
@@ -1,31 +1,215 @@
 use std::{collections::HashMap, time::Duration};
 
-use reqwest::{Client, StatusCode, Url};
-use serde::{Deserialize, Serialize};
+use blog_types::{
+    AuthResponse, CSRF_HEADER, CreatePostRequest, ErrorResponse, LoginRequest, RegisterRequest,
+    SearchPostsQuery, UpdatePostRequest,
+};
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use reqwest::{
+    Client, Request, RequestBuilder, Response, StatusCode, Url,
+    header::{ACCEPT, HeaderMap, HeaderName, HeaderValue, RETRY_AFTER},
+};
 
 use crate::{
-    api_client::BlogApiClient,
-    blog_client::{Post, PostsCollection},
+    api_client::{BlogApiClient, PostEventStream},
+    blog_client::{Post, PostEvent, PostsCollection, ServerInfo, UpdatePostFields, UserStats},
+    builder::ConnectionOptions,
     error::BlogClientError,
+    middleware::{Middlewares, ResponseStatus},
+    options::current_timeout,
+    sse, timer,
 };
 
+#[cfg(feature = "debug-logging")]
+use crate::debug_log;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{builder::ProxyMode, etag_cache::EtagCache};
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::{Method, header::IF_NONE_MATCH};
+
 pub(crate) struct HttpClient {
-    base_url: Url,
+    /// Server address with API path prefix, ending with `/`
+    api_url: Url,
     client: Client,
+    request_timeout: Duration,
+    rate_limit_budget: Duration,
+    middlewares: Middlewares,
+    cookie_session: bool,
+    /// Browser validates cached responses itself, so the cache is used on native targets only
+    #[cfg(not(target_arch = "wasm32"))]
+    etag_cache: Option<EtagCache>,
 }
 
 impl HttpClient {
-    pub(crate) fn new(base_url: &str) -> Result<Self, BlogClientError> {
-        let base_url = Url::parse(base_url)?;
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(10))
-            .build()?;
+    pub(crate) fn new(
+        base_url: &str,
+        options: &ConnectionOptions,
+    ) -> Result<Self, BlogClientError> {
+        let prefix = options.api_prefix.trim_matches('/');
+        let api_path = if prefix.is_empty() {
+            String::from("/")
+        } else {
+            format!("/{prefix}/")
+        };
+        let api_url = Url::parse(base_url)?.join(&api_path)?;
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &options.default_headers {
+            let name = HeaderName::try_from(name.as_str())
+                .map_err(|_| BlogClientError::InvalidHeader(name.clone()))?;
+            let value = HeaderValue::try_from(value.as_str())
+                .map_err(|_| BlogClientError::InvalidHeader(name.to_string()))?;
+            headers.insert(name, value);
+        }
+
+        let mut builder = reqwest::Client::builder().default_headers(headers);
+
+        if let Some(user_agent) = &options.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            builder = builder
+                .connect_timeout(options.connect_timeout)
+                .tcp_keepalive(options.keepalive)
+                .gzip(options.compression)
+                .deflate(options.compression);
+
+            builder = match &options.proxy {
+                ProxyMode::System => builder,
+                ProxyMode::Url(url) => builder.proxy(reqwest::Proxy::all(url)?),
+                ProxyMode::Disabled => builder.no_proxy(),
+            };
+        }
+
+        let client = builder.build()?;
+
+        Ok(Self {
+            api_url,
+            client,
+            request_timeout: options.request_timeout,
+            rate_limit_budget: options.rate_limit_budget,
+            middlewares: options.middlewares.clone(),
+            cookie_session: options.cookie_session,
+            #[cfg(not(target_arch = "wasm32"))]
+            etag_cache: options.etag_cache.then(EtagCache::default),
+        })
+    }
+
+    /// URL of API endpoint, `path` is relative to API path prefix
+    fn endpoint(&self, path: &str) -> Result<Url, BlogClientError> {
+        Ok(self.api_url.join(path)?)
+    }
 
-        Ok(Self { base_url, client })
+    /// Attaches bearer token, or CSRF token of a cookie session
+    fn authorized(&self, request: RequestBuilder, token: &str) -> RequestBuilder {
+        if self.cookie_session {
+            request.header(CSRF_HEADER, token)
+        } else {
+            request.bearer_auth(token)
+        }
+    }
+
+    /// Sends request with timeout of the current call or the default one
+    async fn send(
+        &self,
+        operation: &'static str,
+        request: RequestBuilder,
+    ) -> Result<Response, BlogClientError> {
+        #[cfg(target_arch = "wasm32")]
+        let request = if self.cookie_session {
+            request.fetch_credentials_include()
+        } else {
+            request
+        };
+        let mut request = request.build()?;
+        *request.timeout_mut() = Some(current_timeout().unwrap_or(self.request_timeout));
+
+        self.send_request(operation, request).await
+    }
+
+    /// Sends built request, repeating it after `429 Too Many Requests` while
+    /// the delay from `Retry-After` header fits into rate limit budget
+    async fn send_request(
+        &self,
+        operation: &'static str,
+        mut request: Request,
+    ) -> Result<Response, BlogClientError> {
+        let mut budget = self.rate_limit_budget;
+        loop {
+            let next_request = request.try_clone();
+            let response = self.execute(operation, request).await?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS {
+                return Ok(response);
+            }
+
+            match (retry_after(response.headers()), next_request) {
+                (Some(delay), Some(next_request)) if delay <= budget => {
+                    budget -= delay;
+                    timer::sleep(delay).await;
+                    request = next_request;
+                }
+                _ => return Ok(response),
+            }
+        }
+    }
+
+    /// Executes request once passing it through registered middlewares
+    async fn execute(
+        &self,
+        operation: &'static str,
+        mut request: Request,
+    ) -> Result<Response, BlogClientError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let cache = self
+            .etag_cache
+            .as_ref()
+            .filter(|_| request.method() == Method::GET)
+            .map(|cache| (cache, request.url().to_string()));
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some((cache, url)) = &cache
+            && let Some(etag) = cache.etag(url)
+        {
+            request.headers_mut().insert(IF_NONE_MATCH, etag);
+        }
+
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(<[u8]>::to_vec);
+        let (method, url) = (request.method().clone(), request.url().clone());
+        self.middlewares.on_request(
+            operation,
+            Some((&method, &url)),
+            request.headers_mut(),
+            body.as_deref(),
+        )?;
+        #[cfg(feature = "debug-logging")]
+        debug_log::log_request(operation, &request, body.as_deref());
+
+        let response = self.client.execute(request).await?;
+
+        self.middlewares.on_response(
+            operation,
+            ResponseStatus::Http(response.status().as_u16()),
+            response.headers(),
+        );
+        #[cfg(feature = "debug-logging")]
+        let response = debug_log::log_response(operation, response).await?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some((cache, url)) = cache {
+            return cache.process(url, response).await;
+        }
+
+        Ok(response)
     }
 }
 
-#[async_trait::async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
 impl BlogApiClient for HttpClient {
     async fn register(
         &self,
@@ -33,47 +217,45 @@ impl BlogApiClient for HttpClient {
         email: String,
         password: String,
     ) -> Result<String, BlogClientError> {
-        let url = self.base_url.join("/api/auth/register")?;
+        let url = self.endpoint("auth/register")?;
 
-        let params = CreateUserParams {
+        let params = RegisterRequest {
             username,
             email,
             password,
         };
 
-        let response = self.client.post(url).json(&params).send().await?;
+        let response = self
+            .send("register", self.client.post(url).json(&params))
+            .await?;
 
         match response.status() {
             StatusCode::CREATED => {
-                let user_and_token: UserAndToken = response.json().await?;
+                let auth: AuthResponse = response.json().await?;
 
-                Ok(user_and_token.token)
+                Ok(auth.token)
             }
             StatusCode::CONFLICT => Err(BlogClientError::UserAlreadyExists),
-            other => Err(BlogClientError::UnexpectedHttpResponse {
-                code: other.as_u16(),
-                message: response.text().await?,
-            }),
+            _ => Err(error_from_response(response).await),
         }
     }
 
     async fn login(&self, username: String, password: String) -> Result<String, BlogClientError> {
-        let url = self.base_url.join("/api/auth/login")?;
+        let url = self.endpoint("auth/login")?;
 
-        let params = LoginParams { username, password };
+        let params = LoginRequest { username, password };
 
-        let response = self.client.post(url).json(&params).send().await?;
+        let response = self
+            .send("login", self.client.post(url).json(&params))
+            .await?;
         match response.status() {
             StatusCode::OK => {
-                let user_and_token: UserAndToken = response.json().await?;
+                let auth: AuthResponse = response.json().await?;
 
-                Ok(user_and_token.token)
+                Ok(auth.token)
             }
             StatusCode::UNAUTHORIZED => Err(BlogClientError::InvalidCredentials),
-            other => Err(BlogClientError::UnexpectedHttpResponse {
-                code: other.as_u16(),
-                message: response.text().await?,
-            }),
+            _ => Err(error_from_response(response).await),
         }
     }
 
@@ -83,16 +265,15 @@ impl BlogApiClient for HttpClient {
         title: String,
         content: String,
     ) -> Result<Post, BlogClientError> {
-        let url = self.base_url.join("/api/posts")?;
+        let url = self.endpoint("posts")?;
 
-        let params = CreatePostParams { title, content };
+        let params = CreatePostRequest { title, content };
 
         let response = self
-            .client
-            .post(url)
-            .bearer_auth(token)
-            .json(&params)
-            .send()
+            .send(
+                "create_post",
+                self.authorized(self.client.post(url), token).json(&params),
+            )
             .await?;
         match response.status() {
             StatusCode::CREATED => {
@@ -101,17 +282,14 @@ impl BlogApiClient for HttpClient {
                 Ok(post)
             }
             StatusCode::UNAUTHORIZED => Err(BlogClientError::InvalidToken),
-            other => Err(BlogClientError::UnexpectedHttpResponse {
-                code: other.as_u16(),
-                message: response.text().await?,
-            }),
+            _ => Err(error_from_response(response).await),
         }
     }
 
     async fn get_post(&self, id: i64) -> Result<Post, BlogClientError> {
-        let url = self.base_url.join(format!("/api/posts/{id}").as_str())?;
+        let url = self.endpoint(&format!("posts/{id}"))?;
 
-        let response = self.client.get(url).send().await?;
+        let response = self.send("get_post", self.client.get(url)).await?;
         match response.status() {
             StatusCode::OK => {
                 let post: Post = response.json().await?;
@@ -119,10 +297,7 @@ impl BlogApiClient for HttpClient {
                 Ok(post)
             }
             StatusCode::NOT_FOUND => Err(BlogClientError::NotFound),
-            other => Err(BlogClientError::UnexpectedHttpResponse {
-                code: other.as_u16(),
-                message: response.text().await?,
-            }),
+            _ => Err(error_from_response(response).await),
         }
     }
 
@@ -133,16 +308,15 @@ impl BlogApiClient for HttpClient {
         title: String,
         content: String,
     ) -> Result<Post, BlogClientError> {
-        let url = self.base_url.join(format!("/api/posts/{id}").as_str())?;
+        let url = self.endpoint(&format!("posts/{id}"))?;
 
-        let params = UpdatePostParams { title, content };
+        let params = UpdatePostRequest { title, content };
 
         let response = self
-            .client
-            .put(url)
-            .bearer_auth(token)
-            .json(&params)
-            .send()
+            .send(
+                "update_post",
+                self.authorized(self.client.put(url), token).json(&params),
+            )
             .await?;
 
         match response.status() {
@@ -153,26 +327,55 @@ impl BlogApiClient for HttpClient {
             }
             StatusCode::UNAUTHORIZED => Err(BlogClientError::InvalidToken),
             StatusCode::FORBIDDEN => Err(BlogClientError::Forbidden),
-            other => Err(BlogClientError::UnexpectedHttpResponse {
-                code: other.as_u16(),
-                message: response.text().await?,
-            }),
+            StatusCode::NOT_FOUND => Err(BlogClientError::NotFound),
+            _ => Err(error_from_response(response).await),
+        }
+    }
+
+    async fn patch_post(
+        &self,
+        token: &str,
+        id: i64,
+        fields: UpdatePostFields,
+    ) -> Result<Post, BlogClientError> {
+        let url = self.endpoint(&format!("posts/{id}"))?;
+
+        let response = self
+            .send(
+                "patch_post",
+                self.authorized(self.client.patch(url), token).json(&fields),
+            )
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let post: Post = response.json().await?;
+
+                Ok(post)
+            }
+            StatusCode::UNAUTHORIZED => Err(BlogClientError::InvalidToken),
+            StatusCode::FORBIDDEN => Err(BlogClientError::Forbidden),
+            StatusCode::NOT_FOUND => Err(BlogClientError::NotFound),
+            _ => Err(error_from_response(response).await),
         }
     }
 
     async fn delete_post(&self, token: &str, id: i64) -> Result<(), BlogClientError> {
-        let url = self.base_url.join(format!("/api/posts/{id}").as_str())?;
+        let url = self.endpoint(&format!("posts/{id}"))?;
 
-        let response = self.client.delete(url).bearer_auth(token).send().await?;
+        let response = self
+            .send(
+                "delete_post",
+                self.authorized(self.client.delete(url), token),
+            )
+            .await?;
 
         match response.status() {
             StatusCode::NO_CONTENT => Ok(()),
             StatusCode::UNAUTHORIZED => Err(BlogClientError::InvalidToken),
             StatusCode::FORBIDDEN => Err(BlogClientError::Forbidden),
-            other => Err(BlogClientError::UnexpectedHttpResponse {
-                code: other.as_u16(),
-                message: response.text().await?,
-            }),
+            StatusCode::NOT_FOUND => Err(BlogClientError::NotFound),
+            _ => Err(error_from_response(response).await),
         }
     }
 
@@ -181,7 +384,7 @@ impl BlogApiClient for HttpClient {
         limit: Option<u64>,
         offset: Option<u64>,
     ) -> Result<PostsCollection, BlogClientError> {
-        let url = self.base_url.join("/api/posts")?;
+        let url = self.endpoint("posts")?;
 
         let mut query = HashMap::new();
         if let Some(limit) = limit {
@@ -192,7 +395,9 @@ impl BlogApiClient for HttpClient {
             query.insert("offset", offset);
         }
 
-        let response = self.client.get(url).query(&query).send().await?;
+        let response = self
+            .send("get_posts", self.client.get(url).query(&query))
+            .await?;
 
         match response.status() {
             StatusCode::OK => {
@@ -200,40 +405,127 @@ impl BlogApiClient for HttpClient {
 
                 Ok(posts_response)
             }
-            other => Err(BlogClientError::UnexpectedHttpResponse {
-                code: other.as_u16(),
-                message: response.text().await?,
-            }),
+            _ => Err(error_from_response(response).await),
         }
     }
-}
 
-#[derive(Debug, Serialize)]
-struct CreateUserParams {
-    username: String,
-    email: String,
-    password: String,
-}
+    async fn search_posts(
+        &self,
+        query: String,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<PostsCollection, BlogClientError> {
+        let url = self.endpoint("posts/search")?;
 
-#[derive(Debug, Serialize)]
-struct LoginParams {
-    username: String,
-    password: String,
-}
+        let params = SearchPostsQuery {
+            query,
+            limit,
+            offset,
+        };
+
+        let response = self
+            .send("search_posts", self.client.get(url).query(&params))
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let posts_response: PostsCollection = response.json().await?;
+
+                Ok(posts_response)
+            }
+            _ => Err(error_from_response(response).await),
+        }
+    }
+
+    async fn subscribe_posts(&self) -> Result<PostEventStream, BlogClientError> {
+        let url = self.endpoint("posts/events")?;
+
+        // events arrive while the connection is open, so request timeout is not applied
+        let request = self
+            .client
+            .get(url)
+            .header(ACCEPT, "text/event-stream")
+            .build()?;
+        let response = self.send_request("subscribe_posts", request).await?;
 
-#[derive(Debug, Deserialize)]
-struct UserAndToken {
-    token: String,
+        match response.status() {
+            StatusCode::OK => {
+                let events = sse::event_data(Box::pin(response.bytes_stream())).and_then(
+                    |data| async move {
+                        serde_json::from_str::<PostEvent>(&data)
+                            .map_err(BlogClientError::InvalidEvent)
+                    },
+                );
+
+                Ok(Box::pin(events))
+            }
+            _ => Err(error_from_response(response).await),
+        }
+    }
+
+    async fn server_info(&self) -> Result<ServerInfo, BlogClientError> {
+        let url = self.endpoint("info")?;
+
+        let response = self.send("server_info", self.client.get(url)).await?;
+        match response.status() {
+            StatusCode::OK => {
+                let info: ServerInfo = response.json().await?;
+
+                Ok(info)
+            }
+            _ => Err(error_from_response(response).await),
+        }
+    }
+
+    async fn user_stats(&self, token: &str) -> Result<UserStats, BlogClientError> {
+        let url = self.endpoint("stats/me")?;
+
+        let response = self
+            .send("user_stats", self.authorized(self.client.get(url), token))
+            .await?;
+        match response.status() {
+            StatusCode::OK => {
+                let stats: UserStats = response.json().await?;
+
+                Ok(stats)
+            }
+            StatusCode::UNAUTHORIZED => Err(BlogClientError::InvalidToken),
+            _ => Err(error_from_response(response).await),
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
-struct CreatePostParams {
-    title: String,
-    content: String,
+/// Converts unexpected response into error, parsing error body of validation failures
+async fn error_from_response(response: Response) -> BlogClientError {
+    let code = response.status().as_u16();
+    if response.status() == StatusCode::TOO_MANY_REQUESTS {
+        return BlogClientError::RateLimited {
+            retry_after: retry_after(response.headers()),
+        };
+    }
+
+    let message = match response.text().await {
+        Ok(message) => message,
+        Err(e) => return e.into(),
+    };
+
+    match serde_json::from_str::<ErrorResponse>(&message) {
+        Ok(body) if !body.fields.is_empty() => BlogClientError::Validation {
+            message: body.error,
+            fields: body.fields,
+        },
+        Ok(body) if body.code == "MAINTENANCE" => BlogClientError::ReadOnly(body.error),
+        _ => BlogClientError::UnexpectedHttpResponse { code, message },
+    }
 }
 
-#[derive(Debug, Serialize)]
-struct UpdatePostParams {
-    title: String,
-    content: String,
+/// Delay from `Retry-After` header given either in seconds or as HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.to_utc() - Utc::now()).to_std().unwrap_or_default())
 }
@@ -3,16 +3,48 @@
 #![deny(unreachable_pub)]
 #![warn(missing_docs)]
 
-mod api_client;
+pub mod api_client;
+#[cfg(feature = "grpc")]
+mod auto_client;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod blog_client;
+pub mod builder;
+#[cfg(feature = "debug-logging")]
+mod debug_log;
 pub mod error;
+#[cfg(not(target_arch = "wasm32"))]
+mod etag_cache;
+#[cfg(feature = "grpc")]
 mod grpc_client;
 mod http_client;
+pub mod middleware;
+#[cfg(feature = "test-util")]
+pub mod mock;
+pub mod options;
+pub mod refresh;
+pub mod retry;
+mod sse;
+mod timer;
+pub mod token_store;
+
+/// Version of server API this library is built for, see [`blog_client::ServerInfo`]
+pub const API_VERSION: u32 = 2;
 
 /// Available trqnsports for blog clients
 pub enum Transport {
     /// Http client with server address
     Http(String),
     /// Grpc client with server address
+    #[cfg(feature = "grpc")]
     Grpc(String),
+    /// gRPC if server answers it on the address, HTTP otherwise
+    ///
+    /// Client switches to the other protocol if the active one becomes unreachable.
+    /// Registration, login and creation are repeated over the other protocol only if
+    /// they failed before being sent, otherwise the error is returned
+    #[cfg(feature = "grpc")]
+    Auto(String),
+    /// User-provided implementation of [`api_client::BlogApiClient`]
+    Custom(api_client::CustomClient),
 }
@@ -0,0 +1,100 @@
+//! Hook for inspecting and modifying requests sent by the client
+//!
+//! Middleware is called for both HTTP and gRPC transports, gRPC metadata is exposed
+//! as HTTP headers. Register it with [`crate::builder::BlogClientBuilder::middleware`]
+
+use std::sync::Arc;
+
+pub use reqwest::{Method, Url, header::HeaderMap};
+
+use crate::error::BlogClientError;
+
+/// Outgoing request visible to middleware
+pub struct RequestParts<'a> {
+    /// Name of the called client operation, e.g. `get_post`
+    pub operation: &'static str,
+    /// HTTP method and URL, `None` for gRPC requests
+    pub target: Option<(&'a Method, &'a Url)>,
+    /// Request headers (gRPC metadata), can be modified
+    pub headers: &'a mut HeaderMap,
+    /// Serialized request body, available for HTTP requests with body only
+    pub body: Option<&'a [u8]>,
+}
+
+/// Response status reported by transport
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseStatus {
+    /// HTTP status code
+    Http(u16),
+    /// gRPC status code
+    #[cfg(feature = "grpc")]
+    Grpc(tonic::Code),
+}
+
+/// Received response visible to middleware
+pub struct ResponseParts<'a> {
+    /// Name of the called client operation, e.g. `get_post`
+    pub operation: &'static str,
+    /// Response status
+    pub status: ResponseStatus,
+    /// Response headers (gRPC metadata)
+    pub headers: &'a HeaderMap,
+}
+
+/// Request interceptor, e.g. for adding gateway headers or signing requests
+///
+/// Middlewares are called in registration order, requests which fail before
+/// reaching the server (connection errors) are not reported to [`ClientMiddleware::on_response`]
+pub trait ClientMiddleware: Send + Sync {
+    /// Called before request is sent, returned error aborts the request
+    fn on_request(&self, _request: &mut RequestParts<'_>) -> Result<(), BlogClientError> {
+        Ok(())
+    }
+
+    /// Called after response is received
+    fn on_response(&self, _response: &ResponseParts<'_>) {}
+}
+
+/// Ordered list of registered middlewares
+#[derive(Clone, Default)]
+pub(crate) struct Middlewares(Vec<Arc<dyn ClientMiddleware>>);
+
+impl Middlewares {
+    pub(crate) fn push(&mut self, middleware: Arc<dyn ClientMiddleware>) {
+        self.0.push(middleware);
+    }
+
+    pub(crate) fn on_request(
+        &self,
+        operation: &'static str,
+        target: Option<(&Method, &Url)>,
+        headers: &mut HeaderMap,
+        body: Option<&[u8]>,
+    ) -> Result<(), BlogClientError> {
+        for middleware in &self.0 {
+            middleware.on_request(&mut RequestParts {
+                operation,
+                target,
+                headers,
+                body,
+            })?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn on_response(
+        &self,
+        operation: &'static str,
+        status: ResponseStatus,
+        headers: &HeaderMap,
+    ) {
+        let response = ResponseParts {
+            operation,
+            status,
+            headers,
+        };
+        for middleware in &self.0 {
+            middleware.on_response(&response);
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! In-memory implementation of blog API for tests without a server
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use chrono::{Days, Utc};
+use futures_util::stream;
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use crate::{
+    api_client::{BlogApiClient, ClientType, PostEventStream},
+    blog_client::{
+        BlogClient, DailyPosts, Post, PostEvent, PostsCollection, ServerInfo, UpdatePostFields,
+        UserStats,
+    },
+    builder::ClientSettings,
+    error::BlogClientError,
+};
+
+const DEFAULT_LIMIT: u64 = 10;
+/// Number of days in [`UserStats::posts_per_day`]
+const STATS_DAYS: u64 = 30;
+
+/// Mock blog backend keeping users and posts in memory
+///
+/// Clones share the same state, so a test can keep one handle to inspect posts
+/// and script failures while another is used by [`BlogClient`]
+///
+/// ```
+/// # use blog_client::{error::BlogClientError, mock::MockBlogClient};
+/// # async fn example() {
+/// let mock = MockBlogClient::new();
+/// let client = mock.client();
+///
+/// mock.fail_next("get_post", BlogClientError::NotFound);
+/// assert!(client.get_post(1).await.is_err());
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct MockBlogClient {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    users: Vec<MockUser>,
+    posts: BTreeMap<i64, Post>,
+    tokens: HashMap<String, i64>,
+    next_post_id: i64,
+    failures: HashMap<&'static str, VecDeque<BlogClientError>>,
+    subscribers: Vec<UnboundedSender<PostEvent>>,
+}
+
+struct MockUser {
+    id: i64,
+    username: String,
+    email: String,
+    password: String,
+}
+
+impl MockBlogClient {
+    /// Creates mock with no users and posts
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates [`BlogClient`] backed by this mock
+    pub fn client(&self) -> BlogClient {
+        BlogClient::from_parts(
+            ClientType::Custom(Box::new(self.clone())),
+            ClientSettings::default(),
+        )
+        .expect("client without token store is always created")
+    }
+
+    /// Makes the next call of `operation` (e.g. `"get_post"`) fail with `error`
+    ///
+    /// Failures for the same operation are returned in the order they were added
+    pub fn fail_next(&self, operation: &'static str, error: BlogClientError) {
+        self.state()
+            .failures
+            .entry(operation)
+            .or_default()
+            .push_back(error);
+    }
+
+    /// Returns all stored posts ordered by id
+    pub fn posts(&self) -> Vec<Post> {
+        self.state().posts.values().cloned().collect()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks state and returns scripted failure for the operation if any
+    fn begin(
+        &self,
+        operation: &'static str,
+    ) -> Result<std::sync::MutexGuard<'_, MockState>, BlogClientError> {
+        let mut state = self.state();
+        match state
+            .failures
+            .get_mut(operation)
+            .and_then(VecDeque::pop_front)
+        {
+            Some(error) => Err(error),
+            None => Ok(state),
+        }
+    }
+}
+
+impl MockState {
+    fn publish(&mut self, event: PostEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    fn issue_token(&mut self, user_id: i64) -> String {
+        let token = format!("mock-token-{user_id}-{}", self.tokens.len());
+        self.tokens.insert(token.clone(), user_id);
+        token
+    }
+
+    fn user_id(&self, token: &str) -> Result<i64, BlogClientError> {
+        self.tokens
+            .get(token)
+            .copied()
+            .ok_or(BlogClientError::InvalidToken)
+    }
+
+    fn own_post(&mut self, token: &str, id: i64) -> Result<&mut Post, BlogClientError> {
+        let user_id = self.user_id(token)?;
+        let post = self.posts.get_mut(&id).ok_or(BlogClientError::NotFound)?;
+        if post.author_id != user_id {
+            return Err(BlogClientError::Forbidden);
+        }
+        Ok(post)
+    }
+
+    fn page<'a>(
+        posts: impl DoubleEndedIterator<Item = &'a Post>,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> PostsCollection {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+        let offset = offset.unwrap_or(0);
+        let posts: Vec<&Post> = posts.rev().collect();
+
+        PostsCollection {
+            total_posts: posts.len() as u64,
+            posts: posts
+                .into_iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .cloned()
+                .collect(),
+            limit,
+            offset,
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl BlogApiClient for MockBlogClient {
+    async fn register(
+        &self,
+        username: String,
+        email: String,
+        password: String,
+    ) -> Result<String, BlogClientError> {
+        let mut state = self.begin("register")?;
+
+        if state
+            .users
+            .iter()
+            .any(|user| user.username == username || user.email == email)
+        {
+            return Err(BlogClientError::UserAlreadyExists);
+        }
+
+        let id = state.users.len() as i64 + 1;
+        state.users.push(MockUser {
+            id,
+            username,
+            email,
+            password,
+        });
+
+        Ok(state.issue_token(id))
+    }
+
+    async fn login(&self, username: String, password: String) -> Result<String, BlogClientError> {
+        let mut state = self.begin("login")?;
+
+        let id = state
+            .users
+            .iter()
+            .find(|user| user.username == username && user.password == password)
+            .map(|user| user.id)
+            .ok_or(BlogClientError::InvalidCredentials)?;
+
+        Ok(state.issue_token(id))
+    }
+
+    async fn create_post(
+        &self,
+        token: &str,
+        title: String,
+        content: String,
+    ) -> Result<Post, BlogClientError> {
+        let mut state = self.begin("create_post")?;
+        let author_id = state.user_id(token)?;
+        let author_username = state
+            .users
+            .iter()
+            .find(|user| user.id == author_id)
+            .map(|user| user.username.clone());
+
+        state.next_post_id += 1;
+        let now = Utc::now();
+        let post = Post {
+            id: state.next_post_id,
+            title,
+            content,
+            author_id,
+            author_username,
+            created_at: now,
+            updated_at: now,
+        };
+        state.posts.insert(post.id, post.clone());
+        state.publish(PostEvent::Created(post.clone()));
+
+        Ok(post)
+    }
+
+    async fn get_post(&self, id: i64) -> Result<Post, BlogClientError> {
+        let state = self.begin("get_post")?;
+
+        state
+            .posts
+            .get(&id)
+            .cloned()
+            .ok_or(BlogClientError::NotFound)
+    }
+
+    async fn update_post(
+        &self,
+        token: &str,
+        id: i64,
+        title: String,
+        content: String,
+    ) -> Result<Post, BlogClientError> {
+        let mut state = self.begin("update_post")?;
+        let post = state.own_post(token, id)?;
+
+        post.title = title;
+        post.content = content;
+        post.updated_at = Utc::now();
+
+        let post = post.clone();
+        state.publish(PostEvent::Updated(post.clone()));
+        Ok(post)
+    }
+
+    async fn patch_post(
+        &self,
+        token: &str,
+        id: i64,
+        fields: UpdatePostFields,
+    ) -> Result<Post, BlogClientError> {
+        let mut state = self.begin("patch_post")?;
+        let post = state.own_post(token, id)?;
+
+        if fields.title.is_none() && fields.content.is_none() {
+            return Ok(post.clone());
+        }
+        if let Some(title) = fields.title {
+            post.title = title;
+        }
+        if let Some(content) = fields.content {
+            post.content = content;
+        }
+        post.updated_at = Utc::now();
+
+        let post = post.clone();
+        state.publish(PostEvent::Updated(post.clone()));
+        Ok(post)
+    }
+
+    async fn delete_post(&self, token: &str, id: i64) -> Result<(), BlogClientError> {
+        let mut state = self.begin("delete_post")?;
+        state.own_post(token, id)?;
+        state.posts.remove(&id);
+        state.publish(PostEvent::Deleted { id });
+
+        Ok(())
+    }
+
+    async fn get_posts(
+        &self,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<PostsCollection, BlogClientError> {
+        let state = self.begin("get_posts")?;
+
+        Ok(MockState::page(state.posts.values(), limit, offset))
+    }
+
+    async fn search_posts(
+        &self,
+        query: String,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<PostsCollection, BlogClientError> {
+        let state = self.begin("search_posts")?;
+
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        let matches = state.posts.values().filter(|post| {
+            let text = format!("{} {}", post.title, post.content).to_lowercase();
+            words.iter().all(|word| text.contains(word.as_str()))
+        });
+
+        Ok(MockState::page(matches, limit, offset))
+    }
+
+    async fn subscribe_posts(&self) -> Result<PostEventStream, BlogClientError> {
+        let mut state = self.begin("subscribe_posts")?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        state.subscribers.push(sender);
+
+        Ok(Box::pin(stream::unfold(receiver, |mut receiver| async {
+            let event = receiver.recv().await?;
+            Some((Ok(event), receiver))
+        })))
+    }
+
+    async fn server_info(&self) -> Result<ServerInfo, BlogClientError> {
+        let _state = self.begin("server_info")?;
+
+        Ok(ServerInfo {
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            api_version: crate::API_VERSION,
+            features: Vec::new(),
+            read_only: false,
+            limits: None,
+        })
+    }
+
+    async fn user_stats(&self, token: &str) -> Result<UserStats, BlogClientError> {
+        let state = self.begin("user_stats")?;
+        let user_id = state.user_id(token)?;
+        let username = state
+            .users
+            .iter()
+            .find(|user| user.id == user_id)
+            .map(|user| user.username.clone())
+            .ok_or(BlogClientError::InvalidToken)?;
+        let posts: Vec<&Post> = state
+            .posts
+            .values()
+            .filter(|post| post.author_id == user_id)
+            .collect();
+
+        let today = Utc::now().date_naive();
+        let posts_per_day = (0..STATS_DAYS)
+            .rev()
+            .map(|days_ago| {
+                let day = today - Days::new(days_ago);
+                DailyPosts {
+                    day,
+                    posts: posts
+                        .iter()
+                        .filter(|post| post.created_at.date_naive() == day)
+                        .count() as u64,
+                }
+            })
+            .collect();
+
+        Ok(UserStats {
+            user_id,
+            username,
+            posts: posts.len() as u64,
+            last_post_at: posts.iter().map(|post| post.created_at).max(),
+            posts_per_day,
+        })
+    }
+}
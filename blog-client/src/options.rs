@@ -0,0 +1,84 @@
+//! Per-request timeout and cancellation
+
+use std::{future::Future, time::Duration};
+
+pub use tokio_util::sync::CancellationToken;
+
+use crate::{error::BlogClientError, timer};
+
+tokio::task_local! {
+    static REQUEST_TIMEOUT: Option<Duration>;
+}
+
+/// Options applied to a single client call
+///
+/// ```
+/// # use std::time::Duration;
+/// # use blog_client::{blog_client::BlogClient, options::RequestOptions};
+/// # async fn example(client: BlogClient) {
+/// let post = RequestOptions::new()
+///     .timeout(Duration::from_secs(1))
+///     .run(client.get_post(1))
+///     .await;
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    timeout: Option<Duration>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl RequestOptions {
+    /// Options without timeout and cancellation, client-wide settings are used
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Timeout for the whole call including retries
+    ///
+    /// Also sent to server as request timeout (gRPC deadline)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Token which aborts the call when cancelled
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Runs client call with these options
+    ///
+    /// Returns [`BlogClientError::Timeout`] or [`BlogClientError::Cancelled`]
+    /// if the call did not finish in time or was cancelled
+    pub async fn run<T>(
+        &self,
+        request: impl Future<Output = Result<T, BlogClientError>>,
+    ) -> Result<T, BlogClientError> {
+        let request = REQUEST_TIMEOUT.scope(self.timeout, request);
+
+        let request = async {
+            match self.timeout {
+                Some(timeout) => timer::timeout(timeout, request)
+                    .await
+                    .ok_or(BlogClientError::Timeout)?,
+                None => request.await,
+            }
+        };
+
+        match &self.cancellation {
+            Some(token) => tokio::select! {
+                biased;
+                _ = token.cancelled() => Err(BlogClientError::Cancelled),
+                result = request => result,
+            },
+            None => request.await,
+        }
+    }
+}
+
+/// Timeout of the call running inside [`RequestOptions::run`]
+pub(crate) fn current_timeout() -> Option<Duration> {
+    REQUEST_TIMEOUT.try_with(|timeout| *timeout).ok().flatten()
+}
@@ -0,0 +1,66 @@
+//! Token refresh hook used when server rejects stored JWT token
+
+use std::future::Future;
+
+use crate::{api_client::BlogApiClient, error::BlogClientError};
+
+/// Source of a fresh JWT token
+///
+/// When a token-protected request fails with [`BlogClientError::InvalidToken`],
+/// [`crate::blog_client::BlogClient`] calls [`TokenRefresher::refresh`], stores the returned
+/// token and retries the request once
+///
+/// Implemented for async closures without arguments, e.g. `|| async { Ok(load_token()) }`
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait TokenRefresher: Send + Sync {
+    /// Returns a new token, `client` can be used to call the server
+    async fn refresh(
+        &self,
+        client: &(dyn BlogApiClient + Send + Sync),
+    ) -> Result<String, BlogClientError>;
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl<F, Fut> TokenRefresher for F
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<String, BlogClientError>> + Send,
+{
+    async fn refresh(
+        &self,
+        _client: &(dyn BlogApiClient + Send + Sync),
+    ) -> Result<String, BlogClientError> {
+        self().await
+    }
+}
+
+/// Refreshes token by logging in again with stored credentials
+pub struct LoginRefresher {
+    username: String,
+    password: String,
+}
+
+impl LoginRefresher {
+    /// Creates refresher for given credentials
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl TokenRefresher for LoginRefresher {
+    async fn refresh(
+        &self,
+        client: &(dyn BlogApiClient + Send + Sync),
+    ) -> Result<String, BlogClientError> {
+        client
+            .login(self.username.clone(), self.password.clone())
+            .await
+    }
+}
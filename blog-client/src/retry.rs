@@ -0,0 +1,117 @@
+//! Retry policy for idempotent requests
+
+use std::{
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use crate::{error::BlogClientError, timer};
+
+/// Exponential backoff settings used to retry idempotent requests
+///
+/// Only reads, updates and deletes are retried, and only on connection errors,
+/// timeouts and server-side (5xx / gRPC `UNAVAILABLE`, `INTERNAL`, `DEADLINE_EXCEEDED`) failures
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts including the first one, `1` disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound for a single delay
+    pub max_backoff: Duration,
+    /// Factor applied to the delay after every attempt
+    pub multiplier: f64,
+    /// Randomize every delay between zero and computed backoff
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+            multiplier: 2.0,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that makes exactly one attempt
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) async fn retry<T, F, Fut>(&self, mut operation: F) -> Result<T, BlogClientError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, BlogClientError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Err(e) if attempt < self.max_attempts && e.is_retryable() => {
+                    timer::sleep(self.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        // clamped in seconds before converting, `Duration::mul_f64` panics on overflow
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let seconds = (self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent))
+            .min(self.max_backoff.as_secs_f64());
+        let backoff = Duration::try_from_secs_f64(seconds).unwrap_or(self.max_backoff);
+
+        if self.jitter {
+            backoff.mul_f64(random_fraction())
+        } else {
+            backoff
+        }
+    }
+}
+
+/// Random value in `[0, 1)` without pulling in an RNG crate, good enough for jitter
+fn random_fraction() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            jitter: false,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn backoff_grows_up_to_maximum() {
+        let policy = policy();
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(10), policy.max_backoff);
+    }
+
+    #[test]
+    fn backoff_of_late_attempts_does_not_overflow() {
+        let policy = policy();
+
+        for attempt in [68, 1100, u32::MAX] {
+            assert_eq!(policy.backoff(attempt), policy.max_backoff);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Parser of `text/event-stream` (server-sent events) responses
+
+use futures_util::{Stream, StreamExt, stream};
+
+use crate::error::BlogClientError;
+
+/// Data of events received in the response body
+///
+/// Events without data, e.g. keep-alive comments, are skipped
+pub(crate) fn event_data<S, B>(body: S) -> impl Stream<Item = Result<String, BlogClientError>>
+where
+    S: Stream<Item = Result<B, reqwest::Error>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    stream::try_unfold((body, Vec::new()), |(mut body, mut buffer)| async move {
+        loop {
+            if let Some(data) = next_event_data(&mut buffer) {
+                return Ok(Some((data, (body, buffer))));
+            }
+
+            match body.next().await {
+                Some(chunk) => buffer.extend(chunk?.as_ref().iter().filter(|&&byte| byte != b'\r')),
+                None => return Ok::<_, BlogClientError>(None),
+            }
+        }
+    })
+}
+
+/// Removes complete events from the buffer until one with data is found
+fn next_event_data(buffer: &mut Vec<u8>) -> Option<String> {
+    while let Some(end) = buffer.windows(2).position(|window| window == b"\n\n") {
+        let event: Vec<u8> = buffer.drain(..end + 2).collect();
+        let event = String::from_utf8_lossy(&event);
+        let data: Vec<&str> = event
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(|data| data.strip_prefix(' ').unwrap_or(data))
+            .collect();
+
+        if !data.is_empty() {
+            return Some(data.join("\n"));
+        }
+    }
+
+    None
+}
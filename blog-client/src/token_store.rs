@@ -0,0 +1,102 @@
+//! Persistent storage for JWT token
+
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+};
+
+use crate::error::BlogClientError;
+
+/// Storage used by [`crate::blog_client::BlogClient`] to keep token between runs
+///
+/// Token is loaded when client is built, saved on [`crate::blog_client::BlogClient::set_token`]
+/// and automatic refresh, removed on [`crate::blog_client::BlogClient::clear_token`]
+pub trait TokenStore: Send + Sync {
+    /// Returns saved token if any
+    fn load(&self) -> Result<Option<String>, BlogClientError>;
+
+    /// Saves token replacing the previous one
+    fn save(&self, token: &str) -> Result<(), BlogClientError>;
+
+    /// Removes saved token
+    fn clear(&self) -> Result<(), BlogClientError>;
+}
+
+/// Token kept in memory only, lost when the store is dropped
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+    token: Mutex<Option<String>>,
+}
+
+impl MemoryTokenStore {
+    /// Creates empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TokenStore for MemoryTokenStore {
+    fn load(&self) -> Result<Option<String>, BlogClientError> {
+        Ok(self
+            .token
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone())
+    }
+
+    fn save(&self, token: &str) -> Result<(), BlogClientError> {
+        *self.token.lock().unwrap_or_else(PoisonError::into_inner) = Some(token.to_string());
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), BlogClientError> {
+        *self.token.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        Ok(())
+    }
+}
+
+/// Token saved in a plain text file, readable by the current user only on unix
+#[derive(Debug, Clone)]
+pub struct FileTokenStore {
+    path: PathBuf,
+}
+
+impl FileTokenStore {
+    /// Creates store for given file, the file is created on first save
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl TokenStore for FileTokenStore {
+    fn load(&self) -> Result<Option<String>, BlogClientError> {
+        match fs::read_to_string(&self.path) {
+            Ok(token) => {
+                let token = token.trim();
+                Ok((!token.is_empty()).then(|| token.to_string()))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, token: &str) -> Result<(), BlogClientError> {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        options.open(&self.path)?.write_all(token.as_bytes())?;
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), BlogClientError> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,577 @@
+//! Requests of HTTP transport and mapping of server responses, checked against a mock server
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::time::Duration;
+
+use blog_client::{
+    Transport,
+    blog_client::{BlogClient, UpdatePostFields},
+    error::BlogClientError,
+    retry::RetryPolicy,
+};
+use serde_json::{Value, json};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_json, header, method, path, query_param},
+};
+
+const TOKEN: &str = "test-token";
+
+async fn client(server: &MockServer) -> BlogClient {
+    BlogClient::new(Transport::Http(server.uri()))
+        .await
+        .unwrap()
+}
+
+async fn authorized_client(server: &MockServer) -> BlogClient {
+    let client = client(server).await;
+    client.set_token(String::from(TOKEN)).unwrap();
+    client
+}
+
+/// Mock answering any request with given status and body
+async fn respond(server: &MockServer, status: u16, body: Value) {
+    Mock::given(wiremock::matchers::any())
+        .respond_with(ResponseTemplate::new(status).set_body_json(body))
+        .mount(server)
+        .await;
+}
+
+async fn respond_raw(server: &MockServer, status: u16, body: &str) {
+    Mock::given(wiremock::matchers::any())
+        .respond_with(ResponseTemplate::new(status).set_body_string(body))
+        .mount(server)
+        .await;
+}
+
+fn post_json(id: i64) -> Value {
+    json!({
+        "id": id,
+        "title": "title",
+        "content": "content",
+        "author_id": 1,
+        "author_username": "alice",
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-02T00:00:00Z",
+    })
+}
+
+fn auth_json() -> Value {
+    json!({
+        "token": TOKEN,
+        "user": { "id": 1, "username": "alice", "email": "alice@example.com" },
+    })
+}
+
+#[tokio::test]
+async fn register_sends_credentials_and_returns_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/auth/register"))
+        .and(body_json(json!({
+            "username": "alice",
+            "email": "alice@example.com",
+            "password": "secret",
+        })))
+        .respond_with(ResponseTemplate::new(201).set_body_json(auth_json()))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let token = client(&server)
+        .await
+        .register(
+            String::from("alice"),
+            String::from("alice@example.com"),
+            String::from("secret"),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(token, TOKEN);
+}
+
+#[tokio::test]
+async fn register_conflict_is_existing_user() {
+    let server = MockServer::start().await;
+    respond(&server, 409, json!({ "error": "exists", "status": 409 })).await;
+
+    let result = client(&server)
+        .await
+        .register(String::new(), String::new(), String::new())
+        .await;
+
+    assert!(matches!(result, Err(BlogClientError::UserAlreadyExists)));
+}
+
+#[tokio::test]
+async fn login_sends_credentials_and_returns_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/auth/login"))
+        .and(body_json(
+            json!({ "username": "alice", "password": "secret" }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(auth_json()))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let token = client(&server)
+        .await
+        .login(String::from("alice"), String::from("secret"))
+        .await
+        .unwrap();
+
+    assert_eq!(token, TOKEN);
+}
+
+#[tokio::test]
+async fn login_unauthorized_is_invalid_credentials() {
+    let server = MockServer::start().await;
+    respond(&server, 401, json!({ "error": "invalid", "status": 401 })).await;
+
+    let result = client(&server)
+        .await
+        .login(String::from("alice"), String::from("wrong"))
+        .await;
+
+    assert!(matches!(result, Err(BlogClientError::InvalidCredentials)));
+}
+
+#[tokio::test]
+async fn malformed_login_response_is_decode_error() {
+    let server = MockServer::start().await;
+    respond_raw(&server, 200, "{\"token\":").await;
+
+    let result = client(&server)
+        .await
+        .login(String::from("alice"), String::from("secret"))
+        .await;
+
+    assert!(matches!(result, Err(BlogClientError::Reqwest(e)) if e.is_decode()));
+}
+
+#[tokio::test]
+async fn create_post_sends_bearer_token_and_body() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/posts"))
+        .and(header("authorization", format!("Bearer {TOKEN}").as_str()))
+        .and(body_json(json!({ "title": "title", "content": "content" })))
+        .respond_with(ResponseTemplate::new(201).set_body_json(post_json(7)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let post = authorized_client(&server)
+        .await
+        .create_post(String::from("title"), String::from("content"))
+        .await
+        .unwrap();
+
+    assert_eq!(post.id, 7);
+    assert_eq!(post.author_username.as_deref(), Some("alice"));
+}
+
+#[tokio::test]
+async fn create_post_without_token_sends_nothing() {
+    let server = MockServer::start().await;
+    Mock::given(wiremock::matchers::any())
+        .respond_with(ResponseTemplate::new(201))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let result = client(&server)
+        .await
+        .create_post(String::from("title"), String::from("content"))
+        .await;
+
+    assert!(matches!(result, Err(BlogClientError::TokenNotSet)));
+}
+
+#[tokio::test]
+async fn create_post_unauthorized_is_invalid_token() {
+    let server = MockServer::start().await;
+    respond(&server, 401, json!({ "error": "expired", "status": 401 })).await;
+
+    let result = authorized_client(&server)
+        .await
+        .create_post(String::from("title"), String::from("content"))
+        .await;
+
+    assert!(matches!(result, Err(BlogClientError::InvalidToken)));
+}
+
+#[tokio::test]
+async fn validation_error_lists_fields() {
+    let server = MockServer::start().await;
+    let body = json!({
+        "error": "Field \"title\" is too long",
+        "status": 400,
+        "code": "FIELD_TOO_LONG",
+        "fields": [{ "field": "title", "message": "too long" }],
+    });
+    respond(&server, 400, body).await;
+
+    let result = authorized_client(&server)
+        .await
+        .create_post(String::from("title"), String::from("content"))
+        .await;
+
+    let Err(BlogClientError::Validation { message, fields }) = result else {
+        panic!("expected validation error, got {result:?}");
+    };
+    assert_eq!(message, "Field \"title\" is too long");
+    assert_eq!(fields[0].field, "title");
+}
+
+#[tokio::test]
+async fn get_post_requests_post_by_id() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/posts/7"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(post_json(7)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let post = client(&server).await.get_post(7).await.unwrap();
+
+    assert_eq!(post.id, 7);
+    assert_eq!(post.title, "title");
+}
+
+#[tokio::test]
+async fn get_missing_post_is_not_found() {
+    let server = MockServer::start().await;
+    respond(
+        &server,
+        404,
+        json!({ "error": "Post not found", "status": 404 }),
+    )
+    .await;
+
+    let result = client(&server).await.get_post(7).await;
+
+    assert!(matches!(result, Err(BlogClientError::NotFound)));
+}
+
+#[tokio::test]
+async fn malformed_post_is_decode_error() {
+    let server = MockServer::start().await;
+    respond(&server, 200, json!({ "id": "seven" })).await;
+
+    let result = client(&server).await.get_post(7).await;
+
+    assert!(matches!(result, Err(BlogClientError::Reqwest(e)) if e.is_decode()));
+}
+
+#[tokio::test]
+async fn update_post_sends_put_with_both_fields() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/api/posts/7"))
+        .and(header("authorization", format!("Bearer {TOKEN}").as_str()))
+        .and(body_json(json!({ "title": "new", "content": "text" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(post_json(7)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let result = authorized_client(&server)
+        .await
+        .update_post(7, String::from("new"), String::from("text"))
+        .await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn update_post_error_statuses_are_mapped() {
+    for (status, expected) in [(401, "InvalidToken"), (403, "Forbidden"), (404, "NotFound")] {
+        let server = MockServer::start().await;
+        respond(&server, status, json!({ "error": "", "status": status })).await;
+
+        let result = authorized_client(&server)
+            .await
+            .update_post(7, String::from("new"), String::from("text"))
+            .await;
+
+        let error = result.unwrap_err();
+        assert!(
+            format!("{error:?}").starts_with(expected),
+            "{status}: {error:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn patch_post_sends_only_changed_fields() {
+    let server = MockServer::start().await;
+    Mock::given(method("PATCH"))
+        .and(path("/api/posts/7"))
+        .and(body_json(json!({ "title": "new" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(post_json(7)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let fields = UpdatePostFields {
+        title: Some(String::from("new")),
+        content: None,
+    };
+    let result = authorized_client(&server)
+        .await
+        .update_post_partial(7, fields)
+        .await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn patch_post_error_statuses_are_mapped() {
+    for (status, expected) in [(401, "InvalidToken"), (403, "Forbidden"), (404, "NotFound")] {
+        let server = MockServer::start().await;
+        respond(&server, status, json!({ "error": "", "status": status })).await;
+
+        let result = authorized_client(&server)
+            .await
+            .update_post_partial(7, UpdatePostFields::default())
+            .await;
+
+        let error = result.unwrap_err();
+        assert!(
+            format!("{error:?}").starts_with(expected),
+            "{status}: {error:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn delete_post_sends_delete() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/posts/7"))
+        .and(header("authorization", format!("Bearer {TOKEN}").as_str()))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let result = authorized_client(&server).await.delete_post(7).await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn delete_post_error_statuses_are_mapped() {
+    for (status, expected) in [(401, "InvalidToken"), (403, "Forbidden"), (404, "NotFound")] {
+        let server = MockServer::start().await;
+        respond(&server, status, json!({ "error": "", "status": status })).await;
+
+        let result = authorized_client(&server).await.delete_post(7).await;
+
+        let error = result.unwrap_err();
+        assert!(
+            format!("{error:?}").starts_with(expected),
+            "{status}: {error:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn server_error_keeps_status_and_body() {
+    let server = MockServer::start().await;
+    respond_raw(&server, 500, "database is down").await;
+
+    let result = authorized_client(&server).await.delete_post(7).await;
+
+    let Err(BlogClientError::UnexpectedHttpResponse { code, message }) = result else {
+        panic!("expected unexpected response, got {result:?}");
+    };
+    assert_eq!(code, 500);
+    assert_eq!(message, "database is down");
+}
+
+#[tokio::test]
+async fn maintenance_is_read_only_error_and_not_retried() {
+    let server = MockServer::start().await;
+    let body = json!({
+        "code": "MAINTENANCE",
+        "error": "back in five minutes",
+        "status": 503,
+    });
+    Mock::given(wiremock::matchers::any())
+        .respond_with(ResponseTemplate::new(503).set_body_json(body))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = BlogClient::builder(Transport::Http(server.uri()))
+        .retry_policy(RetryPolicy::default())
+        .build()
+        .await
+        .unwrap();
+    client.set_token(String::from(TOKEN)).unwrap();
+
+    let result = client.delete_post(7).await;
+
+    let Err(error @ BlogClientError::ReadOnly(_)) = result else {
+        panic!("expected read-only error, got {result:?}");
+    };
+    assert!(!error.is_unreachable());
+    assert_eq!(
+        error.to_string(),
+        "Server is in read-only mode: back in five minutes"
+    );
+}
+
+#[tokio::test]
+async fn get_posts_sends_page_bounds() {
+    let server = MockServer::start().await;
+    let body = json!({ "posts": [post_json(1)], "limit": 5, "offset": 10, "total_posts": 11 });
+    Mock::given(method("GET"))
+        .and(path("/api/posts"))
+        .and(query_param("limit", "5"))
+        .and(query_param("offset", "10"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let page = client(&server)
+        .await
+        .get_posts(Some(5), Some(10))
+        .await
+        .unwrap();
+
+    assert_eq!(page.posts.len(), 1);
+    assert_eq!(page.total_posts, 11);
+}
+
+#[tokio::test]
+async fn malformed_posts_page_is_decode_error() {
+    let server = MockServer::start().await;
+    respond_raw(&server, 200, "<html>proxy error</html>").await;
+
+    let result = client(&server).await.get_posts(None, None).await;
+
+    assert!(matches!(result, Err(BlogClientError::Reqwest(e)) if e.is_decode()));
+}
+
+#[tokio::test]
+async fn search_posts_sends_query() {
+    let server = MockServer::start().await;
+    let body = json!({ "posts": [], "limit": 10, "offset": 0, "total_posts": 0 });
+    Mock::given(method("GET"))
+        .and(path("/api/posts/search"))
+        .and(query_param("query", "rust async"))
+        .and(query_param("limit", "10"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let page = client(&server)
+        .await
+        .search_posts(String::from("rust async"), Some(10), None)
+        .await
+        .unwrap();
+
+    assert!(page.posts.is_empty());
+}
+
+#[tokio::test]
+async fn server_info_without_capabilities_is_accepted() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/info"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "version": "0.1.0", "api_version": 1 })),
+        )
+        .mount(&server)
+        .await;
+
+    let info = client(&server).await.server_info().await.unwrap();
+
+    assert_eq!(info.api_version, 1);
+    assert!(info.features.is_empty());
+    assert_eq!(info.limits, None);
+}
+
+#[tokio::test]
+async fn user_stats_unauthorized_is_invalid_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/stats/me"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&server)
+        .await;
+
+    let result = authorized_client(&server).await.user_stats().await;
+
+    assert!(matches!(result, Err(BlogClientError::InvalidToken)));
+}
+
+#[tokio::test]
+async fn rate_limit_reports_retry_after() {
+    let server = MockServer::start().await;
+    Mock::given(wiremock::matchers::any())
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "7"))
+        .mount(&server)
+        .await;
+
+    let result = client(&server).await.get_post(7).await;
+
+    assert!(matches!(
+        result,
+        Err(BlogClientError::RateLimited { retry_after: Some(delay) }) if delay == Duration::from_secs(7)
+    ));
+}
+
+#[tokio::test]
+async fn cookie_session_sends_csrf_header_instead_of_bearer() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/v2/posts/7"))
+        .and(header("x-csrf-token", TOKEN))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = BlogClient::builder(Transport::Http(server.uri()))
+        .api_prefix("/v2")
+        .cookie_session(true)
+        .build()
+        .await
+        .unwrap();
+    client.set_token(String::from(TOKEN)).unwrap();
+    client.delete_post(7).await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    assert!(!requests[0].headers.contains_key("authorization"));
+}
+
+#[tokio::test]
+async fn default_headers_are_sent() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("x-request-source", "tests"))
+        .and(header("user-agent", "blog-tests/1.0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(post_json(7)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = BlogClient::builder(Transport::Http(server.uri()))
+        .default_header("X-Request-Source", "tests")
+        .user_agent("blog-tests/1.0")
+        .build()
+        .await
+        .unwrap();
+
+    assert!(client.get_post(7).await.is_ok());
+}
@@ -0,0 +1,255 @@
+//! Runs the same scenarios through HTTP and gRPC transports of one server
+//! and checks that both end with the same domain-level outcomes
+//!
+//! Requires running blog-server, addresses are taken from `BLOG_TEST_HTTP_URL`
+//! and `BLOG_TEST_GRPC_URL` (`http://127.0.0.1:8080` and `http://127.0.0.1:50051`
+//! by default): `cargo test -p blog-client --test transport_parity -- --ignored`
+#![cfg(feature = "grpc")]
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use blog_client::{
+    Transport,
+    blog_client::{BlogClient, PostsCollection, UpdatePostFields},
+    error::BlogClientError,
+};
+
+const PASSWORD: &str = "parity-password";
+
+/// Expired token signed with another secret, sent with every request by a client
+/// which keeps an old token in its default headers
+const STALE_TOKEN: &str = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCIsImtpZCI6ImRlZmF1bHQifQ.\
+    eyJ1c2VyX2lkIjoxLCJ1c2VybmFtZSI6InN0YWxlIiwiZXhwIjoxNzAwMDAwMDAwfQ.\
+    bm90IGEgc2lnbmF0dXJlIG9mIHRoaXMgc2VydmVy";
+
+/// Outcome of a step: summary of the result or name of the error variant
+type Outcome = Result<String, String>;
+
+async fn connect(transport: &str) -> BlogClient {
+    BlogClient::new(server(transport)).await.unwrap()
+}
+
+fn server(transport: &str) -> Transport {
+    match transport {
+        "http" => Transport::Http(url("BLOG_TEST_HTTP_URL", "http://127.0.0.1:8080")),
+        _ => Transport::Grpc(url("BLOG_TEST_GRPC_URL", "http://127.0.0.1:50051")),
+    }
+}
+
+fn url(var: &str, default: &str) -> String {
+    std::env::var(var).unwrap_or_else(|_| String::from(default))
+}
+
+/// Username unique for the run and transport, so scenarios don't see each other's users
+fn username(transport: &str, name: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    format!("{name}_{transport}_{nanos}")
+}
+
+/// Error variant without data which differs between transports, e.g. messages
+fn error_kind(error: &BlogClientError) -> String {
+    match error {
+        BlogClientError::Validation { fields, .. } => {
+            let fields: Vec<&str> = fields.iter().map(|f| f.field.as_str()).collect();
+            format!("Validation{fields:?}")
+        }
+        error => {
+            let debug = format!("{error:?}");
+            let end = debug.find([' ', '(', '{']).unwrap_or(debug.len());
+            debug[..end].to_string()
+        }
+    }
+}
+
+fn outcome<T>(result: Result<T, BlogClientError>, summary: impl FnOnce(T) -> String) -> Outcome {
+    result.map(summary).map_err(|e| error_kind(&e))
+}
+
+fn page(collection: PostsCollection) -> String {
+    format!(
+        "{} posts, limit {}, offset {}",
+        collection.posts.len(),
+        collection.limit,
+        collection.offset
+    )
+}
+
+async fn register(client: &BlogClient, username: &str) -> Result<String, BlogClientError> {
+    client
+        .register(
+            username.to_string(),
+            format!("{username}@example.com"),
+            String::from(PASSWORD),
+        )
+        .await
+}
+
+async fn auth_errors(transport: &str) -> Vec<(&'static str, Outcome)> {
+    let client = connect(transport).await;
+    let alice = username(transport, "alice");
+    register(&client, &alice).await.unwrap();
+
+    let mut outcomes = Vec::new();
+    let result = register(&client, &alice).await;
+    outcomes.push(("duplicate registration", outcome(result, |_| String::new())));
+
+    let result = client.login(alice.clone(), String::from("wrong")).await;
+    outcomes.push(("wrong password", outcome(result, |_| String::new())));
+
+    let unknown = username(transport, "nobody");
+    let result = client.login(unknown, String::from(PASSWORD)).await;
+    outcomes.push(("unknown user", outcome(result, |_| String::new())));
+
+    client.set_token(String::from("not-a-token")).unwrap();
+    let result = client
+        .create_post(String::from("title"), String::from("content"))
+        .await;
+    outcomes.push(("malformed token", outcome(result, |post| post.title)));
+
+    client.clear_token().unwrap();
+    let result = client
+        .create_post(String::from("title"), String::from("content"))
+        .await;
+    outcomes.push(("missing token", outcome(result, |post| post.title)));
+
+    outcomes
+}
+
+/// Public requests are served even if they carry an invalid token
+async fn stale_token(transport: &str) -> Vec<(&'static str, Outcome)> {
+    let alice = username(transport, "stale");
+    register(&connect(transport).await, &alice).await.unwrap();
+
+    let client = BlogClient::builder(server(transport))
+        .default_header("authorization", format!("Bearer {STALE_TOKEN}"))
+        .build()
+        .await
+        .unwrap();
+
+    let mut outcomes = Vec::new();
+    let result = client.login(alice, String::from(PASSWORD)).await;
+    outcomes.push(("login with stale token", outcome(result, |_| String::new())));
+
+    let result = client.get_posts(Some(1), Some(0)).await;
+    outcomes.push((
+        "list with stale token",
+        outcome(result, |posts| format!("limit {}", posts.limit)),
+    ));
+
+    outcomes
+}
+
+async fn forbidden_edits(transport: &str) -> Vec<(&'static str, Outcome)> {
+    let client = connect(transport).await;
+    let token = register(&client, &username(transport, "author")).await;
+    client.set_token(token.unwrap()).unwrap();
+    let post = client
+        .create_post(String::from("own post"), String::from("content"))
+        .await
+        .unwrap();
+
+    let token = register(&client, &username(transport, "intruder")).await;
+    client.set_token(token.unwrap()).unwrap();
+
+    let mut outcomes = Vec::new();
+    let result = client
+        .update_post(post.id, String::from("edited"), String::from("edited"))
+        .await;
+    outcomes.push((
+        "update of another's post",
+        outcome(result, |post| post.title),
+    ));
+
+    let fields = UpdatePostFields {
+        title: Some(String::from("edited")),
+        content: None,
+    };
+    let result = client.update_post_partial(post.id, fields).await;
+    outcomes.push((
+        "patch of another's post",
+        outcome(result, |post| post.title),
+    ));
+
+    let result = client.delete_post(post.id).await;
+    outcomes.push((
+        "delete of another's post",
+        outcome(result, |_| String::new()),
+    ));
+
+    let result = client.get_post(post.id).await;
+    outcomes.push(("post is kept", outcome(result, |post| post.title)));
+
+    let result = client.get_post(i64::MAX).await;
+    outcomes.push(("missing post", outcome(result, |post| post.title)));
+
+    let result = client
+        .update_post(i64::MAX, String::from("edited"), String::from("edited"))
+        .await;
+    outcomes.push(("update of missing post", outcome(result, |post| post.title)));
+
+    let result = client.delete_post(i64::MAX).await;
+    outcomes.push(("delete of missing post", outcome(result, |_| String::new())));
+
+    let result = client
+        .create_post("t".repeat(10_000), String::from("content"))
+        .await;
+    outcomes.push(("too long title", outcome(result, |post| post.title)));
+
+    outcomes
+}
+
+async fn pagination(transport: &str) -> Vec<(&'static str, Outcome)> {
+    let client = connect(transport).await;
+    let total = client
+        .get_posts(Some(1), Some(0))
+        .await
+        .unwrap()
+        .total_posts;
+
+    let mut outcomes = Vec::new();
+    let result = client.get_posts(Some(0), Some(0)).await;
+    outcomes.push(("zero limit", outcome(result, page)));
+
+    let result = client.get_posts(Some(1), Some(total + 10)).await;
+    outcomes.push(("offset past the end", outcome(result, page)));
+
+    let result = client.get_posts(Some(100_000), Some(total + 10)).await;
+    outcomes.push(("limit above maximum", outcome(result, page)));
+
+    let result = client.get_posts(None, Some(total + 10)).await;
+    outcomes.push(("default limit", outcome(result, page)));
+
+    let result = client.get_posts(Some(1), Some(u64::MAX / 2)).await;
+    outcomes.push(("huge offset", outcome(result, page)));
+
+    outcomes
+}
+
+#[tokio::test]
+#[ignore = "requires running blog-server"]
+async fn auth_errors_match() {
+    assert_eq!(auth_errors("http").await, auth_errors("grpc").await);
+}
+
+#[tokio::test]
+#[ignore = "requires running blog-server"]
+async fn stale_token_does_not_block_public_requests() {
+    let http = stale_token("http").await;
+    assert!(http.iter().all(|(_, outcome)| outcome.is_ok()), "{http:?}");
+    assert_eq!(http, stale_token("grpc").await);
+}
+
+#[tokio::test]
+#[ignore = "requires running blog-server"]
+async fn forbidden_edits_match() {
+    assert_eq!(forbidden_edits("http").await, forbidden_edits("grpc").await);
+}
+
+#[tokio::test]
+#[ignore = "requires running blog-server"]
+async fn pagination_edge_cases_match() {
+    assert_eq!(pagination("http").await, pagination("grpc").await);
+}
@@ -0,0 +1,88 @@
+use std::process::ExitCode;
+
+use blog_client::Transport;
+use clap::{Parser, ValueEnum};
+
+use crate::scenario::{Outcome, StepReport};
+
+mod scenario;
+
+/// Runs a scripted scenario against a blog server and reports which steps failed
+#[derive(Debug, Parser)]
+#[command(version, about = "Blog server end-to-end smoke test", long_about = None)]
+struct Args {
+    /// Address of HTTP API
+    #[arg(
+        long,
+        env = "BLOG_E2E_HTTP_URL",
+        default_value = "http://127.0.0.1:8080"
+    )]
+    http_url: String,
+
+    /// Address of gRPC API
+    #[arg(
+        long,
+        env = "BLOG_E2E_GRPC_URL",
+        default_value = "http://127.0.0.1:50051"
+    )]
+    grpc_url: String,
+
+    /// Transports to run the scenario over
+    #[arg(long, value_enum, default_value_t = Transports::Both)]
+    transport: Transports,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Transports {
+    Http,
+    Grpc,
+    Both,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let mut transports = Vec::new();
+    if matches!(args.transport, Transports::Http | Transports::Both) {
+        transports.push(("http", Transport::Http(args.http_url)));
+    }
+    if matches!(args.transport, Transports::Grpc | Transports::Both) {
+        transports.push(("grpc", Transport::Grpc(args.grpc_url)));
+    }
+
+    let mut reports = Vec::new();
+    for (name, transport) in transports {
+        let transport_reports = scenario::run(name, transport).await;
+        transport_reports.iter().for_each(print_report);
+        reports.extend(transport_reports);
+    }
+
+    let count = |f: fn(&Outcome) -> bool| reports.iter().filter(|r| f(&r.outcome)).count();
+    let failed = count(|outcome| matches!(outcome, Outcome::Failed(_)));
+    println!(
+        "\n{} passed, {failed} failed, {} skipped",
+        count(|outcome| matches!(outcome, Outcome::Passed)),
+        count(|outcome| matches!(outcome, Outcome::Skipped(_))),
+    );
+
+    if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn print_report(report: &StepReport) {
+    let elapsed = report.elapsed.as_millis();
+    match &report.outcome {
+        Outcome::Passed => println!("PASS {} {} ({elapsed} ms)", report.transport, report.step),
+        Outcome::Failed(error) => println!(
+            "FAIL {} {} ({elapsed} ms): {error}",
+            report.transport, report.step
+        ),
+        Outcome::Skipped(reason) => {
+            println!("SKIP {} {}: {reason}", report.transport, report.step)
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Scenario register → post → comment → search → delete, run over one transport
+//!
+//! Every run registers a new user with a unique name, its post is deleted at the end
+//! (also when a step after creation fails) but the user stays on the server
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use blog_client::{
+    API_VERSION, Transport,
+    blog_client::{BlogClient, Post},
+    error::BlogClientError,
+};
+
+/// Steps in order of execution
+const STEPS: [&str; 8] = [
+    "connect",
+    "server info",
+    "register",
+    "create post",
+    "read post",
+    "comment",
+    "search",
+    "delete post",
+];
+
+const PASSWORD: &str = "e2e-password";
+
+#[derive(Debug)]
+pub enum Outcome {
+    Passed,
+    Failed(String),
+    Skipped(String),
+}
+
+#[derive(Debug)]
+pub struct StepReport {
+    pub transport: &'static str,
+    pub step: &'static str,
+    pub outcome: Outcome,
+    pub elapsed: Duration,
+}
+
+/// Reports of executed steps, steps after a failed one are reported as skipped
+struct Run {
+    transport: &'static str,
+    reports: Vec<StepReport>,
+}
+
+impl Run {
+    async fn step<T>(
+        &mut self,
+        step: &'static str,
+        action: impl Future<Output = Result<T, String>>,
+    ) -> Option<T> {
+        let started = Instant::now();
+        let result = action.await;
+        let outcome = match &result {
+            Ok(_) => Outcome::Passed,
+            Err(error) => Outcome::Failed(error.clone()),
+        };
+        self.report(step, outcome, started.elapsed());
+        result.ok()
+    }
+
+    fn skip(&mut self, step: &'static str, reason: &str) {
+        self.report(step, Outcome::Skipped(reason.to_string()), Duration::ZERO);
+    }
+
+    fn report(&mut self, step: &'static str, outcome: Outcome, elapsed: Duration) {
+        self.reports.push(StepReport {
+            transport: self.transport,
+            step,
+            outcome,
+            elapsed,
+        });
+    }
+
+    fn finish(mut self) -> Vec<StepReport> {
+        for step in &STEPS[self.reports.len()..] {
+            self.skip(step, "previous step failed");
+        }
+        self.reports
+    }
+}
+
+pub async fn run(name: &'static str, transport: Transport) -> Vec<StepReport> {
+    let mut run = Run {
+        transport: name,
+        reports: Vec::new(),
+    };
+
+    let connect = async { BlogClient::new(transport).await.map_err(describe) };
+    let Some(client) = run.step("connect", connect).await else {
+        return run.finish();
+    };
+
+    let Some(supports_comments) = run.step("server info", server_info(&client)).await else {
+        return run.finish();
+    };
+
+    let marker = unique_word();
+    let username = format!("e2e_{name}_{marker}");
+    if run
+        .step("register", register(&client, &username))
+        .await
+        .is_none()
+    {
+        return run.finish();
+    }
+
+    let Some(post) = run.step("create post", create_post(&client, &marker)).await else {
+        return run.finish();
+    };
+
+    if !after_creation(&mut run, &client, &post, &marker, supports_comments).await {
+        // best effort, the post of a failed run should not stay on the server
+        let _ = client.delete_post(post.id).await;
+    }
+
+    run.finish()
+}
+
+/// Steps using the created post, returns whether the post was deleted
+async fn after_creation(
+    run: &mut Run,
+    client: &BlogClient,
+    post: &Post,
+    marker: &str,
+    supports_comments: bool,
+) -> bool {
+    if run
+        .step("read post", read_post(client, post))
+        .await
+        .is_none()
+    {
+        return false;
+    }
+
+    if supports_comments {
+        run.skip("comment", "blog-client has no comments API yet");
+    } else {
+        run.skip("comment", "server does not support comments");
+    }
+
+    if run
+        .step("search", search(client, post, marker))
+        .await
+        .is_none()
+    {
+        return false;
+    }
+
+    run.step("delete post", delete_post(client, post))
+        .await
+        .is_some()
+}
+
+/// Checks that the server speaks the same API version, returns whether it supports comments
+async fn server_info(client: &BlogClient) -> Result<bool, String> {
+    let info = client.server_info().await.map_err(describe)?;
+    if info.api_version != API_VERSION {
+        return Err(format!(
+            "server API version {} differs from client API version {API_VERSION}",
+            info.api_version
+        ));
+    }
+    Ok(info.supports("comments"))
+}
+
+async fn register(client: &BlogClient, username: &str) -> Result<(), String> {
+    let token = client
+        .register(
+            username.to_string(),
+            format!("{username}@example.com"),
+            String::from(PASSWORD),
+        )
+        .await
+        .map_err(describe)?;
+    client.set_token(token).map_err(describe)
+}
+
+async fn create_post(client: &BlogClient, marker: &str) -> Result<Post, String> {
+    client
+        .create_post(
+            format!("Smoke test {marker}"),
+            format!("Post created by blog-e2e, search marker {marker}"),
+        )
+        .await
+        .map_err(describe)
+}
+
+async fn read_post(client: &BlogClient, post: &Post) -> Result<(), String> {
+    let read = client.get_post(post.id).await.map_err(describe)?;
+    if read.title != post.title || read.content != post.content {
+        return Err(format!("post {} differs from the created one", post.id));
+    }
+    Ok(())
+}
+
+async fn search(client: &BlogClient, post: &Post, marker: &str) -> Result<(), String> {
+    let found = client
+        .search_posts(marker.to_string(), None, None)
+        .await
+        .map_err(describe)?;
+    if !found.posts.iter().any(|found| found.id == post.id) {
+        return Err(format!("post {} is not found by \"{marker}\"", post.id));
+    }
+    Ok(())
+}
+
+async fn delete_post(client: &BlogClient, post: &Post) -> Result<(), String> {
+    client.delete_post(post.id).await.map_err(describe)?;
+    match client.get_post(post.id).await {
+        Err(BlogClientError::NotFound) => Ok(()),
+        Ok(_) => Err(format!("post {} is still readable", post.id)),
+        Err(error) => Err(describe(error)),
+    }
+}
+
+fn describe(error: BlogClientError) -> String {
+    error.to_string()
+}
+
+/// Latin letters only, so full-text search indexes the marker as one word
+fn unique_word() -> String {
+    let mut nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let mut word = String::from("smoke");
+    while nanos > 0 {
+        word.push(char::from(b'a' + (nanos % 26) as u8));
+        nanos /= 26;
+    }
+    word
+}
@@ -1 +1,3 @@
-tonic::include_proto! {"blog"}
+tonic::include_proto! {"blog.v1"}
+
+pub use prost_types::{FieldMask, Timestamp};
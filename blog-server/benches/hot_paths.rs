@@ -0,0 +1,155 @@
+//! Benchmarks of request hot paths: JWT, password hashing and post queries
+//!
+//! `cargo bench -p blog-server`. Repository benchmarks need a database in
+//! `DATABASE_URL`, it is seeded with posts of the `bench_author` user on the first run
+//! and the benchmarks are skipped when the variable is not set
+
+use std::{hint::black_box, sync::Arc};
+
+use argon2::{
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+    password_hash::{SaltString, rand_core::OsRng},
+};
+use blog_server::{
+    data::{
+        post_repository::{PgPostRepository, PostRepository},
+        user_repository::{PgUserRepository, UserRepository},
+    },
+    infrastructure::{
+        clock::SystemClock,
+        database::{init_db_connection, run_migrations},
+        jwt::{JwtKey, JwtService},
+    },
+};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use tokio::runtime::Runtime;
+
+const SEEDED_POSTS: i64 = 1000;
+const BENCH_AUTHOR: &str = "bench_author";
+
+fn jwt(c: &mut Criterion) {
+    let key = JwtKey {
+        id: String::from("bench"),
+        secret: String::from("bench-secret"),
+    };
+    let service = JwtService::new(key, Vec::new()).unwrap();
+    let token = service
+        .generate_token(1, String::from("alice"), None)
+        .unwrap();
+
+    c.bench_function("jwt/generate", |b| {
+        b.iter(|| service.generate_token(black_box(1), String::from("alice"), None))
+    });
+    c.bench_function("jwt/verify", |b| {
+        b.iter(|| service.verify_token(black_box(&token)))
+    });
+}
+
+/// Default parameters used by `AuthService` and the alternatives recommended by OWASP
+fn argon2(c: &mut Criterion) {
+    let candidates = [
+        ("default", Params::default()),
+        ("m47104_t1", Params::new(47104, 1, 1, None).unwrap()),
+        ("m12288_t3", Params::new(12288, 3, 1, None).unwrap()),
+    ];
+
+    let mut group = c.benchmark_group("argon2");
+    group.sample_size(10);
+    for (name, params) in candidates {
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = argon2
+            .hash_password(b"password", &salt)
+            .unwrap()
+            .to_string();
+        let hash = PasswordHash::new(&hash).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("hash", name), &argon2, |b, argon2| {
+            b.iter(|| argon2.hash_password(black_box(b"password"), &salt))
+        });
+        group.bench_with_input(BenchmarkId::new("verify", name), &argon2, |b, argon2| {
+            b.iter(|| argon2.verify_password(black_box(b"password"), &hash))
+        });
+    }
+    group.finish();
+}
+
+fn repository(c: &mut Criterion) {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set, repository benchmarks are skipped");
+        return;
+    };
+
+    let runtime = Runtime::new().unwrap();
+    let (posts, ids) = runtime.block_on(async {
+        let pool = Arc::new(init_db_connection(&url).await.unwrap());
+        run_migrations(&pool).await.unwrap();
+        let posts = PgPostRepository::new(pool.clone(), Arc::new(SystemClock));
+        let ids = seed(&PgUserRepository::new(pool), &posts).await;
+        (posts, ids)
+    });
+
+    let mut group = c.benchmark_group("repository");
+    group.bench_function("get_post", |b| {
+        b.to_async(&runtime)
+            .iter(|| posts.get_post(black_box(ids[0])))
+    });
+    group.bench_function("get_posts_page/first", |b| {
+        b.to_async(&runtime).iter(|| posts.get_posts_page(10, 0))
+    });
+    group.bench_function("get_posts_page/deep", |b| {
+        b.to_async(&runtime)
+            .iter(|| posts.get_posts_page(10, SEEDED_POSTS / 2))
+    });
+    group.bench_function("get_posts_page/past_end", |b| {
+        b.to_async(&runtime)
+            .iter(|| posts.get_posts_page(10, i64::MAX / 2))
+    });
+    group.bench_function("get_posts_by_ids/100", |b| {
+        b.to_async(&runtime)
+            .iter(|| posts.get_posts_by_ids(black_box(&ids[..100])))
+    });
+    group.bench_function("search_posts", |b| {
+        b.to_async(&runtime)
+            .iter(|| posts.search_posts(black_box("benchmark rust"), 10, 0))
+    });
+    group.finish();
+}
+
+/// Creates the bench user with `SEEDED_POSTS` posts unless they exist, returns ids of the posts
+async fn seed(users: &PgUserRepository, posts: &PgPostRepository) -> Vec<i64> {
+    let author = match users.get_by_username(BENCH_AUTHOR).await.unwrap() {
+        Some(author) => author,
+        None => users
+            .save_user(BENCH_AUTHOR, "bench_author@example.com", "not a hash")
+            .await
+            .unwrap(),
+    };
+
+    let mut ids: Vec<i64> = posts
+        .search_posts("benchmark", SEEDED_POSTS, 0)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|post| post.author_id == author.id)
+        .map(|post| post.id)
+        .collect();
+
+    for i in ids.len() as i64..SEEDED_POSTS {
+        let topic = ["rust", "databases", "web"][i as usize % 3];
+        let post = posts
+            .create_post(
+                format!("Benchmark post {i} about {topic}"),
+                format!("Seeded content of benchmark post {i}. ").repeat(20),
+                author.id,
+            )
+            .await
+            .unwrap();
+        ids.push(post.id);
+    }
+
+    ids
+}
+
+criterion_group!(benches, jwt, argon2, repository);
+criterion_main!(benches);
@@ -0,0 +1,30 @@
+#!/usr/bin/env bash
+# Latency of GET /api/info while the server hashes passwords of a burst of logins
+#
+# Start the server on one core to make the effect of blocking hashing visible, e.g.
+#   cargo build -p blog-server && taskset -c 0 ./target/debug/blog-server
+# then run `blog-server/benches/login_burst.sh`. Settings are taken from environment:
+# BLOG_URL (http://127.0.0.1:8080), LOGINS (64) and PROBES (20)
+set -euo pipefail
+
+url=${BLOG_URL:-http://127.0.0.1:8080}
+logins=${LOGINS:-64}
+probes=${PROBES:-20}
+username=burst$RANDOM$RANDOM
+password=burst-password
+
+curl -sf -o /dev/null -X POST "$url/api/auth/register" -H 'Content-Type: application/json' \
+    -d "{\"username\":\"$username\",\"email\":\"$username@example.com\",\"password\":\"$password\"}"
+
+for _ in $(seq "$logins"); do
+    curl -s -o /dev/null -X POST "$url/api/auth/login" -H 'Content-Type: application/json' \
+        -d "{\"username\":\"$username\",\"password\":\"$password\"}" &
+done
+# let the logins reach the server before the probes
+sleep 0.05
+
+for _ in $(seq "$probes"); do
+    curl -s -o /dev/null -w '%{time_total}\n' "$url/api/info"
+done | sort -n | awk '{ t[NR] = $1 } END { printf "GET /api/info during %d logins: median %.3f s, max %.3f s\n", '"$logins"', t[int((NR + 1) / 2)], t[NR] }'
+
+wait
@@ -0,0 +1,11 @@
+-- Query of PgPostRepository::get_posts_page
+SELECT total.total_posts, page.*
+FROM (SELECT COUNT(*) AS total_posts FROM posts) total
+LEFT JOIN LATERAL (
+    SELECT p.id, p.title, p.content, p.author_id, u.username AS author_username,
+        p.created_at, p.updated_at
+    FROM posts p JOIN users u ON u.id = p.author_id
+    ORDER BY p.created_at DESC, p.id DESC
+    LIMIT 10 OFFSET 0
+) page ON true
+ORDER BY page.created_at DESC, page.id DESC;
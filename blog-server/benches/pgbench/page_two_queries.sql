@@ -0,0 +1,7 @@
+-- Page of posts and the count as separate queries, as before get_posts_page
+SELECT p.id, p.title, p.content, p.author_id, u.username AS author_username,
+    p.created_at, p.updated_at
+FROM posts p JOIN users u ON u.id = p.author_id
+ORDER BY p.created_at DESC, p.id DESC
+LIMIT 10 OFFSET 0;
+SELECT COUNT(*) FROM posts;
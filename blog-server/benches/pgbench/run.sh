@@ -0,0 +1,13 @@
+#!/usr/bin/env bash
+# Compares the first page of posts fetched with two queries and with get_posts_page
+#
+# Needs pgbench and a database in DATABASE_URL. `cargo bench -p blog-server --bench hot_paths`
+# seeds it with 1000 posts. CLIENTS (4) and SECONDS_PER_RUN (10) tune the run
+set -euo pipefail
+
+dir=$(dirname "$0")
+for script in page_two_queries page_one_query; do
+    echo "$script:"
+    pgbench --no-vacuum --protocol=prepared --client="${CLIENTS:-4}" --time="${SECONDS_PER_RUN:-10}" \
+        --file="$dir/$script.sql" "$DATABASE_URL" | grep -E 'latency average|tps'
+done
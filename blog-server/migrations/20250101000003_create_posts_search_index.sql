@@ -0,0 +1,2 @@
+CREATE INDEX idx_posts_search ON posts
+    USING GIN (to_tsvector('simple', coalesce(title, '') || ' ' || coalesce(content, '')));
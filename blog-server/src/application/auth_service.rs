@@ -1,28 +1,43 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
 use crate::{
+    application::maintenance::Maintenance,
     data::user_repository::UserRepository,
-    domain::{error::AppError, user::UserAndToken},
+    domain::{
+        error::AppError,
+        user::{User, UserAndToken},
+    },
     infrastructure::jwt::JwtService,
 };
 
 use argon2::{
-    Argon2, PasswordHash, PasswordVerifier,
-    password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    Algorithm, Argon2, Params, PasswordHash, PasswordVerifier, Version,
+    password_hash::{
+        PasswordHasher, SaltString,
+        rand_core::{OsRng, RngCore},
+    },
 };
+use tracing::debug;
 
 pub struct AuthService {
-    user_repo: UserRepository,
+    user_repo: Arc<dyn UserRepository>,
     jwt_service: Arc<JwtService>,
     argon2: Argon2<'static>,
+    maintenance: Arc<Maintenance>,
 }
 
 impl AuthService {
-    pub fn new(user_repo: UserRepository, jwt_service: Arc<JwtService>) -> Self {
+    pub fn new(
+        user_repo: Arc<dyn UserRepository>,
+        jwt_service: Arc<JwtService>,
+        argon2_params: Params,
+        maintenance: Arc<Maintenance>,
+    ) -> Self {
         Self {
             user_repo,
             jwt_service,
-            argon2: Argon2::default(),
+            argon2: Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params),
+            maintenance,
         }
     }
 
@@ -31,30 +46,32 @@ impl AuthService {
         username: String,
         email: String,
         password: String,
+        cookie_session: bool,
     ) -> Result<UserAndToken, AppError> {
-        let salt = SaltString::generate(&mut OsRng);
+        self.maintenance.ensure_writable()?;
 
-        let password_hash = self
-            .argon2
-            .hash_password(password.as_bytes(), &salt)?
-            .to_string();
+        let argon2 = self.argon2.clone();
+        let password_hash = run_blocking("hash", move || {
+            let salt = SaltString::generate(&mut OsRng);
+            Ok(argon2
+                .hash_password(password.as_bytes(), &salt)?
+                .to_string())
+        })
+        .await?;
 
         let user = self
             .user_repo
             .save_user(&username, &email, &password_hash)
             .await?;
 
-        let token = self
-            .jwt_service
-            .generate_token(user.id, user.username.clone())?;
-
-        Ok(UserAndToken { user, token })
+        self.issue_token(user, cookie_session)
     }
 
     pub async fn login(
         &self,
         username: String,
         password: String,
+        cookie_session: bool,
     ) -> Result<UserAndToken, AppError> {
         let user = self
             .user_repo
@@ -62,16 +79,203 @@ impl AuthService {
             .await?
             .ok_or(AppError::UserNotFound { username })?;
 
-        let parsed_hash = PasswordHash::new(&user.password_hash)?;
+        let argon2 = self.argon2.clone();
+        let password_hash = user.password_hash.clone();
+        run_blocking("verify", move || {
+            let parsed_hash = PasswordHash::new(&password_hash)?;
+            argon2
+                .verify_password(password.as_bytes(), &parsed_hash)
+                .map_err(|_| AppError::InvalidCredentials)
+        })
+        .await?;
+
+        self.issue_token(user, cookie_session)
+    }
+
+    /// Token of a cookie session is issued with a random CSRF token written into its claims
+    fn issue_token(&self, user: User, cookie_session: bool) -> Result<UserAndToken, AppError> {
+        let csrf_token = cookie_session.then(|| {
+            let mut bytes = [0u8; 32];
+            OsRng.fill_bytes(&mut bytes);
+            bytes
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+        });
+
+        let token =
+            self.jwt_service
+                .generate_token(user.id, user.username.clone(), csrf_token.clone())?;
+
+        Ok(UserAndToken {
+            user,
+            token,
+            csrf_token,
+        })
+    }
+}
+
+/// Runs argon2 on the blocking thread pool, so hashing doesn't stall other requests
+/// served by the same executor thread. Time spent waiting for a free blocking thread
+/// and hashing itself is logged in `queued_ms` and `elapsed_ms` fields
+async fn run_blocking<T: Send + 'static>(
+    operation: &'static str,
+    work: impl FnOnce() -> Result<T, AppError> + Send + 'static,
+) -> Result<T, AppError> {
+    let queued_at = Instant::now();
+    let (result, started_at) = tokio::task::spawn_blocking(move || {
+        let started_at = Instant::now();
+        (work(), started_at)
+    })
+    .await
+    .map_err(|e| AppError::HashError(e.to_string()))?;
+
+    debug!(
+        operation,
+        queued_ms = (started_at - queued_at).as_millis() as u64,
+        elapsed_ms = started_at.elapsed().as_millis() as u64,
+        "Password hashing finished"
+    );
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::{
+        data::in_memory::{InMemoryUserRepository, TestClock},
+        infrastructure::jwt::JwtKey,
+    };
+
+    fn auth_service(read_only: bool) -> AuthService {
+        let clock = TestClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+        let jwt_key = JwtKey {
+            id: String::from("test"),
+            secret: String::from("test-secret"),
+        };
+        AuthService::new(
+            InMemoryUserRepository::new(clock.clone()),
+            Arc::new(
+                JwtService::new(jwt_key, Vec::new())
+                    .unwrap()
+                    .with_clock(clock),
+            ),
+            // cheap parameters keep tests fast, hashing itself is the same
+            Params::new(1024, 1, 1, None).unwrap(),
+            Arc::new(Maintenance::new(read_only, None)),
+        )
+    }
+
+    async fn register(service: &AuthService, username: &str) -> UserAndToken {
+        service
+            .register(
+                username.to_string(),
+                format!("{username}@example.com"),
+                String::from("password"),
+                false,
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn registered_user_logs_in() {
+        let service = auth_service(false);
+        let registered = register(&service, "alice").await;
+        assert_eq!(registered.user.id, 1);
+
+        let logged_in = service
+            .login(String::from("alice"), String::from("password"), false)
+            .await
+            .unwrap();
+
+        assert_eq!(logged_in.user.id, registered.user.id);
+        let claims = service.jwt_service.verify_token(&logged_in.token).unwrap();
+        assert_eq!(claims.user_id, 1);
+        assert_eq!(claims.username, "alice");
+        assert_eq!(claims.csrf, None);
+    }
+
+    #[tokio::test]
+    async fn password_is_not_stored_in_plain_text() {
+        let service = auth_service(false);
+        let registered = register(&service, "alice").await;
+
+        assert_ne!(registered.user.password_hash, "password");
+    }
+
+    #[tokio::test]
+    async fn login_with_wrong_password_fails() {
+        let service = auth_service(false);
+        register(&service, "alice").await;
+
+        let result = service
+            .login(String::from("alice"), String::from("wrong"), false)
+            .await;
+
+        assert!(matches!(result, Err(AppError::InvalidCredentials)));
+    }
+
+    #[tokio::test]
+    async fn login_of_unknown_user_fails() {
+        let service = auth_service(false);
+
+        let result = service
+            .login(String::from("bob"), String::from("password"), false)
+            .await;
+
+        assert!(matches!(result, Err(AppError::UserNotFound { username }) if username == "bob"));
+    }
+
+    #[tokio::test]
+    async fn duplicate_username_is_rejected() {
+        let service = auth_service(false);
+        register(&service, "alice").await;
+
+        let result = service
+            .register(
+                String::from("alice"),
+                String::from("other@example.com"),
+                String::from("password"),
+                false,
+            )
+            .await;
+
+        assert!(matches!(result, Err(AppError::UserAlreadyExists)));
+    }
+
+    #[tokio::test]
+    async fn cookie_session_token_carries_csrf_token() {
+        let service = auth_service(false);
+        register(&service, "alice").await;
+
+        let session = service
+            .login(String::from("alice"), String::from("password"), true)
+            .await
+            .unwrap();
+
+        let csrf_token = session.csrf_token.unwrap();
+        assert_eq!(csrf_token.len(), 64);
+        let claims = service.jwt_service.verify_token(&session.token).unwrap();
+        assert_eq!(claims.csrf, Some(csrf_token));
+    }
 
-        self.argon2
-            .verify_password(password.as_bytes(), &parsed_hash)
-            .map_err(|_| AppError::InvalidCredentials)?;
+    #[tokio::test]
+    async fn registration_is_rejected_in_read_only_mode() {
+        let service = auth_service(true);
 
-        let token = self
-            .jwt_service
-            .generate_token(user.id, user.username.clone())?;
+        let result = service
+            .register(
+                String::from("alice"),
+                String::from("alice@example.com"),
+                String::from("password"),
+                false,
+            )
+            .await;
 
-        Ok(UserAndToken { user, token })
+        assert!(matches!(result, Err(AppError::Maintenance(_))));
     }
 }
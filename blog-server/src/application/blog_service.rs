@@ -1,15 +1,65 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use futures_util::{Stream, stream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
 use crate::{
+    application::maintenance::Maintenance,
     data::post_repository::PostRepository,
-    domain::{error::AppError, post::Post},
+    domain::{
+        error::AppError,
+        post::{GetPostsResponse, Post, PostEvent, PostLimits},
+    },
 };
 
+/// Number of events kept for subscribers which are slow to read them
+const EVENTS_CAPACITY: usize = 256;
+
 pub struct BlogService {
-    post_repo: PostRepository,
+    post_repo: Arc<dyn PostRepository>,
+    limits: PostLimits,
+    maintenance: Arc<Maintenance>,
+    events: broadcast::Sender<PostEvent>,
 }
 
 impl BlogService {
-    pub fn new(post_repo: PostRepository) -> Self {
-        Self { post_repo }
+    pub fn new(
+        post_repo: Arc<dyn PostRepository>,
+        limits: PostLimits,
+        maintenance: Arc<Maintenance>,
+    ) -> Self {
+        Self {
+            post_repo,
+            limits,
+            maintenance,
+            events: broadcast::channel(EVENTS_CAPACITY).0,
+        }
+    }
+
+    /// Stream of changes of posts made after subscription
+    ///
+    /// Events missed by a lagging subscriber are skipped
+    pub fn subscribe(&self) -> impl Stream<Item = PostEvent> + Send + 'static {
+        stream::unfold(self.events.subscribe(), |mut events| async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => return Some((event, events)),
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Subscriber is lagging, {skipped} post events skipped")
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    fn publish(&self, event: PostEvent) {
+        // sending fails only when there are no subscribers
+        let _ = self.events.send(event);
     }
 
     pub async fn create_post(
@@ -18,7 +68,15 @@ impl BlogService {
         content: String,
         author_id: i64,
     ) -> Result<Post, AppError> {
-        self.post_repo.create_post(title, content, author_id).await
+        self.maintenance.ensure_writable()?;
+        self.validate_post(&title, &content)?;
+        let post = self
+            .post_repo
+            .create_post(title, content, author_id)
+            .await?;
+
+        self.publish(PostEvent::Created(post.clone()));
+        Ok(post)
     }
 
     pub async fn get_post(&self, post_id: i64) -> Result<Post, AppError> {
@@ -36,29 +94,604 @@ impl BlogService {
         content: String,
         user_id: i64,
     ) -> Result<Post, AppError> {
+        self.maintenance.ensure_writable()?;
+        self.validate_post(&title, &content)?;
+
         let post = self.get_post(post_id).await?;
         if post.author_id != user_id {
             return Err(AppError::Forbidden);
         }
 
-        self.post_repo
+        let post = self
+            .post_repo
             .update_post(post_id, title, content, user_id)
-            .await
+            .await?;
+
+        self.publish(PostEvent::Updated(post.clone()));
+        Ok(post)
+    }
+
+    pub async fn patch_post(
+        &self,
+        post_id: i64,
+        title: Option<String>,
+        content: Option<String>,
+        user_id: i64,
+    ) -> Result<Post, AppError> {
+        self.maintenance.ensure_writable()?;
+        if let Some(title) = &title {
+            self.validate_title(title)?;
+        }
+        if let Some(content) = &content {
+            self.validate_content(content)?;
+        }
+
+        let post = self.get_post(post_id).await?;
+        if post.author_id != user_id {
+            return Err(AppError::Forbidden);
+        }
+
+        if title.is_none() && content.is_none() {
+            return Ok(post);
+        }
+
+        let post = self
+            .post_repo
+            .patch_post(post_id, title, content, user_id)
+            .await?;
+
+        self.publish(PostEvent::Updated(post.clone()));
+        Ok(post)
     }
 
     pub async fn delete_post(&self, post_id: i64, user_id: i64) -> Result<(), AppError> {
+        self.maintenance.ensure_writable()?;
+
         let post = self.get_post(post_id).await?;
         if post.author_id != user_id {
             return Err(AppError::Forbidden);
         }
 
-        self.post_repo.delete_post(post_id, user_id).await
+        self.post_repo.delete_post(post_id, user_id).await?;
+
+        self.publish(PostEvent::Deleted { id: post_id });
+        Ok(())
+    }
+
+    /// Gets posts by ids in one query, returning result for every id in the same order
+    pub async fn get_posts_by_ids(
+        &self,
+        post_ids: &[i64],
+    ) -> Result<Vec<Result<Post, AppError>>, AppError> {
+        self.validate_batch(post_ids)?;
+
+        let posts: HashMap<i64, Post> = self
+            .post_repo
+            .get_posts_by_ids(post_ids)
+            .await?
+            .into_iter()
+            .map(|post| (post.id, post))
+            .collect();
+
+        Ok(post_ids
+            .iter()
+            .map(|id| posts.get(id).cloned().ok_or(AppError::PostNotFound))
+            .collect())
+    }
+
+    /// Deletes posts of the user in one query, returning result for every id in the same order
+    ///
+    /// Missing posts and posts of other users are reported without failing the whole batch
+    pub async fn delete_posts(
+        &self,
+        post_ids: &[i64],
+        user_id: i64,
+    ) -> Result<Vec<Result<(), AppError>>, AppError> {
+        self.maintenance.ensure_writable()?;
+        self.validate_batch(post_ids)?;
+
+        let deleted: HashSet<i64> = self
+            .post_repo
+            .delete_posts(post_ids, user_id)
+            .await?
+            .into_iter()
+            .collect();
+        for id in &deleted {
+            self.publish(PostEvent::Deleted { id: *id });
+        }
+
+        // the rest are missing or belong to other users
+        let missing: Vec<i64> = post_ids
+            .iter()
+            .copied()
+            .filter(|id| !deleted.contains(id))
+            .collect();
+        let existing: HashSet<i64> = self
+            .post_repo
+            .get_posts_by_ids(&missing)
+            .await?
+            .into_iter()
+            .map(|post| post.id)
+            .collect();
+
+        Ok(post_ids
+            .iter()
+            .map(|id| {
+                if deleted.contains(id) {
+                    Ok(())
+                } else if existing.contains(id) {
+                    Err(AppError::Forbidden)
+                } else {
+                    Err(AppError::PostNotFound)
+                }
+            })
+            .collect())
+    }
+
+    pub async fn get_posts(&self, limit: i64, offset: i64) -> Result<GetPostsResponse, AppError> {
+        let limit = self.page_limit(limit, offset)?;
+
+        let (posts, total_posts) = self.post_repo.get_posts_page(limit, offset).await?;
+
+        Ok(GetPostsResponse {
+            posts,
+            total_posts,
+            limit,
+            offset,
+            max_limit: self.limits.max_page_limit,
+        })
+    }
+
+    pub async fn search_posts(
+        &self,
+        query: String,
+        limit: i64,
+        offset: i64,
+    ) -> Result<GetPostsResponse, AppError> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Err(AppError::Validation {
+                field: "query",
+                message: String::from("must not be empty"),
+            });
+        }
+
+        let limit = self.page_limit(limit, offset)?;
+
+        let posts = self.post_repo.search_posts(query, limit, offset).await?;
+        let total_posts = self.post_repo.get_search_results_count(query).await?;
+
+        Ok(GetPostsResponse {
+            posts,
+            total_posts,
+            limit,
+            offset,
+            max_limit: self.limits.max_page_limit,
+        })
+    }
+
+    /// Validates page bounds and returns limit capped by configured maximum
+    fn page_limit(&self, limit: i64, offset: i64) -> Result<i64, AppError> {
+        if limit < 0 {
+            return Err(AppError::Validation {
+                field: "limit",
+                message: String::from("must not be negative"),
+            });
+        }
+
+        if offset < 0 {
+            return Err(AppError::Validation {
+                field: "offset",
+                message: String::from("must not be negative"),
+            });
+        }
+
+        Ok(limit.min(self.limits.max_page_limit))
+    }
+
+    /// Batch is limited like a page of posts
+    fn validate_batch(&self, post_ids: &[i64]) -> Result<(), AppError> {
+        if post_ids.len() as i64 > self.limits.max_page_limit {
+            return Err(AppError::Validation {
+                field: "post_ids",
+                message: format!("at most {} ids are allowed", self.limits.max_page_limit),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_post(&self, title: &str, content: &str) -> Result<(), AppError> {
+        self.validate_title(title)?;
+        self.validate_content(content)
+    }
+
+    fn validate_title(&self, title: &str) -> Result<(), AppError> {
+        if title.chars().count() > self.limits.max_title_length {
+            return Err(AppError::FieldTooLong {
+                field: "title",
+                max: self.limits.max_title_length,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_content(&self, content: &str) -> Result<(), AppError> {
+        if content.chars().count() > self.limits.max_content_length {
+            return Err(AppError::FieldTooLong {
+                field: "content",
+                max: self.limits.max_content_length,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, TimeZone, Utc};
+
+    use super::*;
+    use crate::data::{
+        in_memory::{InMemoryPostRepository, InMemoryUserRepository, TestClock},
+        user_repository::UserRepository,
+    };
+
+    const LIMITS: PostLimits = PostLimits {
+        max_title_length: 10,
+        max_content_length: 100,
+        max_page_limit: 3,
+    };
+
+    struct Fixture {
+        service: BlogService,
+        clock: Arc<TestClock>,
+        alice: i64,
+        bob: i64,
+    }
+
+    async fn fixture() -> Fixture {
+        let clock = TestClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+        let users = InMemoryUserRepository::new(clock.clone());
+        let alice = users.save_user("alice", "alice@example.com", "hash").await;
+        let bob = users.save_user("bob", "bob@example.com", "hash").await;
+
+        let posts = InMemoryPostRepository::new(users, clock.clone());
+        let service = BlogService::new(posts, LIMITS, Arc::new(Maintenance::new(false, None)));
+
+        Fixture {
+            service,
+            clock,
+            alice: alice.unwrap().id,
+            bob: bob.unwrap().id,
+        }
+    }
+
+    impl Fixture {
+        async fn create(&self, title: &str, author_id: i64) -> Post {
+            let post = self
+                .service
+                .create_post(title.to_string(), String::from("content"), author_id)
+                .await
+                .unwrap();
+            self.clock.advance(TimeDelta::minutes(1));
+            post
+        }
+    }
+
+    #[tokio::test]
+    async fn created_post_is_returned_with_author() {
+        let f = fixture().await;
+        let created = f.create("first", f.alice).await;
+
+        let post = f.service.get_post(created.id).await.unwrap();
+
+        assert_eq!(post.id, 1);
+        assert_eq!(post.title, "first");
+        assert_eq!(post.author_username, "alice");
+    }
+
+    #[tokio::test]
+    async fn missing_post_is_not_found() {
+        let f = fixture().await;
+
+        let result = f.service.get_post(42).await;
+
+        assert!(matches!(result, Err(AppError::PostNotFound)));
+    }
+
+    #[tokio::test]
+    async fn too_long_title_is_rejected() {
+        let f = fixture().await;
+
+        let result = f
+            .service
+            .create_post("a".repeat(11), String::new(), f.alice)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(AppError::FieldTooLong {
+                field: "title",
+                max: 10
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn only_author_updates_post() {
+        let f = fixture().await;
+        let post = f.create("first", f.alice).await;
+
+        let result = f
+            .service
+            .update_post(post.id, String::from("edited"), String::new(), f.bob)
+            .await;
+
+        assert!(matches!(result, Err(AppError::Forbidden)));
+        assert_eq!(f.service.get_post(post.id).await.unwrap().title, "first");
+    }
+
+    #[tokio::test]
+    async fn patch_keeps_omitted_fields_and_sets_update_time() {
+        let f = fixture().await;
+        let post = f.create("first", f.alice).await;
+
+        let patched = f
+            .service
+            .patch_post(post.id, Some(String::from("edited")), None, f.alice)
+            .await
+            .unwrap();
+
+        assert_eq!(patched.title, "edited");
+        assert_eq!(patched.content, "content");
+        assert_eq!(patched.created_at, post.created_at);
+        assert_eq!(patched.updated_at, post.created_at + TimeDelta::minutes(1));
+    }
+
+    #[tokio::test]
+    async fn posts_are_listed_newest_first_with_capped_limit() {
+        let f = fixture().await;
+        for title in ["1", "2", "3", "4", "5"] {
+            f.create(title, f.alice).await;
+        }
+
+        let page = f.service.get_posts(10, 1).await.unwrap();
+
+        let titles: Vec<&str> = page.posts.iter().map(|post| post.title.as_str()).collect();
+        assert_eq!(titles, ["4", "3", "2"]);
+        assert_eq!(page.total_posts, 5);
+        assert_eq!(page.limit, 3);
+        assert_eq!(page.max_limit, 3);
+    }
+
+    #[tokio::test]
+    async fn negative_offset_is_rejected() {
+        let f = fixture().await;
+
+        let result = f.service.get_posts(10, -1).await;
+
+        assert!(matches!(
+            result,
+            Err(AppError::Validation {
+                field: "offset",
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn empty_search_query_is_rejected() {
+        let f = fixture().await;
+
+        let result = f.service.search_posts(String::from("  "), 10, 0).await;
+
+        assert!(matches!(
+            result,
+            Err(AppError::Validation { field: "query", .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn batch_get_keeps_order_of_ids() {
+        let f = fixture().await;
+        let first = f.create("first", f.alice).await;
+        let second = f.create("second", f.bob).await;
+
+        let results = f
+            .service
+            .get_posts_by_ids(&[second.id, 42, first.id])
+            .await
+            .unwrap();
+
+        assert_eq!(results[0].as_ref().unwrap().title, "second");
+        assert!(matches!(results[1], Err(AppError::PostNotFound)));
+        assert_eq!(results[2].as_ref().unwrap().title, "first");
+    }
+
+    #[tokio::test]
+    async fn batch_delete_reports_every_post() {
+        let f = fixture().await;
+        let own = f.create("own", f.alice).await;
+        let other = f.create("other", f.bob).await;
+
+        let results = f
+            .service
+            .delete_posts(&[own.id, other.id, 42], f.alice)
+            .await
+            .unwrap();
+
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(AppError::Forbidden)));
+        assert!(matches!(results[2], Err(AppError::PostNotFound)));
+        assert!(matches!(
+            f.service.get_post(own.id).await,
+            Err(AppError::PostNotFound)
+        ));
+        assert!(f.service.get_post(other.id).await.is_ok());
     }
 
-    pub async fn get_posts(&self, limit: i64, offset: i64) -> Result<(Vec<Post>, u64), AppError> {
-        let posts = self.post_repo.get_posts(limit, offset).await?;
-        let total_posts = self.post_repo.get_total_posts_count().await?;
+    #[tokio::test]
+    async fn batch_larger_than_page_limit_is_rejected() {
+        let f = fixture().await;
+
+        let result = f.service.get_posts_by_ids(&[1, 2, 3, 4]).await;
+
+        assert!(matches!(
+            result,
+            Err(AppError::Validation {
+                field: "post_ids",
+                ..
+            })
+        ));
+    }
 
-        Ok((posts, total_posts))
+    /// Properties checked on generated inputs, each case runs on its own runtime
+    mod properties {
+        use proptest::prelude::*;
+
+        use super::*;
+
+        fn block_on<F: Future>(future: F) -> F::Output {
+            tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap()
+                .block_on(future)
+        }
+
+        async fn fixture_with_posts(count: usize) -> Fixture {
+            let f = fixture().await;
+            for i in 0..count {
+                f.create(&format!("post {i}"), f.alice).await;
+            }
+            f
+        }
+
+        proptest! {
+            #[test]
+            fn any_page_bounds_are_validated_without_panics(
+                count in 0usize..8,
+                limit in any::<i64>(),
+                offset in any::<i64>(),
+            ) {
+                block_on(async {
+                    let f = fixture_with_posts(count).await;
+
+                    match f.service.get_posts(limit, offset).await {
+                        Ok(page) => {
+                            prop_assert!(limit >= 0 && offset >= 0);
+                            prop_assert_eq!(page.limit, limit.min(LIMITS.max_page_limit));
+                            prop_assert_eq!(page.offset, offset);
+                            prop_assert_eq!(page.total_posts, count as u64);
+                            let expected =
+                                (count as i64).saturating_sub(offset).clamp(0, page.limit);
+                            prop_assert_eq!(page.posts.len() as i64, expected);
+                        }
+                        Err(AppError::Validation { field, .. }) => {
+                            prop_assert_eq!(field, if limit < 0 { "limit" } else { "offset" });
+                            prop_assert!(limit < 0 || offset < 0);
+                        }
+                        Err(error) => prop_assert!(false, "unexpected error {error:?}"),
+                    }
+                    Ok(())
+                })?;
+            }
+
+            #[test]
+            fn pages_cover_all_posts_newest_first(count in 0usize..12, limit in 1i64..6) {
+                block_on(async {
+                    let f = fixture_with_posts(count).await;
+
+                    let mut ids = Vec::new();
+                    let mut offset = 0;
+                    loop {
+                        let page = f.service.get_posts(limit, offset).await.unwrap();
+                        prop_assert_eq!(page.total_posts, count as u64);
+                        if page.posts.is_empty() {
+                            break;
+                        }
+                        offset += page.posts.len() as i64;
+                        ids.extend(page.posts.iter().map(|post| post.id));
+                    }
+
+                    let expected: Vec<i64> = (1..=count as i64).rev().collect();
+                    prop_assert_eq!(ids, expected);
+                    Ok(())
+                })?;
+            }
+
+            #[test]
+            fn search_total_counts_every_page(
+                query in "\\PC{0,6}",
+                limit in 0i64..6,
+                offset in 0i64..6,
+            ) {
+                block_on(async {
+                    let f = fixture().await;
+                    for title in ["rust", "Rust web", "web", "ÜBER rust", "日本語"] {
+                        f.create(title, f.alice).await;
+                    }
+
+                    match f.service.search_posts(query.clone(), limit, offset).await {
+                        Ok(page) => {
+                            let first = f.service.search_posts(query, 1, 0).await.unwrap();
+                            prop_assert_eq!(page.total_posts, first.total_posts);
+                            let end = offset as u64 + page.posts.len() as u64;
+                            prop_assert!(page.posts.len() as i64 <= page.limit);
+                            prop_assert!(page.posts.is_empty() || end <= page.total_posts);
+                        }
+                        Err(AppError::Validation { field, .. }) => {
+                            prop_assert_eq!(field, "query");
+                            prop_assert!(query.trim().is_empty());
+                        }
+                        Err(error) => prop_assert!(false, "unexpected error {error:?}"),
+                    }
+                    Ok(())
+                })?;
+            }
+
+            #[test]
+            fn unicode_title_is_limited_by_chars_and_kept_intact(title in "\\PC{0,16}") {
+                block_on(async {
+                    let f = fixture().await;
+
+                    let result = f.service.create_post(title.clone(), String::new(), f.alice).await;
+
+                    if title.chars().count() <= LIMITS.max_title_length {
+                        let post = f.service.get_post(result.unwrap().id).await.unwrap();
+                        prop_assert_eq!(post.title, title);
+                    } else {
+                        prop_assert!(
+                            matches!(result, Err(AppError::FieldTooLong { field: "title", .. })),
+                            "unexpected result {:?}",
+                            result
+                        );
+                    }
+                    Ok(())
+                })?;
+            }
+
+            #[test]
+            fn unicode_username_is_returned_as_author(username in "\\PC{1,32}") {
+                block_on(async {
+                    let clock = TestClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+                    let users = InMemoryUserRepository::new(clock.clone());
+                    let user = users.save_user(&username, "user@example.com", "hash").await;
+                    let posts = InMemoryPostRepository::new(users, clock);
+                    let maintenance = Arc::new(Maintenance::new(false, None));
+                    let service = BlogService::new(posts, LIMITS, maintenance);
+
+                    let post = service
+                        .create_post(String::from("title"), String::new(), user.unwrap().id)
+                        .await
+                        .unwrap();
+
+                    prop_assert_eq!(post.author_username, username);
+                    Ok(())
+                })?;
+            }
+        }
     }
 }
@@ -0,0 +1,26 @@
+use crate::domain::error::AppError;
+
+const DEFAULT_MESSAGE: &str = "Service is in read-only maintenance mode, try again later";
+
+/// Read-only switch checked by every write operation
+pub struct Maintenance {
+    read_only: bool,
+    message: String,
+}
+
+impl Maintenance {
+    pub fn new(read_only: bool, message: Option<String>) -> Self {
+        Self {
+            read_only,
+            message: message.unwrap_or_else(|| String::from(DEFAULT_MESSAGE)),
+        }
+    }
+
+    pub fn ensure_writable(&self) -> Result<(), AppError> {
+        if self.read_only {
+            Err(AppError::Maintenance(self.message.clone()))
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -1,2 +1,4 @@
 pub mod auth_service;
 pub mod blog_service;
+pub mod maintenance;
+pub mod stats_service;
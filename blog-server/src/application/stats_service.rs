@@ -0,0 +1,68 @@
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+use crate::{
+    data::stats_repository::StatsRepository,
+    domain::{
+        error::AppError,
+        stats::{Stats, UserStats},
+    },
+};
+
+const STATS_DAYS: i32 = 30;
+const TOP_AUTHORS_COUNT: i64 = 10;
+
+pub struct StatsService {
+    stats_repo: StatsRepository,
+    cache_ttl: Duration,
+    cache: Mutex<Option<(Instant, Stats)>>,
+}
+
+impl StatsService {
+    pub fn new(stats_repo: StatsRepository, cache_ttl: Duration) -> Self {
+        Self {
+            stats_repo,
+            cache_ttl,
+            cache: Mutex::new(None),
+        }
+    }
+
+    pub async fn get_stats(&self) -> Result<Stats, AppError> {
+        // lock is held while stats are recalculated so concurrent requests wait for one query set
+        let mut cache = self.cache.lock().await;
+
+        if let Some((calculated_at, stats)) = cache.as_ref()
+            && calculated_at.elapsed() < self.cache_ttl
+        {
+            return Ok(stats.clone());
+        }
+
+        let stats = Stats {
+            total_users: self.stats_repo.get_total_users_count().await?,
+            total_posts: self.stats_repo.get_total_posts_count().await?,
+            posts_per_day: self.stats_repo.get_posts_per_day(STATS_DAYS).await?,
+            top_authors: self.stats_repo.get_top_authors(TOP_AUTHORS_COUNT).await?,
+        };
+
+        *cache = Some((Instant::now(), stats.clone()));
+
+        Ok(stats)
+    }
+
+    /// Stats of single user are not cached, they are requested only by the user
+    pub async fn get_user_stats(&self, user_id: i64) -> Result<UserStats, AppError> {
+        // token of removed user is no longer valid
+        let mut stats = self
+            .stats_repo
+            .get_user_stats(user_id)
+            .await?
+            .ok_or(AppError::InvalidToken)?;
+        stats.posts_per_day = self
+            .stats_repo
+            .get_user_posts_per_day(user_id, STATS_DAYS)
+            .await?;
+
+        Ok(stats)
+    }
+}
@@ -0,0 +1,287 @@
+//! Repositories keeping data in memory, used by unit tests of services
+//!
+//! Ids are assigned sequentially starting from 1 and timestamps are taken from
+//! injected [`Clock`], usually [`TestClock`], so test results don't depend on
+//! a database or real time
+
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicI64, Ordering},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{
+    data::{post_repository::PostRepository, user_repository::UserRepository},
+    domain::{error::AppError, post::Post, user::User},
+    infrastructure::clock::Clock,
+};
+
+/// Clock moved only by tests
+pub struct TestClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl TestClock {
+    pub fn new(now: DateTime<Utc>) -> Arc<Self> {
+        Arc::new(Self {
+            now: Mutex::new(now),
+        })
+    }
+
+    pub fn advance(&self, delta: TimeDelta) {
+        *self.now.lock().unwrap() += delta;
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+pub struct InMemoryUserRepository {
+    users: Mutex<Vec<User>>,
+    next_id: AtomicI64,
+    clock: Arc<dyn Clock>,
+}
+
+impl InMemoryUserRepository {
+    pub fn new(clock: Arc<dyn Clock>) -> Arc<Self> {
+        Arc::new(Self {
+            users: Mutex::new(Vec::new()),
+            next_id: AtomicI64::new(1),
+            clock,
+        })
+    }
+
+    fn username(&self, user_id: i64) -> Option<String> {
+        let users = self.users.lock().unwrap();
+        let user = users.iter().find(|user| user.id == user_id)?;
+        Some(user.username.clone())
+    }
+}
+
+#[async_trait]
+impl UserRepository for InMemoryUserRepository {
+    async fn get_by_username(&self, username: &str) -> Result<Option<User>, AppError> {
+        let users = self.users.lock().unwrap();
+        Ok(users
+            .iter()
+            .find(|user| user.username == username)
+            .map(copy_user))
+    }
+
+    async fn save_user(
+        &self,
+        username: &str,
+        email: &str,
+        password_hash: &str,
+    ) -> Result<User, AppError> {
+        let mut users = self.users.lock().unwrap();
+        // unique constraints of the users table
+        if users
+            .iter()
+            .any(|user| user.username == username || user.email == email)
+        {
+            return Err(AppError::UserAlreadyExists);
+        }
+
+        let user = User {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            username: username.to_string(),
+            email: email.to_string(),
+            password_hash: password_hash.to_string(),
+            created_at: self.clock.now(),
+        };
+        users.push(copy_user(&user));
+        Ok(user)
+    }
+}
+
+/// Posts of users from [`InMemoryUserRepository`], in creation order
+pub struct InMemoryPostRepository {
+    posts: Mutex<Vec<Post>>,
+    next_id: AtomicI64,
+    users: Arc<InMemoryUserRepository>,
+    clock: Arc<dyn Clock>,
+}
+
+impl InMemoryPostRepository {
+    pub fn new(users: Arc<InMemoryUserRepository>, clock: Arc<dyn Clock>) -> Arc<Self> {
+        Arc::new(Self {
+            posts: Mutex::new(Vec::new()),
+            next_id: AtomicI64::new(1),
+            users,
+            clock,
+        })
+    }
+
+    /// Changes post of the author, like `UPDATE ... WHERE id = $1 AND author_id = $2`
+    /// followed by `fetch_one`
+    fn modify(
+        &self,
+        post_id: i64,
+        author_id: i64,
+        change: impl FnOnce(&mut Post),
+    ) -> Result<Post, AppError> {
+        let mut posts = self.posts.lock().unwrap();
+        let post = posts
+            .iter_mut()
+            .find(|post| post.id == post_id && post.author_id == author_id)
+            .ok_or(AppError::SqlxError(sqlx::Error::RowNotFound))?;
+        change(post);
+        post.updated_at = self.clock.now();
+        Ok(post.clone())
+    }
+
+    fn matching(&self, query: &str) -> Vec<Post> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        let mut posts: Vec<Post> = self
+            .posts
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|post| {
+                let text = format!("{} {}", post.title, post.content).to_lowercase();
+                words.iter().all(|word| text.contains(word.as_str()))
+            })
+            .cloned()
+            .collect();
+        posts.reverse();
+        posts
+    }
+}
+
+#[async_trait]
+impl PostRepository for InMemoryPostRepository {
+    async fn create_post(
+        &self,
+        title: String,
+        content: String,
+        author_id: i64,
+    ) -> Result<Post, AppError> {
+        let author_username = self
+            .users
+            .username(author_id)
+            .ok_or(AppError::SqlxError(sqlx::Error::RowNotFound))?;
+        let now = self.clock.now();
+        let post = Post {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            title,
+            content,
+            author_id,
+            author_username,
+            created_at: now,
+            updated_at: now,
+        };
+        self.posts.lock().unwrap().push(post.clone());
+        Ok(post)
+    }
+
+    async fn get_post(&self, post_id: i64) -> Result<Option<Post>, AppError> {
+        let posts = self.posts.lock().unwrap();
+        Ok(posts.iter().find(|post| post.id == post_id).cloned())
+    }
+
+    async fn get_posts_by_ids(&self, post_ids: &[i64]) -> Result<Vec<Post>, AppError> {
+        let posts = self.posts.lock().unwrap();
+        Ok(posts
+            .iter()
+            .filter(|post| post_ids.contains(&post.id))
+            .cloned()
+            .collect())
+    }
+
+    async fn update_post(
+        &self,
+        post_id: i64,
+        title: String,
+        content: String,
+        author_id: i64,
+    ) -> Result<Post, AppError> {
+        self.modify(post_id, author_id, |post| {
+            post.title = title;
+            post.content = content;
+        })
+    }
+
+    async fn patch_post(
+        &self,
+        post_id: i64,
+        title: Option<String>,
+        content: Option<String>,
+        author_id: i64,
+    ) -> Result<Post, AppError> {
+        self.modify(post_id, author_id, |post| {
+            if let Some(title) = title {
+                post.title = title;
+            }
+            if let Some(content) = content {
+                post.content = content;
+            }
+        })
+    }
+
+    async fn delete_post(&self, post_id: i64, author_id: i64) -> Result<(), AppError> {
+        let mut posts = self.posts.lock().unwrap();
+        posts.retain(|post| !(post.id == post_id && post.author_id == author_id));
+        Ok(())
+    }
+
+    async fn delete_posts(&self, post_ids: &[i64], author_id: i64) -> Result<Vec<i64>, AppError> {
+        let mut posts = self.posts.lock().unwrap();
+        let mut deleted = Vec::new();
+        posts.retain(|post| {
+            let delete = post_ids.contains(&post.id) && post.author_id == author_id;
+            if delete {
+                deleted.push(post.id);
+            }
+            !delete
+        });
+        Ok(deleted)
+    }
+
+    async fn get_posts_page(&self, limit: i64, offset: i64) -> Result<(Vec<Post>, u64), AppError> {
+        let posts = self.posts.lock().unwrap();
+        let page = posts
+            .iter()
+            .rev()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect();
+        Ok((page, posts.len() as u64))
+    }
+
+    /// Posts containing every word of the query, newest first
+    async fn search_posts(
+        &self,
+        query: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Post>, AppError> {
+        Ok(self
+            .matching(query)
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect())
+    }
+
+    async fn get_search_results_count(&self, query: &str) -> Result<u64, AppError> {
+        Ok(self.matching(query).len() as u64)
+    }
+}
+
+/// `User` is not `Clone` so password hashes aren't copied around by accident
+fn copy_user(user: &User) -> User {
+    User {
+        id: user.id,
+        username: user.username.clone(),
+        email: user.email.clone(),
+        password_hash: user.password_hash.clone(),
+        created_at: user.created_at,
+    }
+}
@@ -1,2 +1,5 @@
+#[cfg(test)]
+pub mod in_memory;
 pub mod post_repository;
+pub mod stats_repository;
 pub mod user_repository;
@@ -1,42 +1,110 @@
 use std::sync::Arc;
 
-use sqlx::PgPool;
+use async_trait::async_trait;
+use sqlx::{FromRow, PgPool, Row};
 
-use crate::domain::{error::AppError, post::Post};
+use crate::{
+    domain::{error::AppError, post::Post},
+    infrastructure::clock::Clock,
+};
 
-pub struct PostRepository {
+/// Storage of posts
+#[async_trait]
+pub trait PostRepository: Send + Sync {
+    async fn create_post(
+        &self,
+        title: String,
+        content: String,
+        author_id: i64,
+    ) -> Result<Post, AppError>;
+
+    async fn get_post(&self, post_id: i64) -> Result<Option<Post>, AppError>;
+
+    /// Posts with given ids in no particular order, missing posts are skipped
+    async fn get_posts_by_ids(&self, post_ids: &[i64]) -> Result<Vec<Post>, AppError>;
+
+    async fn update_post(
+        &self,
+        post_id: i64,
+        title: String,
+        content: String,
+        author_id: i64,
+    ) -> Result<Post, AppError>;
+
+    /// Updates only provided fields, keeping the others unchanged
+    async fn patch_post(
+        &self,
+        post_id: i64,
+        title: Option<String>,
+        content: Option<String>,
+        author_id: i64,
+    ) -> Result<Post, AppError>;
+
+    async fn delete_post(&self, post_id: i64, author_id: i64) -> Result<(), AppError>;
+
+    /// Deletes posts of the author with given ids and returns ids of the deleted ones
+    async fn delete_posts(&self, post_ids: &[i64], author_id: i64) -> Result<Vec<i64>, AppError>;
+
+    /// Page of posts, newest first, with total count of posts
+    async fn get_posts_page(&self, limit: i64, offset: i64) -> Result<(Vec<Post>, u64), AppError>;
+
+    /// Full-text search over title and content, most relevant posts first
+    async fn search_posts(
+        &self,
+        query: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Post>, AppError>;
+
+    async fn get_search_results_count(&self, query: &str) -> Result<u64, AppError>;
+}
+
+/// Posts stored in PostgreSQL, creation and update times are taken from the clock
+pub struct PgPostRepository {
     db_pool: Arc<PgPool>,
+    clock: Arc<dyn Clock>,
 }
 
-impl PostRepository {
-    pub fn new(db_pool: Arc<PgPool>) -> Self {
-        Self { db_pool }
+impl PgPostRepository {
+    pub fn new(db_pool: Arc<PgPool>, clock: Arc<dyn Clock>) -> Self {
+        Self { db_pool, clock }
     }
+}
 
-    pub async fn create_post(
+#[async_trait]
+impl PostRepository for PgPostRepository {
+    async fn create_post(
         &self,
         title: String,
         content: String,
         author_id: i64,
     ) -> Result<Post, AppError> {
         let query = "
-            INSERT INTO posts (title, content, author_id)
-            VALUES ($1, $2, $3)
-            RETURNING id, title, content, author_id, created_at, updated_at";
+            WITH p AS (
+                INSERT INTO posts (title, content, author_id, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $4)
+                RETURNING id, title, content, author_id, created_at, updated_at
+            )
+            SELECT p.id, p.title, p.content, p.author_id, u.username AS author_username,
+                p.created_at, p.updated_at
+            FROM p JOIN users u ON u.id = p.author_id";
 
         sqlx::query_as(query)
             .bind(title)
             .bind(content)
             .bind(author_id)
+            .bind(self.clock.now())
             .fetch_one(&*self.db_pool)
             .await
             .map_err(AppError::from)
     }
 
-    pub async fn get_post(&self, post_id: i64) -> Result<Option<Post>, AppError> {
+    async fn get_post(&self, post_id: i64) -> Result<Option<Post>, AppError> {
         sqlx::query_as(
-            "SELECT id, title, content, author_id, created_at, updated_at 
-            FROM posts WHERE id = $1",
+            "SELECT p.id, p.title, p.content, p.author_id, u.username AS author_username,
+                p.created_at, p.updated_at
+            FROM posts p JOIN users u ON u.id = p.author_id
+            WHERE p.id = $1",
         )
         .bind(post_id)
         .fetch_optional(&*self.db_pool)
@@ -44,29 +112,76 @@ impl PostRepository {
         .map_err(AppError::from)
     }
 
-    pub async fn update_post(
+    async fn get_posts_by_ids(&self, post_ids: &[i64]) -> Result<Vec<Post>, AppError> {
+        sqlx::query_as(
+            "SELECT p.id, p.title, p.content, p.author_id, u.username AS author_username,
+                p.created_at, p.updated_at
+            FROM posts p JOIN users u ON u.id = p.author_id
+            WHERE p.id = ANY($1)",
+        )
+        .bind(post_ids)
+        .fetch_all(&*self.db_pool)
+        .await
+        .map_err(AppError::from)
+    }
+
+    async fn update_post(
         &self,
         post_id: i64,
         title: String,
         content: String,
         author_id: i64,
     ) -> Result<Post, AppError> {
-        let query = "UPDATE posts 
-        SET title = $2, content = $3, updated_at = NOW() 
-        WHERE id = $1 AND author_id = $4 
-        RETURNING id, title, content, author_id, created_at, updated_at";
+        let query = "WITH p AS (
+            UPDATE posts
+            SET title = $2, content = $3, updated_at = $5
+            WHERE id = $1 AND author_id = $4
+            RETURNING id, title, content, author_id, created_at, updated_at
+        )
+        SELECT p.id, p.title, p.content, p.author_id, u.username AS author_username,
+            p.created_at, p.updated_at
+        FROM p JOIN users u ON u.id = p.author_id";
 
         sqlx::query_as(query)
             .bind(post_id)
             .bind(title)
             .bind(content)
             .bind(author_id)
+            .bind(self.clock.now())
             .fetch_one(&*self.db_pool)
             .await
             .map_err(AppError::from)
     }
 
-    pub async fn delete_post(&self, post_id: i64, author_id: i64) -> Result<(), AppError> {
+    async fn patch_post(
+        &self,
+        post_id: i64,
+        title: Option<String>,
+        content: Option<String>,
+        author_id: i64,
+    ) -> Result<Post, AppError> {
+        let query = "WITH p AS (
+            UPDATE posts
+            SET title = COALESCE($2, title), content = COALESCE($3, content), updated_at = $5
+            WHERE id = $1 AND author_id = $4
+            RETURNING id, title, content, author_id, created_at, updated_at
+        )
+        SELECT p.id, p.title, p.content, p.author_id, u.username AS author_username,
+            p.created_at, p.updated_at
+        FROM p JOIN users u ON u.id = p.author_id";
+
+        sqlx::query_as(query)
+            .bind(post_id)
+            .bind(title)
+            .bind(content)
+            .bind(author_id)
+            .bind(self.clock.now())
+            .fetch_one(&*self.db_pool)
+            .await
+            .map_err(AppError::from)
+    }
+
+    async fn delete_post(&self, post_id: i64, author_id: i64) -> Result<(), AppError> {
         let query = "DELETE FROM posts
             WHERE id = $1 AND author_id = $2";
 
@@ -80,13 +195,69 @@ impl PostRepository {
         Ok(())
     }
 
-    pub async fn get_posts(&self, limit: i64, offset: i64) -> Result<Vec<Post>, AppError> {
-        let query = "SELECT id, title, content, author_id, created_at, updated_at
-            FROM posts
-            ORDER BY created_at DESC 
-            LIMIT $1 OFFSET $2";
+    async fn delete_posts(&self, post_ids: &[i64], author_id: i64) -> Result<Vec<i64>, AppError> {
+        let query = "DELETE FROM posts
+            WHERE id = ANY($1) AND author_id = $2
+            RETURNING id";
 
-        sqlx::query_as(query)
+        sqlx::query_scalar(query)
+            .bind(post_ids)
+            .bind(author_id)
+            .fetch_all(&*self.db_pool)
+            .await
+            .map_err(AppError::from)
+    }
+
+    /// One round trip: the count is joined to the page, so a page past the end
+    /// still returns a single row with the count and NULL post columns
+    async fn get_posts_page(&self, limit: i64, offset: i64) -> Result<(Vec<Post>, u64), AppError> {
+        let query = "SELECT total.total_posts, page.*
+            FROM (SELECT COUNT(*) AS total_posts FROM posts) total
+            LEFT JOIN LATERAL (
+                SELECT p.id, p.title, p.content, p.author_id, u.username AS author_username,
+                    p.created_at, p.updated_at
+                FROM posts p JOIN users u ON u.id = p.author_id
+                ORDER BY p.created_at DESC, p.id DESC
+                LIMIT $1 OFFSET $2
+            ) page ON true
+            ORDER BY page.created_at DESC, page.id DESC";
+
+        let rows = sqlx::query(query)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&*self.db_pool)
+            .await?;
+
+        let total_posts = match rows.first() {
+            Some(row) => row.try_get::<i64, _>("total_posts")? as u64,
+            None => 0,
+        };
+        let mut posts = Vec::with_capacity(rows.len());
+        for row in &rows {
+            if row.try_get::<Option<i64>, _>("id")?.is_some() {
+                posts.push(Post::from_row(row)?);
+            }
+        }
+
+        Ok((posts, total_posts))
+    }
+
+    async fn search_posts(
+        &self,
+        query: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Post>, AppError> {
+        let query_sql = "SELECT p.id, p.title, p.content, p.author_id, u.username AS author_username,
+                p.created_at, p.updated_at
+            FROM posts p JOIN users u ON u.id = p.author_id, plainto_tsquery('simple', $1) query
+            WHERE to_tsvector('simple', coalesce(p.title, '') || ' ' || coalesce(p.content, '')) @@ query
+            ORDER BY ts_rank(to_tsvector('simple', coalesce(p.title, '') || ' ' || coalesce(p.content, '')), query) DESC,
+                p.created_at DESC, p.id DESC
+            LIMIT $2 OFFSET $3";
+
+        sqlx::query_as(query_sql)
+            .bind(query)
             .bind(limit)
             .bind(offset)
             .fetch_all(&*self.db_pool)
@@ -94,9 +265,14 @@ impl PostRepository {
             .map_err(AppError::from)
     }
 
-    pub async fn get_total_posts_count(&self) -> Result<u64, AppError> {
-        let query = "SELECT COUNT(*) FROM posts";
-        sqlx::query_scalar(query)
+    async fn get_search_results_count(&self, query: &str) -> Result<u64, AppError> {
+        let query_sql = "SELECT COUNT(*)
+            FROM posts
+            WHERE to_tsvector('simple', coalesce(title, '') || ' ' || coalesce(content, ''))
+                @@ plainto_tsquery('simple', $1)";
+
+        sqlx::query_scalar(query_sql)
+            .bind(query)
             .fetch_one(&*self.db_pool)
             .await
             .map(|count: i64| count as u64)
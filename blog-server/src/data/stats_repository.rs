@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use sqlx::PgPool;
+
+use crate::domain::{
+    error::AppError,
+    stats::{AuthorStats, DailyPosts, UserStats},
+};
+
+pub struct StatsRepository {
+    db_pool: Arc<PgPool>,
+}
+
+impl StatsRepository {
+    pub fn new(db_pool: Arc<PgPool>) -> Self {
+        Self { db_pool }
+    }
+
+    pub async fn get_total_users_count(&self) -> Result<u64, AppError> {
+        let query = "SELECT COUNT(*) FROM users";
+        sqlx::query_scalar(query)
+            .fetch_one(&*self.db_pool)
+            .await
+            .map(|count: i64| count as u64)
+            .map_err(AppError::from)
+    }
+
+    pub async fn get_total_posts_count(&self) -> Result<u64, AppError> {
+        let query = "SELECT COUNT(*) FROM posts";
+        sqlx::query_scalar(query)
+            .fetch_one(&*self.db_pool)
+            .await
+            .map(|count: i64| count as u64)
+            .map_err(AppError::from)
+    }
+
+    pub async fn get_posts_per_day(&self, days: i32) -> Result<Vec<DailyPosts>, AppError> {
+        let query = "SELECT d::date AS day, COUNT(p.id) AS posts
+            FROM generate_series(CURRENT_DATE - ($1 - 1), CURRENT_DATE, INTERVAL '1 day') AS d
+            LEFT JOIN posts p ON p.created_at::date = d::date
+            GROUP BY d
+            ORDER BY d";
+
+        sqlx::query_as(query)
+            .bind(days)
+            .fetch_all(&*self.db_pool)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn get_user_posts_per_day(
+        &self,
+        user_id: i64,
+        days: i32,
+    ) -> Result<Vec<DailyPosts>, AppError> {
+        let query = "SELECT d::date AS day, COUNT(p.id) AS posts
+            FROM generate_series(CURRENT_DATE - ($1 - 1), CURRENT_DATE, INTERVAL '1 day') AS d
+            LEFT JOIN posts p ON p.created_at::date = d::date AND p.author_id = $2
+            GROUP BY d
+            ORDER BY d";
+
+        sqlx::query_as(query)
+            .bind(days)
+            .bind(user_id)
+            .fetch_all(&*self.db_pool)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn get_user_stats(&self, user_id: i64) -> Result<Option<UserStats>, AppError> {
+        let query = "SELECT u.id AS user_id, u.username, COUNT(p.id) AS posts,
+                MAX(p.created_at) AS last_post_at
+            FROM users u
+            LEFT JOIN posts p ON p.author_id = u.id
+            WHERE u.id = $1
+            GROUP BY u.id, u.username";
+
+        sqlx::query_as(query)
+            .bind(user_id)
+            .fetch_optional(&*self.db_pool)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn get_top_authors(&self, limit: i64) -> Result<Vec<AuthorStats>, AppError> {
+        let query = "SELECT u.id AS user_id, u.username, COUNT(p.id) AS posts
+            FROM users u
+            JOIN posts p ON p.author_id = u.id
+            GROUP BY u.id, u.username
+            ORDER BY posts DESC, u.id
+            LIMIT $1";
+
+        sqlx::query_as(query)
+            .bind(limit)
+            .fetch_all(&*self.db_pool)
+            .await
+            .map_err(AppError::from)
+    }
+}
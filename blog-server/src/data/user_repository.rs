@@ -1,19 +1,37 @@
 use std::sync::Arc;
 
+use async_trait::async_trait;
 use sqlx::PgPool;
 
 use crate::domain::{error::AppError, user::User};
 
-pub struct UserRepository {
+/// Storage of users
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn get_by_username(&self, username: &str) -> Result<Option<User>, AppError>;
+
+    async fn save_user(
+        &self,
+        username: &str,
+        email: &str,
+        password_hash: &str,
+    ) -> Result<User, AppError>;
+}
+
+/// Users stored in PostgreSQL
+pub struct PgUserRepository {
     db_pool: Arc<PgPool>,
 }
 
-impl UserRepository {
+impl PgUserRepository {
     pub fn new(db_pool: Arc<PgPool>) -> Self {
         Self { db_pool }
     }
+}
 
-    pub async fn get_by_username(&self, username: &str) -> Result<Option<User>, AppError> {
+#[async_trait]
+impl UserRepository for PgUserRepository {
+    async fn get_by_username(&self, username: &str) -> Result<Option<User>, AppError> {
         sqlx::query_as(
             "SELECT id, username, email, password_hash, created_at FROM users WHERE username = $1",
         )
@@ -23,7 +41,7 @@ impl UserRepository {
         .map_err(AppError::from)
     }
 
-    pub async fn save_user(
+    async fn save_user(
         &self,
         username: &str,
         email: &str,
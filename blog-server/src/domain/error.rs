@@ -1,5 +1,6 @@
 use std::{env::VarError, net::AddrParseError};
 
+use serde::Serialize;
 use sqlx::migrate::MigrateError;
 use thiserror::Error;
 
@@ -15,6 +16,15 @@ pub enum AppError {
     PostNotFound,
     #[error("Forbidden: trying to edit another user's post")]
     Forbidden,
+    #[error("Invalid value of \"{field}\": {message}")]
+    Validation {
+        field: &'static str,
+        message: String,
+    },
+    #[error("Payload is too large, limit is {limit} bytes")]
+    PayloadTooLarge { limit: usize },
+    #[error("Field \"{field}\" is too long, maximum length is {max} characters")]
+    FieldTooLong { field: &'static str, max: usize },
     #[error("SQL error: {0}")]
     SqlxError(#[from] sqlx::Error),
     #[error("Migrate error: {0}")]
@@ -31,12 +41,20 @@ pub enum AppError {
     HashError(String),
     #[error("Token is invalid or expired")]
     InvalidToken,
+    #[error("CSRF token is missing or does not match the session")]
+    InvalidCsrfToken,
     #[error("I/O error {0}")]
     Io(#[from] std::io::Error),
     #[error("Unable to parse address {0}")]
     InvalidAddress(#[from] AddrParseError),
+    #[error("{0}")]
+    Maintenance(String),
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
     #[error("GRPC error {0}")]
     GrpcTransport(#[from] tonic::transport::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
 }
 
 impl From<argon2::password_hash::Error> for AppError {
@@ -44,3 +62,69 @@ impl From<argon2::password_hash::Error> for AppError {
         Self::HashError(value.to_string())
     }
 }
+
+/// Stable machine-readable error codes returned to API clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    UserNotFound,
+    UserAlreadyExists,
+    InvalidCredentials,
+    PostNotFound,
+    Forbidden,
+    ValidationFailed,
+    PayloadTooLarge,
+    FieldTooLong,
+    InvalidToken,
+    InvalidCsrfToken,
+    Maintenance,
+    InternalError,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::UserNotFound => "USER_NOT_FOUND",
+            ErrorCode::UserAlreadyExists => "USER_ALREADY_EXISTS",
+            ErrorCode::InvalidCredentials => "INVALID_CREDENTIALS",
+            ErrorCode::PostNotFound => "POST_NOT_FOUND",
+            ErrorCode::Forbidden => "FORBIDDEN",
+            ErrorCode::ValidationFailed => "VALIDATION_FAILED",
+            ErrorCode::PayloadTooLarge => "PAYLOAD_TOO_LARGE",
+            ErrorCode::FieldTooLong => "FIELD_TOO_LONG",
+            ErrorCode::InvalidToken => "INVALID_TOKEN",
+            ErrorCode::InvalidCsrfToken => "INVALID_CSRF_TOKEN",
+            ErrorCode::Maintenance => "MAINTENANCE",
+            ErrorCode::InternalError => "INTERNAL_ERROR",
+        }
+    }
+}
+
+impl AppError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::UserNotFound { .. } => ErrorCode::UserNotFound,
+            AppError::UserAlreadyExists => ErrorCode::UserAlreadyExists,
+            AppError::InvalidCredentials => ErrorCode::InvalidCredentials,
+            AppError::PostNotFound => ErrorCode::PostNotFound,
+            AppError::Forbidden => ErrorCode::Forbidden,
+            AppError::Validation { .. } => ErrorCode::ValidationFailed,
+            AppError::PayloadTooLarge { .. } => ErrorCode::PayloadTooLarge,
+            AppError::FieldTooLong { .. } => ErrorCode::FieldTooLong,
+            AppError::InvalidToken => ErrorCode::InvalidToken,
+            AppError::InvalidCsrfToken => ErrorCode::InvalidCsrfToken,
+            AppError::Maintenance(_) => ErrorCode::Maintenance,
+            _ => ErrorCode::InternalError,
+        }
+    }
+
+    /// Request field that caused the error, reported to clients as a field violation
+    pub fn field(&self) -> Option<&'static str> {
+        match self {
+            AppError::Validation { field, .. } | AppError::FieldTooLong { field, .. } => {
+                Some(field)
+            }
+            _ => None,
+        }
+    }
+}
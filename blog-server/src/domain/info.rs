@@ -0,0 +1,58 @@
+use serde::Serialize;
+
+use crate::domain::post::PostLimits;
+
+/// Version of HTTP and gRPC API, increased on changes incompatible with older clients
+pub const API_VERSION: u32 = 2;
+
+/// Optional features provided by this server, clients hide UI of the missing ones
+pub const FEATURES: &[&str] = &["search", "post_events", "batch_posts", "cookie_sessions"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerInfo {
+    pub version: &'static str,
+    pub api_version: u32,
+    pub features: &'static [&'static str],
+    /// Writes are rejected in maintenance mode
+    pub read_only: bool,
+    pub limits: ServerLimits,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ServerLimits {
+    pub max_title_length: usize,
+    pub max_content_length: usize,
+    pub max_page_limit: i64,
+    /// Largest JSON body of HTTP request in bytes
+    pub max_request_size: usize,
+    /// Largest gRPC message in bytes
+    pub max_grpc_message_size: usize,
+}
+
+impl ServerInfo {
+    pub fn new(limits: ServerLimits, read_only: bool) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            api_version: API_VERSION,
+            features: FEATURES,
+            read_only,
+            limits,
+        }
+    }
+}
+
+impl ServerLimits {
+    pub fn new(
+        post_limits: PostLimits,
+        max_request_size: usize,
+        max_grpc_message_size: usize,
+    ) -> Self {
+        Self {
+            max_title_length: post_limits.max_title_length,
+            max_content_length: post_limits.max_content_length,
+            max_page_limit: post_limits.max_page_limit,
+            max_request_size,
+            max_grpc_message_size,
+        }
+    }
+}
@@ -1,3 +1,5 @@
 pub mod error;
+pub mod info;
 pub mod post;
+pub mod stats;
 pub mod user;
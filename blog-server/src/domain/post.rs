@@ -2,16 +2,26 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Debug, Clone, Serialize, FromRow)]
 pub struct Post {
     pub id: i64,
     pub title: String,
     pub content: String,
     pub author_id: i64,
+    pub author_username: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// Change of a post delivered to subscribers
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PostEvent {
+    Created(Post),
+    Updated(Post),
+    Deleted { id: i64 },
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreatePostParams {
     pub title: String,
@@ -24,6 +34,19 @@ pub struct UpdatePostParams {
     pub content: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PatchPostParams {
+    pub title: Option<String>,
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PostLimits {
+    pub max_title_length: usize,
+    pub max_content_length: usize,
+    pub max_page_limit: i64,
+}
+
 #[derive(Deserialize)]
 pub struct GetPostsParams {
     #[serde(default = "default_limit")]
@@ -33,6 +56,17 @@ pub struct GetPostsParams {
     pub offset: i64,
 }
 
+#[derive(Deserialize)]
+pub struct SearchPostsParams {
+    pub query: String,
+
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+
+    #[serde(default = "default_offset")]
+    pub offset: i64,
+}
+
 fn default_limit() -> i64 {
     10
 }
@@ -47,4 +81,5 @@ pub struct GetPostsResponse {
     pub total_posts: u64,
     pub limit: i64,
     pub offset: i64,
+    pub max_limit: i64,
 }
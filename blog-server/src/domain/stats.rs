@@ -0,0 +1,36 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::prelude::FromRow;
+
+/// Public dashboard aggregates, there is no comments total since the blog has no comments
+#[derive(Debug, Clone, Serialize)]
+pub struct Stats {
+    pub total_users: u64,
+    pub total_posts: u64,
+    pub posts_per_day: Vec<DailyPosts>,
+    pub top_authors: Vec<AuthorStats>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct DailyPosts {
+    pub day: NaiveDate,
+    pub posts: i64,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AuthorStats {
+    pub user_id: i64,
+    pub username: String,
+    pub posts: i64,
+}
+
+/// Activity of one user, `posts_per_day` is filled separately
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct UserStats {
+    pub user_id: i64,
+    pub username: String,
+    pub posts: i64,
+    pub last_post_at: Option<DateTime<Utc>>,
+    #[sqlx(skip)]
+    pub posts_per_day: Vec<DailyPosts>,
+}
@@ -1,5 +1,5 @@
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use sqlx::FromRow;
 
 #[derive(Debug, Serialize, FromRow)]
@@ -11,26 +11,15 @@ pub struct User {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct CreateUserParams {
-    pub username: String,
-    pub email: String,
-    pub password: String,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct LoginParams {
-    pub username: String,
-    pub password: String,
-}
-
 #[derive(Debug, Serialize)]
 pub struct UserAndToken {
     pub user: User,
     pub token: String,
+    /// CSRF token bound to the token of a cookie session
+    pub csrf_token: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct AuthenticatedUser {
     pub user_id: i64,
     pub username: String,
@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+
+/// Source of current time, injected where time matters so tests can freeze it
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Real time of the host
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
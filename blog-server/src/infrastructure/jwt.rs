@@ -1,34 +1,117 @@
-use chrono::{TimeDelta, Utc};
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use std::sync::Arc;
+
+use chrono::TimeDelta;
+use jsonwebtoken::{
+    DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode, errors::ErrorKind,
+};
 use serde::{Deserialize, Serialize};
 use tracing::trace;
 
-use crate::domain::error::AppError;
+use crate::{
+    domain::error::AppError,
+    infrastructure::clock::{Clock, SystemClock},
+};
+
+/// Largest `exp` taken as seconds (year 5138), larger values are most likely milliseconds
+/// and would make the token valid practically forever
+const MAX_EXP_SECONDS: i64 = 100_000_000_000;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub user_id: i64,
     pub username: String,
+    /// Expiration time in seconds since Unix epoch, as required by RFC 7519
     pub exp: i64,
+    /// CSRF token of a cookie session, requests authorized by the cookie must repeat it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub csrf: Option<String>,
+}
+
+/// Secret with its key id, written into `kid` header of issued tokens
+pub struct JwtKey {
+    pub id: String,
+    pub secret: String,
+}
+
+impl JwtKey {
+    /// Parses comma separated list of keys in `kid:secret` format
+    pub fn parse_list(value: &str) -> Result<Vec<JwtKey>, AppError> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                item.split_once(':')
+                    .filter(|(id, secret)| !id.is_empty() && !secret.is_empty())
+                    .map(|(id, secret)| JwtKey {
+                        id: id.to_string(),
+                        secret: secret.to_string(),
+                    })
+                    .ok_or_else(|| {
+                        AppError::InvalidConfig(String::from(
+                            "JWT keys must be listed in kid:secret format",
+                        ))
+                    })
+            })
+            .collect()
+    }
 }
 
 pub struct JwtService {
+    key_id: String,
     encoding_key: EncodingKey,
-    decoding_key: DecodingKey,
+    // primary key goes first, then secondary keys accepted only for verification
+    decoding_keys: Vec<(String, DecodingKey)>,
+    clock: Arc<dyn Clock>,
 }
 
 impl JwtService {
-    pub fn new(secret: &str) -> Self {
-        let secret_bytes = secret.as_bytes();
-        JwtService {
-            encoding_key: EncodingKey::from_secret(secret_bytes),
-            decoding_key: DecodingKey::from_secret(secret_bytes),
+    pub fn new(primary: JwtKey, secondary: Vec<JwtKey>) -> Result<Self, AppError> {
+        let mut decoding_keys: Vec<(String, DecodingKey)> = Vec::with_capacity(secondary.len() + 1);
+
+        for key in std::iter::once(&primary).chain(secondary.iter()) {
+            if decoding_keys.iter().any(|(id, _)| *id == key.id) {
+                return Err(AppError::InvalidConfig(format!(
+                    "Duplicate JWT key id \"{}\"",
+                    key.id
+                )));
+            }
+            decoding_keys.push((
+                key.id.clone(),
+                DecodingKey::from_secret(key.secret.as_bytes()),
+            ));
         }
+
+        trace!(
+            "JWT keys loaded: primary {}, {} secondary",
+            primary.id,
+            secondary.len()
+        );
+
+        Ok(JwtService {
+            encoding_key: EncodingKey::from_secret(primary.secret.as_bytes()),
+            key_id: primary.id,
+            decoding_keys,
+            clock: Arc::new(SystemClock),
+        })
     }
 
-    pub fn generate_token(&self, user_id: i64, username: String) -> Result<String, AppError> {
+    /// Replaces system time used for expiration of issued and verified tokens
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn generate_token(
+        &self,
+        user_id: i64,
+        username: String,
+        csrf: Option<String>,
+    ) -> Result<String, AppError> {
         const TOKEN_LIFETIME: TimeDelta = TimeDelta::days(1);
-        let expiration_time = Utc::now()
+        let expiration_time = self
+            .clock
+            .now()
             .checked_add_signed(TOKEN_LIFETIME)
             .ok_or(AppError::InvalidDatetime)?;
 
@@ -37,16 +120,143 @@ impl JwtService {
         let claims = Claims {
             user_id,
             username,
-            exp: expiration_time.timestamp_millis(),
+            exp: expiration_time.timestamp(),
+            csrf,
         };
 
-        encode(&Header::default(), &claims, &self.encoding_key).map_err(AppError::from)
+        let header = Header {
+            kid: Some(self.key_id.clone()),
+            ..Header::default()
+        };
+
+        encode(&header, &claims, &self.encoding_key).map_err(AppError::from)
     }
 
+    /// Checks signature of the token and its expiration against the clock of the service
     pub fn verify_token(&self, token: &str) -> Result<Claims, AppError> {
-        let validation = Validation::default();
-        decode::<Claims>(&token, &self.decoding_key, &validation)
-            .map(|data| data.claims)
-            .map_err(AppError::from)
+        let mut validation = Validation::default();
+        // expiration is checked by `check_expiration`, jsonwebtoken would use system time
+        validation.validate_exp = false;
+
+        let claims = self.decode_claims(token, &validation)?;
+        self.check_expiration(claims, validation.leeway)
+    }
+
+    /// Seconds left until expiration of the verified token
+    pub fn seconds_left(&self, claims: &Claims) -> i64 {
+        claims.exp - self.clock.now().timestamp()
+    }
+
+    fn check_expiration(&self, claims: Claims, leeway: u64) -> Result<Claims, AppError> {
+        if claims.exp > MAX_EXP_SECONDS || self.seconds_left(&claims) + (leeway as i64) < 0 {
+            return Err(jsonwebtoken::errors::Error::from(ErrorKind::ExpiredSignature).into());
+        }
+
+        Ok(claims)
+    }
+
+    fn decode_claims(&self, token: &str, validation: &Validation) -> Result<Claims, AppError> {
+        let header = decode_header(token)?;
+
+        match header.kid {
+            Some(kid) => {
+                let (_, key) = self
+                    .decoding_keys
+                    .iter()
+                    .find(|(id, _)| *id == kid)
+                    .ok_or(AppError::InvalidToken)?;
+
+                decode::<Claims>(token, key, validation)
+                    .map(|data| data.claims)
+                    .map_err(AppError::from)
+            }
+            // tokens issued before key ids were introduced
+            None => {
+                let mut last_error = AppError::InvalidToken;
+                for (_, key) in &self.decoding_keys {
+                    match decode::<Claims>(token, key, validation) {
+                        Ok(data) => return Ok(data.claims),
+                        Err(e) => last_error = AppError::from(e),
+                    }
+                }
+                Err(last_error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use jsonwebtoken::errors::ErrorKind;
+
+    use super::*;
+    use crate::data::in_memory::TestClock;
+
+    fn jwt_service(clock: Arc<TestClock>) -> JwtService {
+        let key = JwtKey {
+            id: String::from("test"),
+            secret: String::from("test-secret"),
+        };
+        JwtService::new(key, Vec::new()).unwrap().with_clock(clock)
+    }
+
+    fn is_expired(result: Result<Claims, AppError>) -> bool {
+        matches!(result, Err(AppError::JwtError(e)) if *e.kind() == ErrorKind::ExpiredSignature)
+    }
+
+    #[test]
+    fn token_expires_after_a_day_with_leeway() {
+        let clock = TestClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+        let service = jwt_service(clock.clone());
+        let token = service
+            .generate_token(1, String::from("alice"), None)
+            .unwrap();
+
+        clock.advance(TimeDelta::days(1) + TimeDelta::seconds(60));
+        let claims = service.verify_token(&token).unwrap();
+        assert_eq!(claims.username, "alice");
+        assert_eq!(service.seconds_left(&claims), -60);
+
+        clock.advance(TimeDelta::seconds(1));
+        assert!(is_expired(service.verify_token(&token)));
+    }
+
+    #[test]
+    fn expiration_is_checked_against_injected_clock() {
+        // a token issued in 2024 is expired by the system clock but not by the frozen one
+        let clock = TestClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+        let service = jwt_service(clock.clone());
+        let token = service
+            .generate_token(1, String::from("alice"), None)
+            .unwrap();
+
+        assert!(service.verify_token(&token).is_ok());
+
+        let key = JwtKey {
+            id: String::from("test"),
+            secret: String::from("test-secret"),
+        };
+        let system_time_service = JwtService::new(key, Vec::new()).unwrap();
+        assert!(is_expired(system_time_service.verify_token(&token)));
+    }
+
+    #[test]
+    fn expiration_in_milliseconds_is_rejected() {
+        let clock = TestClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+        let service = jwt_service(clock.clone());
+        let claims = Claims {
+            user_id: 1,
+            username: String::from("alice"),
+            exp: (clock.now() + TimeDelta::days(1)).timestamp_millis(),
+            csrf: None,
+        };
+        let header = Header {
+            kid: Some(String::from("test")),
+            ..Header::default()
+        };
+        let token = encode(&header, &claims, &EncodingKey::from_secret(b"test-secret")).unwrap();
+
+        assert!(is_expired(service.verify_token(&token)));
     }
 }
@@ -1,3 +1,4 @@
+pub mod clock;
 pub mod database;
 pub mod jwt;
 pub mod logging;
@@ -0,0 +1,7 @@
+//! Blog server internals, shared by the server binary and benchmarks
+
+pub mod application;
+pub mod data;
+pub mod domain;
+pub mod infrastructure;
+pub mod presentation;
@@ -1,45 +1,108 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use actix_cors::Cors;
-use actix_web::{App, HttpServer, middleware::Logger, web};
+use actix_web::{
+    App, HttpServer,
+    http::Uri,
+    middleware::{Compress, Logger, from_fn},
+    web,
+};
+use argon2::{
+    Algorithm, Argon2, Params, PasswordHasher, PasswordVerifier, Version,
+    password_hash::{SaltString, rand_core::OsRng},
+};
 use blog_grpc_api::blog_service_server::BlogServiceServer;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 use tokio::{signal, sync::oneshot::Receiver};
+use tonic::{codec::CompressionEncoding, service::interceptor::InterceptedService};
 use tracing::{error, info, trace, warn};
 
-use crate::{
-    application::{auth_service::AuthService, blog_service::BlogService},
-    data::{post_repository::PostRepository, user_repository::UserRepository},
-    domain::error::AppError,
+use actix_web_httpauth::middleware::HttpAuthentication;
+use blog_server::{
+    application::{
+        auth_service::AuthService, blog_service::BlogService, maintenance::Maintenance,
+        stats_service::StatsService,
+    },
+    data::{
+        post_repository::PgPostRepository, stats_repository::StatsRepository,
+        user_repository::PgUserRepository,
+    },
+    domain::{
+        error::AppError,
+        info::{ServerInfo, ServerLimits},
+        post::PostLimits,
+    },
     infrastructure::{
+        clock::{Clock, SystemClock},
         database::{init_db_connection, run_migrations},
-        jwt::JwtService,
+        jwt::{JwtKey, JwtService},
         logging::init_logging,
     },
     presentation::{
-        grpc_service::GrpcService,
+        grpc_service::{AuthInterceptor, GrpcService},
         http_handlers::{
-            create_post, delete_post, get_post, get_posts, login, register, update_post,
+            create_post, delete_post, get_info, get_post, get_posts, get_stats, get_user_stats,
+            json_error_handler, login, logout, patch_post, post_events, register, search_posts,
+            update_post,
         },
-        middleware::jwt_validator,
+        middleware::{jwt_validator, localize_errors},
     },
 };
-use actix_web_httpauth::middleware::HttpAuthentication;
-
-mod application;
-mod data;
-mod domain;
-mod infrastructure;
-mod presentation;
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     #[arg(long = "http_port", default_value_t = 8080)]
     http_port: u16,
     #[arg(long = "grpc_port", default_value_t = 50051)]
     grpc_port: u16,
+    #[arg(long = "json_limit", default_value_t = 1024 * 1024)]
+    json_limit: usize,
+    #[arg(long = "max_title_length", default_value_t = 256)]
+    max_title_length: usize,
+    #[arg(long = "max_content_length", default_value_t = 100_000)]
+    max_content_length: usize,
+    #[arg(long = "max_page_limit", default_value_t = 100)]
+    max_page_limit: i64,
+    #[arg(long = "grpc_max_message_size", default_value_t = 16 * 1024 * 1024)]
+    grpc_max_message_size: usize,
+    #[arg(long = "stats_cache_ttl", default_value_t = 60)]
+    stats_cache_ttl: u64,
+    #[arg(long = "shutdown_timeout", default_value_t = 30)]
+    shutdown_timeout: u64,
+    #[arg(long = "read_only", default_value_t = false)]
+    read_only: bool,
+    #[arg(long = "maintenance_message")]
+    maintenance_message: Option<String>,
+    /// Origin allowed to send cookies and credentialed requests, e.g. https://blog.example.com
+    #[arg(
+        long = "allowed_origin",
+        alias = "allowed-origin",
+        value_delimiter = ','
+    )]
+    allowed_origins: Vec<String>,
+    #[arg(long = "argon2_memory_kib", global = true, default_value_t = Params::DEFAULT_M_COST)]
+    argon2_memory_kib: u32,
+    #[arg(long = "argon2_iterations", global = true, default_value_t = Params::DEFAULT_T_COST)]
+    argon2_iterations: u32,
+    #[arg(long = "argon2_parallelism", global = true, default_value_t = Params::DEFAULT_P_COST)]
+    argon2_parallelism: u32,
+    #[command(subcommand)]
+    command: Option<ServerCommand>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum ServerCommand {
+    /// Reports latency of password hashing with given argon2 parameters and exits
+    BenchHash {
+        #[arg(long, default_value_t = 10)]
+        samples: usize,
+    },
 }
 
 #[tokio::main]
@@ -47,33 +110,72 @@ async fn main() -> Result<(), AppError> {
     let args = Args::parse();
     dotenvy::dotenv().ok();
     init_logging();
+
+    let argon2_params = argon2_params(&args)?;
+    if let Some(ServerCommand::BenchHash { samples }) = args.command {
+        bench_hash(argon2_params, samples.max(1));
+        return Ok(());
+    }
+
     info!("Starting blog server...");
 
     let url = std::env::var("DATABASE_URL")?;
-    let jwt_secret = std::env::var("JWT_SECRET")?;
+    let jwt_key = JwtKey {
+        id: std::env::var("JWT_KEY_ID").unwrap_or_else(|_| String::from("default")),
+        secret: std::env::var("JWT_SECRET")?,
+    };
+    let jwt_secondary_keys = match std::env::var("JWT_SECONDARY_SECRETS") {
+        Ok(value) => JwtKey::parse_list(&value)?,
+        Err(_) => Vec::new(),
+    };
 
     let db_pool = init_db_connection(&url).await?;
     run_migrations(&db_pool).await?;
 
     let db_pool = Arc::new(db_pool);
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
 
-    let user_repo = UserRepository::new(db_pool.clone());
-    let post_repo = PostRepository::new(db_pool.clone());
+    let user_repo = Arc::new(PgUserRepository::new(db_pool.clone()));
+    let post_repo = Arc::new(PgPostRepository::new(db_pool.clone(), clock.clone()));
+    let stats_repo = StatsRepository::new(db_pool.clone());
 
-    let jwt_service = Arc::new(JwtService::new(&jwt_secret));
-    let auth_service = Arc::new(AuthService::new(user_repo, jwt_service.clone()));
-    let blog_service = Arc::new(BlogService::new(post_repo));
+    if args.read_only {
+        warn!("Server is running in read-only maintenance mode");
+    }
+    let maintenance = Arc::new(Maintenance::new(
+        args.read_only,
+        args.maintenance_message.clone(),
+    ));
+
+    let jwt_service = Arc::new(JwtService::new(jwt_key, jwt_secondary_keys)?.with_clock(clock));
+    let auth_service = Arc::new(AuthService::new(
+        user_repo,
+        jwt_service.clone(),
+        argon2_params,
+        maintenance.clone(),
+    ));
+    let blog_service = Arc::new(BlogService::new(post_repo, post_limits(&args), maintenance));
+    let stats_service = Arc::new(StatsService::new(
+        stats_repo,
+        Duration::from_secs(args.stats_cache_ttl),
+    ));
 
     let host = "0.0.0.0";
 
     let (mut http_task, http_server_handle) = {
-        let http_port = args.http_port;
         let jwt_service = jwt_service.clone();
         let auth_service = auth_service.clone();
         let blog_service = blog_service.clone();
+        let stats_service = stats_service.clone();
 
-        let http_server =
-            setup_http_server(host, http_port, jwt_service, auth_service, blog_service)?;
+        let http_server = setup_http_server(
+            host,
+            &args,
+            jwt_service,
+            auth_service,
+            blog_service,
+            stats_service,
+        )?;
 
         let http_server_handle = http_server.handle();
 
@@ -85,7 +187,7 @@ async fn main() -> Result<(), AppError> {
     let (grpc_shutdown_tx, grpc_shutdown_rx) = tokio::sync::oneshot::channel::<()>();
 
     let mut grpc_task = {
-        let grpc_port = args.grpc_port;
+        let args = args.clone();
         let jwt_service = jwt_service.clone();
         let auth_service = auth_service.clone();
         let blog_service = blog_service.clone();
@@ -93,10 +195,11 @@ async fn main() -> Result<(), AppError> {
         tokio::spawn(async move {
             run_grpc_server(
                 host,
-                grpc_port,
+                &args,
                 jwt_service,
                 auth_service,
                 blog_service,
+                stats_service,
                 grpc_shutdown_rx,
             )
             .await
@@ -104,21 +207,32 @@ async fn main() -> Result<(), AppError> {
     };
 
     tokio::select! {
-        _ = signal::ctrl_c() => {
-            info!("Ctrl+C received. Shutting down...");
+        signal_name = shutdown_signal() => {
+            info!("{signal_name} received. Shutting down...");
 
             let _ = grpc_shutdown_tx.send(());
 
-            let _ = http_server_handle.stop(true).await;
-
-            let (http_res, grpc_res) = tokio::join!(http_task, grpc_task);
-
-            if let Err(e) = http_res {
-                warn!("HTTP task finished with error: {e}");
-            }
-
-            if let Err(e) = grpc_res {
-                warn!("GRPC task finished with error: {e}");
+            let drain_timeout = Duration::from_secs(args.shutdown_timeout);
+            let drain = async {
+                http_server_handle.stop(true).await;
+                tokio::join!(&mut http_task, &mut grpc_task)
+            };
+
+            match tokio::time::timeout(drain_timeout, drain).await {
+                Ok((http_res, grpc_res)) => {
+                    if let Err(e) = http_res {
+                        warn!("HTTP task finished with error: {e}");
+                    }
+
+                    if let Err(e) = grpc_res {
+                        warn!("GRPC task finished with error: {e}");
+                    }
+                }
+                Err(_) => {
+                    warn!("Servers did not stop within {drain_timeout:?}, aborting");
+                    http_task.abort();
+                    grpc_task.abort();
+                }
             }
         },
         res = &mut http_task => {
@@ -134,57 +248,227 @@ async fn main() -> Result<(), AppError> {
     Ok(())
 }
 
+fn post_limits(args: &Args) -> PostLimits {
+    PostLimits {
+        max_title_length: args.max_title_length,
+        max_content_length: args.max_content_length,
+        max_page_limit: args.max_page_limit,
+    }
+}
+
+fn server_info(args: &Args) -> ServerInfo {
+    let limits = ServerLimits::new(
+        post_limits(args),
+        args.json_limit,
+        args.grpc_max_message_size,
+    );
+    ServerInfo::new(limits, args.read_only)
+}
+
+/// Argon2 parameters from arguments, rejected at startup when argon2 doesn't accept them
+fn argon2_params(args: &Args) -> Result<Params, AppError> {
+    let params = Params::new(
+        args.argon2_memory_kib,
+        args.argon2_iterations,
+        args.argon2_parallelism,
+        None,
+    )
+    .map_err(|e| AppError::InvalidConfig(format!("Invalid argon2 parameters: {e}")))?;
+
+    let default_cost = u64::from(Params::DEFAULT_M_COST) * u64::from(Params::DEFAULT_T_COST);
+    if u64::from(params.m_cost()) * u64::from(params.t_cost()) < default_cost {
+        warn!(
+            "Argon2 parameters m={} t={} are weaker than the default m={} t={}",
+            params.m_cost(),
+            params.t_cost(),
+            Params::DEFAULT_M_COST,
+            Params::DEFAULT_T_COST
+        );
+    }
+
+    Ok(params)
+}
+
+/// Hashes and verifies a password `samples` times and prints latency of both operations
+fn bench_hash(params: Params, samples: usize) {
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone());
+    let mut hash_times = Vec::with_capacity(samples);
+    let mut verify_times = Vec::with_capacity(samples);
+
+    for _ in 0..samples {
+        let salt = SaltString::generate(&mut OsRng);
+        let started = Instant::now();
+        let hash = argon2.hash_password(b"bench-password", &salt);
+        hash_times.push(started.elapsed());
+
+        let Ok(hash) = hash else {
+            error!("Unable to hash password with given parameters");
+            return;
+        };
+        let started = Instant::now();
+        let _ = argon2.verify_password(b"bench-password", &hash);
+        verify_times.push(started.elapsed());
+    }
+
+    println!(
+        "argon2id m={} KiB t={} p={}, {samples} samples",
+        params.m_cost(),
+        params.t_cost(),
+        params.p_cost()
+    );
+    for (operation, mut times) in [("hash", hash_times), ("verify", verify_times)] {
+        times.sort();
+        let median = times[times.len() / 2];
+        println!(
+            "{operation:>6}: min {:?}, median {median:?}, max {:?}, ~{:.1}/s per core",
+            times[0],
+            times[times.len() - 1],
+            1.0 / median.as_secs_f64()
+        );
+    }
+}
+
+/// Waits for Ctrl+C or SIGTERM and returns the name of the received signal
+async fn shutdown_signal() -> &'static str {
+    let ctrl_c = async {
+        if let Err(e) = signal::ctrl_c().await {
+            error!("Unable to listen for Ctrl+C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                error!("Unable to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => "Ctrl+C",
+        _ = terminate => "SIGTERM",
+    }
+}
+
+/// Without allowed origins any origin may call the API, but browsers send no cookies;
+/// otherwise only the allowed origins may call it and credentials are supported
+fn cors(allowed_origins: &[String]) -> Cors {
+    let cors = Cors::default()
+        .allow_any_header()
+        .allowed_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"])
+        .expose_headers(vec!["Link", "X-Total-Count", "ETag"])
+        .max_age(3600);
+
+    if allowed_origins.is_empty() {
+        return cors.allow_any_origin();
+    }
+
+    allowed_origins
+        .iter()
+        .fold(cors, |cors, origin| cors.allowed_origin(origin))
+        .supports_credentials()
+}
+
 fn setup_http_server(
     host: &str,
-    port: u16,
+    args: &Args,
     jwt_service: Arc<JwtService>,
     auth_service: Arc<AuthService>,
     blog_service: Arc<BlogService>,
+    stats_service: Arc<StatsService>,
 ) -> Result<actix_web::dev::Server, AppError> {
+    let port = args.http_port;
+    let json_limit = args.json_limit;
+    let allowed_origins = args.allowed_origins.clone();
+    if let Some(origin) = allowed_origins
+        .iter()
+        .find(|origin| origin.as_str() == "*" || origin.parse::<Uri>().is_err())
+    {
+        return Err(AppError::InvalidConfig(format!(
+            "Invalid allowed origin \"{origin}\""
+        )));
+    }
     trace!("Starting HTTP server on {host}:{}", port);
     let auth_service = web::Data::new(auth_service);
     let blog_service = web::Data::new(blog_service);
     let jwt_service = web::Data::new(jwt_service);
+    let stats_service = web::Data::new(stats_service);
+    let server_info = web::Data::new(server_info(args));
 
     let server = HttpServer::new(move || {
-        let cors = Cors::default()
-            .allow_any_origin()
-            .allow_any_header()
-            .allowed_methods(vec!["GET", "POST", "PUT", "DELETE", "OPTIONS"])
-            .max_age(3600);
-
-        App::new().app_data(jwt_service.clone()).service(
-            web::scope("/api")
-                .wrap(cors)
-                .wrap(Logger::default())
-                .service(
-                    web::scope("/auth")
-                        .app_data(auth_service.clone())
-                        .route("/register", web::post().to(register))
-                        .route("/login", web::post().to(login)),
-                )
-                .service(
-                    web::scope("/posts")
-                        .app_data(blog_service.clone())
-                        .route("", web::get().to(get_posts))
-                        .service(
-                            web::resource("")
-                                .wrap(HttpAuthentication::bearer(jwt_validator))
-                                .route(web::post().to(create_post)),
-                        )
-                        .service(
-                            web::scope("/{id}")
-                                .route("", web::get().to(get_post))
-                                .service(
-                                    web::resource("")
-                                        .wrap(HttpAuthentication::bearer(jwt_validator))
-                                        .route(web::put().to(update_post))
-                                        .route(web::delete().to(delete_post)),
-                                ),
-                        ),
-                ),
-        )
+        let cors = cors(&allowed_origins);
+
+        let json_config = web::JsonConfig::default()
+            .limit(json_limit)
+            .error_handler(json_error_handler);
+
+        App::new()
+            .app_data(jwt_service.clone())
+            .app_data(json_config)
+            .service(
+                web::scope("/api")
+                    .wrap(from_fn(localize_errors))
+                    .wrap(cors)
+                    .wrap(Logger::default())
+                    .wrap(Compress::default())
+                    .service(
+                        web::scope("/auth")
+                            .app_data(auth_service.clone())
+                            .route("/register", web::post().to(register))
+                            .route("/login", web::post().to(login))
+                            .route("/logout", web::post().to(logout)),
+                    )
+                    .service(
+                        web::resource("/info")
+                            .app_data(server_info.clone())
+                            .route(web::get().to(get_info)),
+                    )
+                    .service(
+                        web::scope("/stats")
+                            .app_data(stats_service.clone())
+                            .route("", web::get().to(get_stats))
+                            .service(
+                                web::resource("/me")
+                                    .wrap(HttpAuthentication::with_fn(jwt_validator))
+                                    .route(web::get().to(get_user_stats)),
+                            ),
+                    )
+                    .service(
+                        web::scope("/posts")
+                            .app_data(blog_service.clone())
+                            .route("", web::get().to(get_posts))
+                            .route("/search", web::get().to(search_posts))
+                            .route("/events", web::get().to(post_events))
+                            .service(
+                                web::resource("")
+                                    .wrap(HttpAuthentication::with_fn(jwt_validator))
+                                    .route(web::post().to(create_post)),
+                            )
+                            .service(
+                                web::scope("/{id}")
+                                    .route("", web::get().to(get_post))
+                                    .service(
+                                        web::resource("")
+                                            .wrap(HttpAuthentication::with_fn(jwt_validator))
+                                            .route(web::put().to(update_post))
+                                            .route(web::patch().to(patch_post))
+                                            .route(web::delete().to(delete_post)),
+                                    ),
+                            ),
+                    ),
+            )
     })
+    .disable_signals()
+    .shutdown_timeout(args.shutdown_timeout)
     .bind((host, port))?;
 
     Ok(server.run())
@@ -192,16 +476,26 @@ fn setup_http_server(
 
 async fn run_grpc_server(
     host: &str,
-    port: u16,
+    args: &Args,
     jwt_service: Arc<JwtService>,
     auth_service: Arc<AuthService>,
     blog_service: Arc<BlogService>,
+    stats_service: Arc<StatsService>,
     grpc_shutdown_rx: Receiver<()>,
 ) -> Result<(), AppError> {
-    let grpc_service =
-        BlogServiceServer::new(GrpcService::new(auth_service, blog_service, jwt_service));
-
-    let grpc_address: SocketAddr = format!("{host}:{}", port).parse()?;
+    let grpc_service = BlogServiceServer::new(GrpcService::new(
+        auth_service,
+        blog_service,
+        stats_service,
+        server_info(args),
+    ))
+    .accept_compressed(CompressionEncoding::Gzip)
+    .send_compressed(CompressionEncoding::Gzip)
+    .max_decoding_message_size(args.grpc_max_message_size)
+    .max_encoding_message_size(args.grpc_max_message_size);
+    let grpc_service = InterceptedService::new(grpc_service, AuthInterceptor::new(jwt_service));
+
+    let grpc_address: SocketAddr = format!("{host}:{}", args.grpc_port).parse()?;
 
     trace!("Starting GRPC server on {}", grpc_address);
 
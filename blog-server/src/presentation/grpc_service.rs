@@ -1,61 +1,118 @@
-use std::sync::Arc;
+use std::{collections::HashMap, pin::Pin, sync::Arc, time::SystemTime};
 
 use blog_grpc_api::{
-    AuthResponse, CreatePostRequest, DeletePostRequest, GetPostRequest, GetPostsRequest,
-    GetPostsResponse, LoginRequest, PostResponse, RegisterRequest, UpdatePostRequest,
+    AuthResponse, BatchDeletePostsRequest, BatchDeletePostsResponse, BatchDeleteResult, BatchError,
+    BatchGetPostsRequest, BatchGetPostsResponse, BatchPostResult, CreatePostRequest,
+    DeletePostRequest, FieldMask, GetPostRequest, GetPostsRequest, GetPostsResponse, LoginRequest,
+    PatchPostRequest, PostEventKind, PostResponse, RegisterRequest, SearchPostsRequest,
+    SubscribePostsRequest, Timestamp, UpdatePostRequest, batch_post_result,
     blog_service_server::BlogService,
 };
-use tonic::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::{Stream, StreamExt};
+use tonic::{async_trait, service::Interceptor};
+use tonic_types::{ErrorDetails, StatusExt};
 
 use crate::{
-    application::auth_service::AuthService,
-    domain::{error::AppError, post::Post},
+    application::{auth_service::AuthService, stats_service::StatsService},
+    domain::{
+        error::AppError,
+        info::ServerInfo,
+        post::{Post, PostEvent},
+        stats::UserStats,
+        user::AuthenticatedUser,
+    },
     infrastructure::jwt::JwtService,
+    presentation::i18n::{Locale, localized_message},
 };
 
-pub(crate) struct GrpcService {
+pub struct GrpcService {
     auth_service: Arc<AuthService>,
     posts_service: Arc<crate::application::blog_service::BlogService>,
+    stats_service: Arc<StatsService>,
+    server_info: ServerInfo,
+}
+
+/// Verifies bearer token of gRPC requests once, before they reach [`GrpcService`]
+///
+/// User of a valid token is added to request extensions. Invalid token is ignored here,
+/// like in HTTP only methods requiring auth reject requests without a verified user,
+/// so public methods (login, reading posts) still work with a stale token
+#[derive(Clone)]
+pub struct AuthInterceptor {
     jwt_service: Arc<JwtService>,
 }
 
 impl GrpcService {
-    pub(crate) fn new(
+    pub fn new(
         auth_service: Arc<AuthService>,
         posts_service: Arc<crate::application::blog_service::BlogService>,
-        jwt_service: Arc<JwtService>,
+        stats_service: Arc<StatsService>,
+        server_info: ServerInfo,
     ) -> Self {
         Self {
             auth_service,
             posts_service,
-            jwt_service,
+            stats_service,
+            server_info,
         }
     }
+}
+
+impl AuthInterceptor {
+    pub fn new(jwt_service: Arc<JwtService>) -> Self {
+        Self { jwt_service }
+    }
+}
 
-    fn get_user_id<T>(&self, request: &tonic::Request<T>) -> Result<i64, AppError> {
-        let token = request
+impl Interceptor for AuthInterceptor {
+    fn call(
+        &mut self,
+        mut request: tonic::Request<()>,
+    ) -> Result<tonic::Request<()>, tonic::Status> {
+        let claims = request
             .metadata()
             .get("authorization")
-            .and_then(|s| s.to_str().ok())
-            .and_then(|s| s.strip_prefix("Bearer "))
-            .ok_or(AppError::InvalidToken)?;
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .and_then(|token| self.jwt_service.verify_token(token).ok());
 
-        Ok(self.jwt_service.verify_token(token)?.user_id)
+        if let Some(claims) = claims {
+            request
+                .extensions_mut()
+                .insert(AuthenticatedUser::from(claims));
+        }
+        Ok(request)
     }
 }
 
+/// Id of the user authenticated by [`AuthInterceptor`], missing or invalid token
+/// is reported as [`AppError::InvalidToken`]
+fn authenticated_user_id<T>(request: &tonic::Request<T>) -> Result<i64, AppError> {
+    request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .map(|user| user.user_id)
+        .ok_or(AppError::InvalidToken)
+}
+
 #[async_trait]
 impl BlogService for GrpcService {
+    type SubscribePostsStream =
+        Pin<Box<dyn Stream<Item = Result<blog_grpc_api::PostEvent, tonic::Status>> + Send>>;
+
     async fn register(
         &self,
         request: tonic::Request<RegisterRequest>,
     ) -> Result<tonic::Response<AuthResponse>, tonic::Status> {
+        let locale = request_locale(&request);
         let params = request.into_inner();
         let token = self
             .auth_service
-            .register(params.username, params.email, params.password)
+            .register(params.username, params.email, params.password, false)
             .await
-            .map(|user_and_token| user_and_token.token)?;
+            .map(|user_and_token| user_and_token.token)
+            .localized(locale)?;
 
         Ok(AuthResponse { token }.into())
     }
@@ -63,12 +120,14 @@ impl BlogService for GrpcService {
         &self,
         request: tonic::Request<LoginRequest>,
     ) -> Result<tonic::Response<AuthResponse>, tonic::Status> {
+        let locale = request_locale(&request);
         let params = request.into_inner();
         let token = self
             .auth_service
-            .login(params.username, params.password)
+            .login(params.username, params.password, false)
             .await
-            .map(|user_and_token| user_and_token.token)?;
+            .map(|user_and_token| user_and_token.token)
+            .localized(locale)?;
 
         Ok(AuthResponse { token }.into())
     }
@@ -76,75 +135,297 @@ impl BlogService for GrpcService {
         &self,
         request: tonic::Request<CreatePostRequest>,
     ) -> Result<tonic::Response<PostResponse>, tonic::Status> {
-        let user_id = self.get_user_id(&request)?;
+        let locale = request_locale(&request);
+        let user_id = authenticated_user_id(&request).localized(locale)?;
         let params = request.into_inner();
         let post = self
             .posts_service
             .create_post(params.title, params.content, user_id)
-            .await?;
+            .await
+            .localized(locale)?;
         Ok(to_post_response(post).into())
     }
     async fn get_post(
         &self,
         request: tonic::Request<GetPostRequest>,
     ) -> Result<tonic::Response<PostResponse>, tonic::Status> {
+        let locale = request_locale(&request);
         let params = request.into_inner();
-        let post = self.posts_service.get_post(params.post_id).await?;
+        let post = self
+            .posts_service
+            .get_post(params.post_id)
+            .await
+            .localized(locale)?;
         Ok(to_post_response(post).into())
     }
     async fn update_post(
         &self,
         request: tonic::Request<UpdatePostRequest>,
     ) -> Result<tonic::Response<PostResponse>, tonic::Status> {
-        let user_id = self.get_user_id(&request)?;
+        let locale = request_locale(&request);
+        let user_id = authenticated_user_id(&request).localized(locale)?;
+        let params = request.into_inner();
+        let post = match params.update_mask {
+            Some(mask) if !mask.paths.is_empty() => {
+                let (title, content) =
+                    apply_field_mask(&mask, params.title, params.content).localized(locale)?;
+                self.posts_service
+                    .patch_post(params.post_id, title, content, user_id)
+                    .await
+            }
+            _ => {
+                self.posts_service
+                    .update_post(params.post_id, params.title, params.content, user_id)
+                    .await
+            }
+        }
+        .localized(locale)?;
+        Ok(to_post_response(post).into())
+    }
+    async fn patch_post(
+        &self,
+        request: tonic::Request<PatchPostRequest>,
+    ) -> Result<tonic::Response<PostResponse>, tonic::Status> {
+        let locale = request_locale(&request);
+        let user_id = authenticated_user_id(&request).localized(locale)?;
         let params = request.into_inner();
         let post = self
             .posts_service
-            .update_post(params.post_id, params.title, params.content, user_id)
-            .await?;
+            .patch_post(params.post_id, params.title, params.content, user_id)
+            .await
+            .localized(locale)?;
         Ok(to_post_response(post).into())
     }
     async fn delete_post(
         &self,
         request: tonic::Request<DeletePostRequest>,
     ) -> Result<tonic::Response<()>, tonic::Status> {
-        let user_id = self.get_user_id(&request)?;
+        let locale = request_locale(&request);
+        let user_id = authenticated_user_id(&request).localized(locale)?;
         let params = request.into_inner();
         self.posts_service
             .delete_post(params.post_id, user_id)
-            .await?;
+            .await
+            .localized(locale)?;
         Ok(().into())
     }
     async fn get_posts(
         &self,
         request: tonic::Request<GetPostsRequest>,
     ) -> Result<tonic::Response<GetPostsResponse>, tonic::Status> {
+        let locale = request_locale(&request);
         let params = request.into_inner();
         let limit = params.limit.unwrap_or(10);
         let offset = params.offset.unwrap_or(0);
-        let (posts, total_posts_count) = self.posts_service.get_posts(limit, offset).await?;
-        Ok(GetPostsResponse {
-            posts: posts.into_iter().map(to_grpc_post).collect(),
-            limit,
-            offset,
-            total_posts_count: total_posts_count as i64,
+        let response = self
+            .posts_service
+            .get_posts(limit, offset)
+            .await
+            .localized(locale)?;
+        Ok(to_get_posts_response(response).into())
+    }
+    async fn batch_get_posts(
+        &self,
+        request: tonic::Request<BatchGetPostsRequest>,
+    ) -> Result<tonic::Response<BatchGetPostsResponse>, tonic::Status> {
+        let locale = request_locale(&request);
+        let params = request.into_inner();
+        let results = self
+            .posts_service
+            .get_posts_by_ids(&params.post_ids)
+            .await
+            .localized(locale)?;
+
+        let results = params
+            .post_ids
+            .into_iter()
+            .zip(results)
+            .map(|(post_id, result)| BatchPostResult {
+                post_id,
+                result: Some(match result {
+                    Ok(post) => batch_post_result::Result::Post(to_grpc_post(post)),
+                    Err(e) => batch_post_result::Result::Error(to_batch_error(&e, locale)),
+                }),
+            })
+            .collect();
+        Ok(BatchGetPostsResponse { results }.into())
+    }
+    async fn batch_delete_posts(
+        &self,
+        request: tonic::Request<BatchDeletePostsRequest>,
+    ) -> Result<tonic::Response<BatchDeletePostsResponse>, tonic::Status> {
+        let locale = request_locale(&request);
+        let user_id = authenticated_user_id(&request).localized(locale)?;
+        let params = request.into_inner();
+        let results = self
+            .posts_service
+            .delete_posts(&params.post_ids, user_id)
+            .await
+            .localized(locale)?;
+
+        let results = params
+            .post_ids
+            .into_iter()
+            .zip(results)
+            .map(|(post_id, result)| BatchDeleteResult {
+                post_id,
+                error: result.err().map(|e| to_batch_error(&e, locale)),
+            })
+            .collect();
+        Ok(BatchDeletePostsResponse { results }.into())
+    }
+    async fn search_posts(
+        &self,
+        request: tonic::Request<SearchPostsRequest>,
+    ) -> Result<tonic::Response<GetPostsResponse>, tonic::Status> {
+        let locale = request_locale(&request);
+        let params = request.into_inner();
+        let limit = params.limit.unwrap_or(10);
+        let offset = params.offset.unwrap_or(0);
+        let response = self
+            .posts_service
+            .search_posts(params.query, limit, offset)
+            .await
+            .localized(locale)?;
+        Ok(to_get_posts_response(response).into())
+    }
+    async fn subscribe_posts(
+        &self,
+        _request: tonic::Request<SubscribePostsRequest>,
+    ) -> Result<tonic::Response<Self::SubscribePostsStream>, tonic::Status> {
+        let events = self
+            .posts_service
+            .subscribe()
+            .map(|event| Ok(to_grpc_post_event(event)));
+        Ok(tonic::Response::new(Box::pin(events)))
+    }
+    async fn get_server_info(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> Result<tonic::Response<blog_grpc_api::ServerInfo>, tonic::Status> {
+        let info = &self.server_info;
+        let limits = &info.limits;
+        Ok(blog_grpc_api::ServerInfo {
+            version: info.version.to_string(),
+            api_version: info.api_version,
+            features: info.features.iter().map(|f| f.to_string()).collect(),
+            read_only: info.read_only,
+            limits: Some(blog_grpc_api::ServerLimits {
+                max_title_length: limits.max_title_length as u64,
+                max_content_length: limits.max_content_length as u64,
+                max_page_limit: limits.max_page_limit as u64,
+                max_request_size: limits.max_request_size as u64,
+                max_grpc_message_size: limits.max_grpc_message_size as u64,
+            }),
         }
         .into())
     }
+    async fn get_user_stats(
+        &self,
+        request: tonic::Request<()>,
+    ) -> Result<tonic::Response<blog_grpc_api::UserStats>, tonic::Status> {
+        let locale = request_locale(&request);
+        let user_id = authenticated_user_id(&request).localized(locale)?;
+        let stats = self
+            .stats_service
+            .get_user_stats(user_id)
+            .await
+            .localized(locale)?;
+        Ok(to_grpc_user_stats(stats).into())
+    }
 }
 
 impl From<AppError> for tonic::Status {
     fn from(value: AppError) -> Self {
-        match value {
-            AppError::UserNotFound { .. } => tonic::Status::not_found(value.to_string()),
-            AppError::UserAlreadyExists => tonic::Status::already_exists(value.to_string()),
-            AppError::InvalidCredentials => tonic::Status::unauthenticated(value.to_string()),
-            AppError::PostNotFound => tonic::Status::not_found(value.to_string()),
-            AppError::Forbidden => tonic::Status::permission_denied(value.to_string()),
-            AppError::InvalidToken => tonic::Status::unauthenticated(value.to_string()),
-            value => tonic::Status::internal(value.to_string()),
+        to_status(value, Locale::default())
+    }
+}
+
+const ERROR_DOMAIN: &str = "blog-server";
+
+fn to_status(value: AppError, locale: Locale) -> tonic::Status {
+    let message = localized_message(&value, locale);
+    let code = match value {
+        AppError::UserNotFound { .. } => tonic::Code::NotFound,
+        AppError::UserAlreadyExists => tonic::Code::AlreadyExists,
+        AppError::InvalidCredentials => tonic::Code::Unauthenticated,
+        AppError::PostNotFound => tonic::Code::NotFound,
+        AppError::Forbidden => tonic::Code::PermissionDenied,
+        AppError::Validation { .. } => tonic::Code::InvalidArgument,
+        AppError::PayloadTooLarge { .. } => tonic::Code::OutOfRange,
+        AppError::FieldTooLong { .. } => tonic::Code::InvalidArgument,
+        AppError::Maintenance(_) => tonic::Code::Unavailable,
+        AppError::InvalidToken => tonic::Code::Unauthenticated,
+        AppError::InvalidCsrfToken => tonic::Code::PermissionDenied,
+        _ => tonic::Code::Internal,
+    };
+
+    let mut details = ErrorDetails::with_error_info(
+        value.code().as_str(),
+        ERROR_DOMAIN,
+        HashMap::<String, String>::new(),
+    );
+
+    if let Some(field) = value.field() {
+        details.add_bad_request_violation(field, message.clone());
+    }
+
+    tonic::Status::with_error_details(code, message, details)
+}
+
+/// Keeps only the fields listed in the mask of `UpdatePost`
+fn apply_field_mask(
+    mask: &FieldMask,
+    title: String,
+    content: String,
+) -> Result<(Option<String>, Option<String>), AppError> {
+    let (mut masked_title, mut masked_content) = (None, None);
+    for path in &mask.paths {
+        match path.as_str() {
+            "title" => masked_title = Some(title.clone()),
+            "content" => masked_content = Some(content.clone()),
+            _ => {
+                return Err(AppError::Validation {
+                    field: "update_mask",
+                    message: format!("unknown field {path}"),
+                });
+            }
         }
     }
+
+    Ok((masked_title, masked_content))
+}
+
+/// Error of a single item of batch request
+fn to_batch_error(error: &AppError, locale: Locale) -> BatchError {
+    BatchError {
+        code: error.code().as_str().to_string(),
+        message: localized_message(error, locale),
+    }
+}
+
+/// Locale requested by client through `accept-language` metadata
+fn request_locale<T>(request: &tonic::Request<T>) -> Locale {
+    request
+        .metadata()
+        .get("accept-language")
+        .and_then(|value| value.to_str().ok())
+        .map(Locale::from_accept_language)
+        .unwrap_or_default()
+}
+
+trait Localized<T> {
+    fn localized(self, locale: Locale) -> Result<T, tonic::Status>;
+}
+
+impl<T> Localized<T> for Result<T, AppError> {
+    fn localized(self, locale: Locale) -> Result<T, tonic::Status> {
+        self.map_err(|error| to_status(error, locale))
+    }
+}
+
+fn to_timestamp(time: DateTime<Utc>) -> Timestamp {
+    Timestamp::from(SystemTime::from(time))
 }
 
 fn to_grpc_post(post: Post) -> blog_grpc_api::Post {
@@ -153,8 +434,26 @@ fn to_grpc_post(post: Post) -> blog_grpc_api::Post {
         title: post.title,
         content: post.content,
         author_id: post.author_id,
-        created_at: post.created_at.timestamp_millis(),
-        updated_at: post.updated_at.timestamp_millis(),
+        author_username: Some(post.author_username),
+        created_at: Some(to_timestamp(post.created_at)),
+        updated_at: Some(to_timestamp(post.updated_at)),
+    }
+}
+
+fn to_grpc_user_stats(stats: UserStats) -> blog_grpc_api::UserStats {
+    blog_grpc_api::UserStats {
+        user_id: stats.user_id,
+        username: stats.username,
+        posts: stats.posts,
+        last_post_at: stats.last_post_at.map(to_timestamp),
+        posts_per_day: stats
+            .posts_per_day
+            .into_iter()
+            .map(|daily| blog_grpc_api::DailyPosts {
+                day: daily.day.to_string(),
+                posts: daily.posts,
+            })
+            .collect(),
     }
 }
 
@@ -163,3 +462,27 @@ fn to_post_response(post: Post) -> PostResponse {
         post: Some(to_grpc_post(post)),
     }
 }
+
+fn to_grpc_post_event(event: PostEvent) -> blog_grpc_api::PostEvent {
+    let (kind, post_id, post) = match event {
+        PostEvent::Created(post) => (PostEventKind::Created, post.id, Some(post)),
+        PostEvent::Updated(post) => (PostEventKind::Updated, post.id, Some(post)),
+        PostEvent::Deleted { id } => (PostEventKind::Deleted, id, None),
+    };
+
+    blog_grpc_api::PostEvent {
+        kind: kind.into(),
+        post_id,
+        post: post.map(to_grpc_post),
+    }
+}
+
+fn to_get_posts_response(response: crate::domain::post::GetPostsResponse) -> GetPostsResponse {
+    GetPostsResponse {
+        posts: response.posts.into_iter().map(to_grpc_post).collect(),
+        limit: response.limit,
+        offset: response.offset,
+        total_posts_count: response.total_posts as i64,
+        max_limit: response.max_limit,
+    }
+}
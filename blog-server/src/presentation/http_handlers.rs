@@ -1,41 +1,124 @@
-use std::sync::Arc;
+use std::{fmt::Display, sync::Arc};
 
 use actix_web::{
     HttpMessage, HttpRequest, HttpResponse, HttpResponseBuilder, ResponseError,
-    http::StatusCode,
+    cookie::{Cookie, SameSite, time::Duration},
+    error::JsonPayloadError,
+    http::{
+        StatusCode,
+        header::{self, ETag, EntityTag, IfNoneMatch},
+    },
     web::{self, Data},
 };
+use blog_types::{
+    AuthMode, AuthQuery, AuthResponse, AuthUser, ErrorResponse, FieldViolation, LoginRequest,
+    RegisterRequest, SESSION_COOKIE, SessionResponse,
+};
+use futures_util::StreamExt;
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 
 use crate::{
-    application::{auth_service::AuthService, blog_service::BlogService},
+    application::{
+        auth_service::AuthService, blog_service::BlogService, stats_service::StatsService,
+    },
     domain::{
-        error::AppError,
-        post::{CreatePostParams, GetPostsParams, GetPostsResponse, UpdatePostParams},
-        user::{AuthenticatedUser, CreateUserParams, LoginParams},
+        error::{AppError, ErrorCode},
+        info::ServerInfo,
+        post::{
+            CreatePostParams, GetPostsParams, PatchPostParams, SearchPostsParams, UpdatePostParams,
+        },
+        user::{AuthenticatedUser, UserAndToken},
     },
+    infrastructure::jwt::JwtService,
+    presentation::i18n::{Locale, localized_message},
 };
 
 pub async fn register(
+    req: HttpRequest,
     auth_service: Data<Arc<AuthService>>,
-    request: web::Json<CreateUserParams>,
+    query: web::Query<AuthQuery>,
+    request: web::Json<RegisterRequest>,
 ) -> Result<HttpResponse, AppError> {
+    let cookie_session = query.mode == AuthMode::Cookie;
     let user_and_token = auth_service
-        .register(request.0.username, request.0.email, request.0.password)
+        .register(
+            request.0.username,
+            request.0.email,
+            request.0.password,
+            cookie_session,
+        )
         .await?;
 
-    Ok(HttpResponseBuilder::new(StatusCode::CREATED).json(user_and_token))
+    auth_response(&req, StatusCode::CREATED, user_and_token)
 }
 
 pub async fn login(
+    req: HttpRequest,
     auth_service: Data<Arc<AuthService>>,
-    request: web::Json<LoginParams>,
+    query: web::Query<AuthQuery>,
+    request: web::Json<LoginRequest>,
 ) -> Result<HttpResponse, AppError> {
+    let cookie_session = query.mode == AuthMode::Cookie;
     let user_and_token = auth_service
-        .login(request.0.username, request.0.password)
+        .login(request.0.username, request.0.password, cookie_session)
         .await?;
 
-    Ok(HttpResponseBuilder::new(StatusCode::OK).json(user_and_token))
+    auth_response(&req, StatusCode::OK, user_and_token)
+}
+
+/// Ends cookie session by removing its cookie, bearer tokens are simply forgotten by clients
+pub async fn logout(req: HttpRequest) -> HttpResponse {
+    let mut cookie = session_cookie(&req, String::new());
+    cookie.make_removal();
+
+    HttpResponse::NoContent().cookie(cookie).finish()
+}
+
+/// Returns bearer token in body, or sets it as a cookie when CSRF token was issued for a cookie session
+fn auth_response(
+    req: &HttpRequest,
+    status: StatusCode,
+    user_and_token: UserAndToken,
+) -> Result<HttpResponse, AppError> {
+    let Some(csrf_token) = user_and_token.csrf_token.clone() else {
+        return Ok(HttpResponseBuilder::new(status).json(AuthResponse::from(user_and_token)));
+    };
+
+    let jwt_service = req
+        .app_data::<Data<Arc<JwtService>>>()
+        .ok_or_else(|| AppError::InvalidConfig(String::from("JwtService is not configured")))?;
+    let claims = jwt_service.verify_token(&user_and_token.token)?;
+    let expires_at = claims.exp;
+    let max_age = jwt_service.seconds_left(&claims);
+
+    let mut cookie = session_cookie(req, user_and_token.token);
+    cookie.set_max_age(Duration::seconds(max_age));
+
+    let response = SessionResponse {
+        csrf_token,
+        expires_at,
+        user: AuthUser {
+            id: user_and_token.user.id,
+            username: user_and_token.user.username,
+            email: user_and_token.user.email,
+        },
+    };
+
+    Ok(HttpResponseBuilder::new(status)
+        .cookie(cookie)
+        .json(response))
+}
+
+/// Cookie which scripts can't read and browsers don't send with requests from other sites
+fn session_cookie(req: &HttpRequest, token: String) -> Cookie<'static> {
+    let secure = req.connection_info().scheme() == "https";
+    Cookie::build(SESSION_COOKIE, token)
+        .path("/")
+        .http_only(true)
+        .secure(secure)
+        .same_site(SameSite::Strict)
+        .finish()
 }
 
 pub async fn create_post(
@@ -54,6 +137,7 @@ pub async fn create_post(
 }
 
 pub async fn get_post(
+    req: HttpRequest,
     path: web::Path<i64>,
     blog_service: Data<Arc<BlogService>>,
 ) -> Result<HttpResponse, AppError> {
@@ -61,7 +145,7 @@ pub async fn get_post(
 
     let post = blog_service.get_post(post_id).await?;
 
-    Ok(HttpResponseBuilder::new(StatusCode::OK).json(post))
+    json_with_etag(&req, HttpResponseBuilder::new(StatusCode::OK), &post)
 }
 
 pub async fn update_post(
@@ -81,6 +165,38 @@ pub async fn update_post(
     Ok(HttpResponseBuilder::new(StatusCode::OK).json(post))
 }
 
+pub async fn patch_post(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    blog_service: Data<Arc<BlogService>>,
+    post_data: web::Json<PatchPostParams>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = try_get_user_id(req)?;
+    let post_id = path.into_inner();
+    let post_data = post_data.into_inner();
+
+    let post = blog_service
+        .patch_post(post_id, post_data.title, post_data.content, user_id)
+        .await?;
+
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(post))
+}
+
+/// Streams changes of posts as server-sent events
+pub async fn post_events(blog_service: Data<Arc<BlogService>>) -> HttpResponse {
+    let events = blog_service.subscribe().map(|event| {
+        let data = serde_json::to_string(&event)?;
+        Ok::<_, AppError>(web::Bytes::from(format!("event: post\ndata: {data}\n\n")))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(header::CacheControl(vec![header::CacheDirective::NoCache]))
+        // compression would buffer events until enough data is collected
+        .insert_header(header::ContentEncoding::Identity)
+        .streaming(events)
+}
+
 pub async fn delete_post(
     req: HttpRequest,
     path: web::Path<i64>,
@@ -95,19 +211,131 @@ pub async fn delete_post(
 }
 
 pub async fn get_posts(
+    req: HttpRequest,
     params: web::Query<GetPostsParams>,
     blog_service: Data<Arc<BlogService>>,
 ) -> Result<HttpResponse, AppError> {
-    let (posts, total_posts_count) = blog_service.get_posts(params.limit, params.offset).await?;
+    let response = blog_service.get_posts(params.limit, params.offset).await?;
+
+    let links = pagination_links(
+        req.path(),
+        response.limit,
+        response.offset,
+        response.total_posts as i64,
+    );
+
+    let mut builder = HttpResponseBuilder::new(StatusCode::OK);
+    builder
+        .insert_header((header::LINK, links))
+        .insert_header(("X-Total-Count", response.total_posts));
+
+    json_with_etag(&req, builder, &response)
+}
+
+pub async fn search_posts(
+    req: HttpRequest,
+    params: web::Query<SearchPostsParams>,
+    blog_service: Data<Arc<BlogService>>,
+) -> Result<HttpResponse, AppError> {
+    let params = params.into_inner();
+    let response = blog_service
+        .search_posts(params.query, params.limit, params.offset)
+        .await?;
+
+    let mut builder = HttpResponseBuilder::new(StatusCode::OK);
+    builder.insert_header(("X-Total-Count", response.total_posts));
+
+    json_with_etag(&req, builder, &response)
+}
+
+/// Builds RFC 5988 `Link` header value with first/prev/next/last pages
+fn pagination_links(path: &str, limit: i64, offset: i64, total: i64) -> String {
+    let link =
+        |offset: i64, rel: &str| format!("<{path}?limit={limit}&offset={offset}>; rel=\"{rel}\"");
+
+    let mut links = vec![link(0, "first")];
+
+    if limit > 0 {
+        if offset > 0 {
+            links.push(link((offset - limit).max(0), "prev"));
+        }
+
+        if offset.saturating_add(limit) < total {
+            links.push(link(offset + limit, "next"));
+        }
+
+        let last_offset = if total > 0 {
+            (total - 1) / limit * limit
+        } else {
+            0
+        };
+        links.push(link(last_offset, "last"));
+    }
+
+    links.join(", ")
+}
+
+pub async fn get_stats(
+    req: HttpRequest,
+    stats_service: Data<Arc<StatsService>>,
+) -> Result<HttpResponse, AppError> {
+    let stats = stats_service.get_stats().await?;
+
+    json_with_etag(&req, HttpResponseBuilder::new(StatusCode::OK), &stats)
+}
 
-    let response = GetPostsResponse {
-        posts,
-        total_posts: total_posts_count,
-        limit: params.limit,
-        offset: params.offset,
+pub async fn get_user_stats(
+    req: HttpRequest,
+    stats_service: Data<Arc<StatsService>>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = try_get_user_id(req)?;
+
+    let stats = stats_service.get_user_stats(user_id).await?;
+
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(stats))
+}
+
+pub async fn get_info(server_info: web::Data<ServerInfo>) -> HttpResponse {
+    HttpResponse::Ok().json(server_info.get_ref())
+}
+
+/// Serializes body and tags it with ETag, answering `304 Not Modified`
+/// if the client already has the same representation
+///
+/// The tag is a SHA-256 prefix of the body, so it stays the same across server builds.
+/// `304` keeps headers set on `builder`, e.g. `Link` and `X-Total-Count` of lists
+fn json_with_etag(
+    req: &HttpRequest,
+    mut builder: HttpResponseBuilder,
+    value: &impl Serialize,
+) -> Result<HttpResponse, AppError> {
+    let body = serde_json::to_vec(value)?;
+
+    let digest = Sha256::digest(&body);
+    let etag = EntityTag::new_strong(
+        digest[..16]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect(),
+    );
+
+    let not_modified = match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+        None => false,
     };
 
-    Ok(HttpResponseBuilder::new(StatusCode::OK).json(response))
+    if not_modified {
+        return Ok(builder
+            .status(StatusCode::NOT_MODIFIED)
+            .insert_header(ETag(etag))
+            .finish());
+    }
+
+    Ok(builder
+        .insert_header(ETag(etag))
+        .content_type(header::ContentType::json())
+        .body(body))
 }
 
 fn try_get_user_id(req: HttpRequest) -> Result<i64, AppError> {
@@ -117,28 +345,220 @@ fn try_get_user_id(req: HttpRequest) -> Result<i64, AppError> {
     }
 }
 
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    match err {
+        JsonPayloadError::OverflowKnownLength { limit, .. }
+        | JsonPayloadError::Overflow { limit } => AppError::PayloadTooLarge { limit }.into(),
+        other => other.into(),
+    }
+}
+
 impl ResponseError for AppError {
-    fn error_response(&self) -> HttpResponse {
-        let status = match self {
+    fn status_code(&self) -> StatusCode {
+        match self {
             AppError::UserNotFound { .. } => StatusCode::UNAUTHORIZED,
             AppError::UserAlreadyExists => StatusCode::CONFLICT,
             AppError::InvalidCredentials => StatusCode::UNAUTHORIZED,
             AppError::PostNotFound => StatusCode::NOT_FOUND,
             AppError::Forbidden => StatusCode::FORBIDDEN,
+            AppError::Validation { .. } => StatusCode::BAD_REQUEST,
+            AppError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::FieldTooLong { .. } => StatusCode::BAD_REQUEST,
+            AppError::Maintenance(_) => StatusCode::SERVICE_UNAVAILABLE,
             AppError::InvalidToken => StatusCode::UNAUTHORIZED,
+            AppError::InvalidCsrfToken => StatusCode::FORBIDDEN,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
-        };
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        LocalizedError::new(self, Locale::default()).error_response()
+    }
+}
+
+/// Application error with message translated to the requested locale
+#[derive(Debug)]
+pub struct LocalizedError {
+    status: StatusCode,
+    code: ErrorCode,
+    message: String,
+    field: Option<&'static str>,
+}
+
+impl LocalizedError {
+    pub fn new(error: &AppError, locale: Locale) -> Self {
+        Self {
+            status: error.status_code(),
+            code: error.code(),
+            message: localized_message(error, locale),
+            field: error.field(),
+        }
+    }
+}
+
+impl Display for LocalizedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
 
-        let description = ErrorDescription {
-            error: self.to_string(),
-            status: status.as_u16(),
+impl ResponseError for LocalizedError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let description = ErrorResponse {
+            error: self.message.clone(),
+            status: self.status.as_u16(),
+            code: self.code.as_str().to_string(),
+            fields: self
+                .field
+                .map(|field| FieldViolation {
+                    field: field.to_string(),
+                    message: self.message.clone(),
+                })
+                .into_iter()
+                .collect(),
         };
-        HttpResponse::build(status).json(serde_json::json!(description))
+        HttpResponse::build(self.status).json(description)
+    }
+}
+
+/// Token with public fields of the user, password hash is never sent
+impl From<UserAndToken> for AuthResponse {
+    fn from(value: UserAndToken) -> Self {
+        Self {
+            token: value.token,
+            user: AuthUser {
+                id: value.user.id,
+                username: value.user.username,
+                email: value.user.email,
+            },
+        }
     }
 }
 
-#[derive(Debug, Serialize)]
-struct ErrorDescription {
-    error: String,
-    status: u16,
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Offset of the link with given relation type in `Link` header value
+    fn link_offset(links: &str, rel: &str) -> Option<i64> {
+        let link = links
+            .split(", ")
+            .find(|link| link.ends_with(&format!("rel=\"{rel}\"")))?;
+        let start = link.find("offset=")? + "offset=".len();
+        let end = link.find('>')?;
+        Some(link[start..end].parse().unwrap())
+    }
+
+    fn percent_encode(value: &str) -> String {
+        value.bytes().map(|byte| format!("%{byte:02X}")).collect()
+    }
+
+    #[test]
+    fn etag_is_stable_and_not_modified_keeps_list_headers() {
+        // first half of SHA-256 of `{"posts":[]}`
+        let etag = "\"a14592560126042a799e0da2b44a5dc4\"";
+        let body = serde_json::json!({"posts": []});
+        let list_builder = || {
+            let mut builder = HttpResponseBuilder::new(StatusCode::OK);
+            builder
+                .insert_header((
+                    header::LINK,
+                    "</api/posts?limit=10&offset=0>; rel=\"first\"",
+                ))
+                .insert_header(("X-Total-Count", 0));
+            builder
+        };
+
+        let req = actix_web::test::TestRequest::default().to_http_request();
+        let response = json_with_etag(&req, list_builder(), &body).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(header::ETAG).unwrap(), etag);
+
+        let req = actix_web::test::TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, etag))
+            .to_http_request();
+        let response = json_with_etag(&req, list_builder(), &body).unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get(header::ETAG).unwrap(), etag);
+        assert!(response.headers().contains_key(header::LINK));
+        assert_eq!(response.headers().get("X-Total-Count").unwrap(), "0");
+    }
+
+    #[test]
+    fn too_long_field_is_validation_error_not_too_large_body() {
+        let error = AppError::FieldTooLong {
+            field: "title",
+            max: 10,
+        };
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+
+        let body_limit = AppError::PayloadTooLarge { limit: 1024 };
+        assert_eq!(body_limit.status_code(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    proptest! {
+        #[test]
+        fn any_query_string_is_parsed_without_panics(query in "\\PC{0,40}") {
+            let _ = web::Query::<GetPostsParams>::from_query(&query);
+            let _ = web::Query::<SearchPostsParams>::from_query(&query);
+        }
+
+        #[test]
+        fn page_bounds_are_parsed_exactly_or_defaulted(
+            limit in proptest::option::of(any::<i64>()),
+            offset in proptest::option::of(any::<i64>()),
+        ) {
+            let mut params = Vec::new();
+            params.extend(limit.map(|limit| format!("limit={limit}")));
+            params.extend(offset.map(|offset| format!("offset={offset}")));
+
+            let parsed = web::Query::<GetPostsParams>::from_query(&params.join("&")).unwrap();
+
+            prop_assert_eq!(parsed.limit, limit.unwrap_or(10));
+            prop_assert_eq!(parsed.offset, offset.unwrap_or(0));
+        }
+
+        #[test]
+        fn unicode_search_query_is_decoded_intact(query in "\\PC{0,20}") {
+            let encoded = format!("query={}", percent_encode(&query));
+
+            let parsed = web::Query::<SearchPostsParams>::from_query(&encoded).unwrap();
+
+            prop_assert_eq!(&parsed.query, &query);
+        }
+
+        #[test]
+        fn pagination_links_stay_within_bounds(
+            limit in 0i64..=i64::MAX,
+            offset in 0i64..=i64::MAX,
+            total in 0i64..=i64::MAX,
+        ) {
+            let links = pagination_links("/api/posts", limit, offset, total);
+
+            prop_assert_eq!(link_offset(&links, "first"), Some(0));
+            if limit == 0 {
+                prop_assert_eq!(links.split(", ").count(), 1);
+                return Ok(());
+            }
+
+            let last = link_offset(&links, "last").unwrap();
+            prop_assert!(last >= 0 && last % limit == 0);
+            prop_assert!(last < total.max(1));
+
+            match link_offset(&links, "next") {
+                Some(next) => prop_assert_eq!(next, offset + limit),
+                None => prop_assert!(offset.saturating_add(limit) >= total),
+            }
+            match link_offset(&links, "prev") {
+                Some(prev) => prop_assert!(prev >= 0 && prev < offset),
+                None => prop_assert_eq!(offset, 0),
+            }
+        }
+    }
 }
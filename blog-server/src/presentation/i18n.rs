@@ -0,0 +1,97 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use crate::domain::error::AppError;
+
+type Catalog = HashMap<String, String>;
+
+static EN_CATALOG: LazyLock<Catalog> =
+    LazyLock::new(|| load_catalog(include_str!("../../locales/en.json")));
+static RU_CATALOG: LazyLock<Catalog> =
+    LazyLock::new(|| load_catalog(include_str!("../../locales/ru.json")));
+
+fn load_catalog(json: &str) -> Catalog {
+    serde_json::from_str(json).expect("message catalog must be valid JSON object")
+}
+
+/// Locales with available message catalogs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    Ru,
+}
+
+impl Locale {
+    /// Picks the best supported locale from `Accept-Language` value, e.g. `ru-RU,ru;q=0.9,en;q=0.8`
+    pub fn from_accept_language(value: &str) -> Self {
+        value
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.trim().split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                let locale = Self::from_tag(tag)?;
+                Some((locale, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(locale, _)| locale)
+            .unwrap_or_default()
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag.split(['-', '_']).next()?;
+        match language.to_ascii_lowercase().as_str() {
+            "en" => Some(Self::En),
+            "ru" => Some(Self::Ru),
+            _ => None,
+        }
+    }
+
+    fn catalog(self) -> &'static Catalog {
+        match self {
+            Self::En => &EN_CATALOG,
+            Self::Ru => &RU_CATALOG,
+        }
+    }
+}
+
+/// Returns error message translated to given locale, errors without catalog entry keep their default text
+pub fn localized_message(error: &AppError, locale: Locale) -> String {
+    let Some(args) = message_args(error) else {
+        return error.to_string();
+    };
+
+    let Some(template) = locale.catalog().get(error.code().as_str()) else {
+        return error.to_string();
+    };
+
+    args.iter()
+        .fold(template.clone(), |message, (name, value)| {
+            message.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+fn message_args(error: &AppError) -> Option<Vec<(&'static str, String)>> {
+    let args = match error {
+        AppError::UserNotFound { username } => vec![("username", username.clone())],
+        AppError::UserAlreadyExists
+        | AppError::InvalidCredentials
+        | AppError::PostNotFound
+        | AppError::Forbidden
+        | AppError::InvalidToken
+        | AppError::InvalidCsrfToken => vec![],
+        AppError::Validation { field, message } => {
+            vec![("field", field.to_string()), ("message", message.clone())]
+        }
+        AppError::PayloadTooLarge { limit } => vec![("limit", limit.to_string())],
+        AppError::FieldTooLong { field, max } => {
+            vec![("field", field.to_string()), ("max", max.to_string())]
+        }
+        _ => return None,
+    };
+
+    Some(args)
+}
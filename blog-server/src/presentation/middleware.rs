@@ -1,11 +1,20 @@
 use std::sync::Arc;
 
-use actix_web::{Error, HttpMessage, dev::ServiceRequest, web};
+use actix_web::{
+    Error, HttpMessage, ResponseError,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header,
+    middleware::Next,
+    web,
+};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
+use blog_types::{CSRF_HEADER, SESSION_COOKIE};
 
 use crate::{
-    domain::user::AuthenticatedUser,
+    domain::{error::AppError, user::AuthenticatedUser},
     infrastructure::jwt::{Claims, JwtService},
+    presentation::{http_handlers::LocalizedError, i18n::Locale},
 };
 
 impl From<Claims> for AuthenticatedUser {
@@ -17,27 +26,92 @@ impl From<Claims> for AuthenticatedUser {
     }
 }
 
+/// Authorizes request by bearer token or, when `Authorization` header is absent, by session cookie
 pub async fn jwt_validator(
     request: ServiceRequest,
-    auth: BearerAuth,
+    auth: Option<BearerAuth>,
 ) -> Result<ServiceRequest, (Error, ServiceRequest)> {
-    match request
-        .app_data::<web::Data<Arc<JwtService>>>()
-        .map(|jwt_service| jwt_service.verify_token(auth.token()))
-    {
-        Some(Ok(claims)) => {
+    let Some(jwt_service) = request.app_data::<web::Data<Arc<JwtService>>>() else {
+        return Err((
+            actix_web::error::ErrorInternalServerError("JwtService is not configured"),
+            request,
+        ));
+    };
+
+    let claims = match auth {
+        Some(auth) => jwt_service
+            .verify_token(auth.token())
+            .map_err(|_| AppError::InvalidToken),
+        None => verify_session(&request, jwt_service),
+    };
+
+    match claims {
+        Ok(claims) => {
             let user = AuthenticatedUser::from(claims);
             request.extensions_mut().insert(user);
 
             Ok(request)
         }
-        Some(Err(_)) => Err((
-            actix_web::error::ErrorUnauthorized("Invalid or expired token"),
-            request,
-        )),
-        None => Err((
-            actix_web::error::ErrorInternalServerError("JwtService is not configured"),
-            request,
-        )),
+        Err(e) => Err((e.into(), request)),
+    }
+}
+
+/// Session cookie is accepted only with the CSRF token it was issued with,
+/// which a page of another site can't read
+fn verify_session(request: &ServiceRequest, jwt_service: &JwtService) -> Result<Claims, AppError> {
+    let cookie = request
+        .cookie(SESSION_COOKIE)
+        .ok_or(AppError::InvalidToken)?;
+    let claims = jwt_service
+        .verify_token(cookie.value())
+        .map_err(|_| AppError::InvalidToken)?;
+
+    let csrf_token = request
+        .headers()
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok());
+    match (&claims.csrf, csrf_token) {
+        (Some(expected), Some(actual)) if expected == actual => Ok(claims),
+        _ => Err(AppError::InvalidCsrfToken),
+    }
+}
+
+/// Re-renders application errors in the language requested through `Accept-Language`
+pub async fn localize_errors<B: MessageBody>(
+    request: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let locale = request
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(Locale::from_accept_language)
+        .unwrap_or_default();
+
+    if locale == Locale::default() {
+        return next
+            .call(request)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    }
+
+    match next.call(request).await {
+        Ok(response) => {
+            let localized = response
+                .response()
+                .error()
+                .and_then(|error| error.as_error::<AppError>())
+                .map(|error| LocalizedError::new(error, locale).error_response());
+
+            Ok(match localized {
+                Some(localized) => response.into_response(localized).map_into_right_body(),
+                None => response.map_into_left_body(),
+            })
+        }
+        // errors raised by inner middleware, e.g. failed token validation
+        Err(error) => match error.as_error::<AppError>() {
+            Some(app_error) => Err(LocalizedError::new(app_error, locale).into()),
+            None => Err(error),
+        },
     }
 }
@@ -1,3 +1,4 @@
 pub mod grpc_service;
 pub mod http_handlers;
+pub mod i18n;
 pub mod middleware;
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /auth/register`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisterRequest {
+    /// unique user name
+    pub username: String,
+    /// user email
+    pub email: String,
+    /// user password
+    pub password: String,
+}
+
+/// Body of `POST /auth/login`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginRequest {
+    /// user name
+    pub username: String,
+    /// user password
+    pub password: String,
+}
+
+/// Response of registration and login
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthResponse {
+    /// JWT for authorized requests
+    pub token: String,
+    /// authenticated user
+    pub user: AuthUser,
+}
+
+/// User returned with the token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthUser {
+    /// user id
+    pub id: i64,
+    /// user name
+    pub username: String,
+    /// user email
+    pub email: String,
+}
+
+/// Name of the HttpOnly cookie holding the token of a cookie session
+pub const SESSION_COOKIE: &str = "blog_session";
+/// Header carrying CSRF token of a cookie session
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+/// How the client keeps the token issued by registration and login
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    /// token is returned in [`AuthResponse`] and sent in `Authorization` header
+    #[default]
+    Bearer,
+    /// token is set as [`SESSION_COOKIE`] which scripts can't read,
+    /// requests sent with it must carry CSRF token from [`SessionResponse`]
+    Cookie,
+}
+
+/// Query of `POST /auth/register` and `POST /auth/login`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthQuery {
+    /// how the issued token is kept, bearer token by default
+    #[serde(default)]
+    pub mode: AuthMode,
+}
+
+/// Response of registration and login in [`AuthMode::Cookie`] mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionResponse {
+    /// token to send in [`CSRF_HEADER`] header with the session cookie
+    pub csrf_token: String,
+    /// expiration time of the session in seconds since Unix epoch
+    pub expires_at: i64,
+    /// authenticated user
+    pub user: AuthUser,
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// Error envelope returned by server with non-success status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    /// stable machine-readable code, e.g. `VALIDATION_FAILED`
+    pub code: String,
+    /// message translated to the requested locale
+    pub error: String,
+    /// HTTP status of the response
+    pub status: u16,
+    /// invalid request fields
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldViolation>,
+}
+
+/// Invalid request field reported by server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldViolation {
+    /// Field name
+    pub field: String,
+    /// Description of the problem
+    pub message: String,
+}
@@ -0,0 +1,17 @@
+#![deny(unreachable_pub)]
+#![warn(missing_docs)]
+
+//! Request and response bodies of the blog HTTP API
+//!
+//! Shared by server and clients, so both sides of the API agree on field names and types
+
+mod auth;
+mod error;
+mod post;
+
+pub use auth::{
+    AuthMode, AuthQuery, AuthResponse, AuthUser, CSRF_HEADER, LoginRequest, RegisterRequest,
+    SESSION_COOKIE, SessionResponse,
+};
+pub use error::{ErrorResponse, FieldViolation};
+pub use post::{CreatePostRequest, SearchPostsQuery, UpdatePostRequest};
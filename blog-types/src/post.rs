@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /posts`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePostRequest {
+    /// post title
+    pub title: String,
+    /// post content
+    pub content: String,
+}
+
+/// Body of `PUT /posts/{id}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdatePostRequest {
+    /// new post title
+    pub title: String,
+    /// new post content
+    pub content: String,
+}
+
+/// Query of `GET /posts/search`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchPostsQuery {
+    /// search query
+    pub query: String,
+    /// number of posts to return, server default if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    /// number of posts to skip
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+}
@@ -0,0 +1,46 @@
+//! Benchmarks of Markdown rendering on the host: `cargo bench -p blog-wasm`
+
+use std::hint::black_box;
+
+use blog_wasm::render_markdown;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+const SECTION: &str = "## Section
+
+Paragraph with *emphasis*, **strong text**, `inline code` and a [link](https://example.com).
+~~Outdated~~ statement.
+
+- [x] done item
+- [ ] pending item
+
+| Column | Value |
+|--------|-------|
+| first  | 1     |
+| second | 2     |
+
+```rust
+fn main() {
+    println!(\"hello\");
+}
+```
+
+<script>alert(1)</script><img src=x onerror=alert(1)>
+
+";
+
+fn markdown(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render_markdown");
+    for sections in [1, 10, 100] {
+        let content = SECTION.repeat(sections);
+        group.throughput(Throughput::Bytes(content.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(content.len()),
+            &content,
+            |b, content| b.iter(|| render_markdown(black_box(content))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, markdown);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+//! In-memory cache of fetched posts, so back navigation doesn't refetch them
+
+use std::collections::HashMap;
+
+use blog_client::blog_client::{Post, PostEvent, PostsCollection};
+
+/// Pages of posts by `(offset, limit)` and posts by id
+///
+/// Pages are dropped on any change of posts since the change may shift them
+#[derive(Default)]
+pub(crate) struct PostsCache {
+    pages: HashMap<(u64, u64), PostsCollection>,
+    posts: HashMap<i64, Post>,
+}
+
+impl PostsCache {
+    pub(crate) fn page(&self, offset: u64, limit: u64) -> Option<PostsCollection> {
+        self.pages.get(&(offset, limit)).cloned()
+    }
+
+    /// Keeps page and each of its posts
+    pub(crate) fn insert_page(&mut self, offset: u64, limit: u64, page: &PostsCollection) {
+        for post in &page.posts {
+            self.posts.insert(post.id, post.clone());
+        }
+        self.pages.insert((offset, limit), page.clone());
+    }
+
+    pub(crate) fn post(&self, id: i64) -> Option<Post> {
+        self.posts.get(&id).cloned()
+    }
+
+    pub(crate) fn insert_post(&mut self, post: &Post) {
+        self.posts.insert(post.id, post.clone());
+    }
+
+    /// Keeps created or updated post, cached pages are invalidated
+    pub(crate) fn post_changed(&mut self, post: &Post) {
+        self.pages.clear();
+        self.insert_post(post);
+    }
+
+    pub(crate) fn post_deleted(&mut self, id: i64) {
+        self.pages.clear();
+        self.posts.remove(&id);
+    }
+
+    /// Applies change made by any user and received from the server
+    pub(crate) fn apply(&mut self, event: &PostEvent) {
+        match event {
+            PostEvent::Created(post) | PostEvent::Updated(post) => self.post_changed(post),
+            PostEvent::Deleted { id } => self.post_deleted(*id),
+        }
+    }
+}
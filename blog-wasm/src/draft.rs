@@ -0,0 +1,142 @@
+//! Work in progress of the post editor, saved to local storage while user types
+//!
+//! Every user of the browser has own draft, so it is not shown to the next user who logs in
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::{error::AppError, session::local_storage};
+
+const DRAFT_KEY_PREFIX: &str = "post_draft";
+/// Pause in typing after which the draft is saved
+const AUTOSAVE_DELAY_MS: i32 = 1000;
+
+/// Draft of a new post or of changes to an existing one
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Draft {
+    /// Edited post, `None` for a new post
+    pub(crate) post_id: Option<i64>,
+    pub(crate) title: String,
+    pub(crate) content: String,
+    /// Time of the last change in milliseconds since Unix epoch
+    pub(crate) saved_at: f64,
+}
+
+/// Debounced saving of a single draft
+///
+/// Every change restarts the timer, so the draft is written once user stops typing
+pub(crate) struct DraftAutosave {
+    state: Rc<State>,
+}
+
+struct State {
+    /// Storage key and the draft waiting for the timer
+    pending: RefCell<Option<(String, Draft)>>,
+    timer: Cell<Option<i32>>,
+    on_timer: Closure<dyn FnMut()>,
+}
+
+impl DraftAutosave {
+    pub(crate) fn new() -> Self {
+        let state = Rc::new_cyclic(|state: &Weak<State>| State {
+            pending: RefCell::new(None),
+            timer: Cell::new(None),
+            on_timer: Closure::new({
+                let state = state.clone();
+                move || {
+                    if let Some(state) = state.upgrade() {
+                        state.timer.set(None);
+                        if let Err(e) = state.flush() {
+                            web_sys::console::error_1(&e.into());
+                        }
+                    }
+                }
+            }),
+        });
+
+        Self { state }
+    }
+
+    /// Schedules saving of the draft of the user, replacing the one waiting for the timer
+    pub(crate) fn schedule(&self, user_id: Option<i64>, draft: Draft) -> Result<(), AppError> {
+        self.state.cancel_timer();
+        self.state.flush()?;
+        *self.state.pending.borrow_mut() = Some((draft_key(user_id), draft));
+
+        let window = web_sys::window().ok_or(AppError::LocalStorageUnavailable)?;
+        let timer = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+            self.state.on_timer.as_ref().unchecked_ref(),
+            AUTOSAVE_DELAY_MS,
+        )?;
+        self.state.timer.set(Some(timer));
+        Ok(())
+    }
+
+    /// Draft of the user waiting for the timer or the saved one
+    pub(crate) fn load(&self, user_id: Option<i64>) -> Result<Option<Draft>, AppError> {
+        self.state.flush()?;
+        let Some(json) = local_storage()?.get_item(&draft_key(user_id))? else {
+            return Ok(None);
+        };
+        Ok(Some(serde_json::from_str(&json)?))
+    }
+
+    /// Forgets the draft of the user, e.g. after the post is published or on logout
+    pub(crate) fn discard(&self, user_id: Option<i64>) -> Result<(), AppError> {
+        let key = draft_key(user_id);
+        let is_pending = self
+            .state
+            .pending
+            .borrow()
+            .as_ref()
+            .is_some_and(|(pending_key, _)| *pending_key == key);
+        if is_pending {
+            self.state.cancel_timer();
+            self.state.pending.borrow_mut().take();
+        }
+        local_storage()?.remove_item(&key)?;
+        Ok(())
+    }
+}
+
+impl Drop for DraftAutosave {
+    fn drop(&mut self) {
+        self.state.cancel_timer();
+        if let Err(e) = self.state.flush() {
+            web_sys::console::error_1(&e.into());
+        }
+    }
+}
+
+impl State {
+    fn cancel_timer(&self) {
+        if let Some(timer) = self.timer.take()
+            && let Some(window) = web_sys::window()
+        {
+            window.clear_timeout_with_handle(timer);
+        }
+    }
+
+    /// Writes the pending draft, if any
+    fn flush(&self) -> Result<(), AppError> {
+        let Some((key, draft)) = self.pending.borrow_mut().take() else {
+            return Ok(());
+        };
+        let json = serde_json::to_string(&draft)?;
+        local_storage()?.set_item(&key, &json)?;
+        Ok(())
+    }
+}
+
+/// Storage key of the draft of a user, drafts written without login are kept apart
+fn draft_key(user_id: Option<i64>) -> String {
+    match user_id {
+        Some(user_id) => format!("{DRAFT_KEY_PREFIX}:{user_id}"),
+        None => format!("{DRAFT_KEY_PREFIX}:guest"),
+    }
+}
@@ -1,27 +1,143 @@
+use blog_client::error::BlogClientError;
+use blog_types::{ErrorResponse, FieldViolation};
+use js_sys::Reflect;
 use thiserror::Error;
 use wasm_bindgen::JsValue;
 
 #[derive(Debug, Error)]
-pub enum AppError {
+pub(crate) enum AppError {
     #[error("Can't access local storage")]
     LocalStorageUnavailable,
     #[error("Error converting object to/from JSON: {0}")]
     JsonError(#[from] serde_json::Error),
+    #[error("Error converting object to/from JS value: {0}")]
+    JsConversion(#[from] serde_wasm_bindgen::Error),
+    #[error("{0}")]
+    Client(#[from] BlogClientError),
+    /// Error response of a request made without the library client
+    #[error("{}", .0.error)]
+    Server(ErrorResponse),
+    /// `fetch` rejected, server can't be reached
+    #[error("Server is unreachable: {0:?}")]
+    Offline(JsValue),
+    #[error("Server event without text data")]
+    InvalidEvent,
     #[error("JsValue: {}", 0.to_string())]
     JsValue(wasm_bindgen::JsValue),
 }
 
+/// Properties of error object passed to JavaScript
+struct ErrorDetails {
+    /// Server error code, e.g. `VALIDATION_FAILED`, or one of client side codes
+    /// `OFFLINE`, `NETWORK_ERROR`, `TIMEOUT`, `ABORTED`, `NOT_AUTHENTICATED`, `RATE_LIMITED`,
+    /// `UNEXPECTED_RESPONSE`, `CLIENT_ERROR`
+    code: String,
+    /// HTTP status, `None` if no response was received
+    status: Option<u16>,
+    message: String,
+    field_errors: Vec<FieldViolation>,
+}
+
+impl AppError {
+    fn details(&self) -> ErrorDetails {
+        match self {
+            AppError::Server(response) => ErrorDetails::from_response(response),
+            AppError::Client(BlogClientError::UnexpectedHttpResponse { code, message }) => {
+                // body of unexpected response is the server error envelope
+                match serde_json::from_str::<ErrorResponse>(message) {
+                    Ok(response) => ErrorDetails::from_response(&response),
+                    Err(_) => ErrorDetails::new("UNEXPECTED_RESPONSE", Some(*code), self),
+                }
+            }
+            AppError::Client(BlogClientError::Validation { message, fields }) => ErrorDetails {
+                code: String::from("VALIDATION_FAILED"),
+                status: Some(400),
+                message: message.clone(),
+                field_errors: fields.clone(),
+            },
+            AppError::Client(error) => {
+                let (code, status) = client_error_code(error);
+                ErrorDetails::new(code, status, self)
+            }
+            AppError::Offline(_) => ErrorDetails::new("OFFLINE", None, self),
+            AppError::LocalStorageUnavailable
+            | AppError::InvalidEvent
+            | AppError::JsonError(_)
+            | AppError::JsConversion(_)
+            | AppError::JsValue(_) => ErrorDetails::new("CLIENT_ERROR", None, self),
+        }
+    }
+}
+
+impl ErrorDetails {
+    fn new(code: &str, status: Option<u16>, error: &AppError) -> Self {
+        Self {
+            code: String::from(code),
+            status,
+            message: error.to_string(),
+            field_errors: Vec::new(),
+        }
+    }
+
+    fn from_response(response: &ErrorResponse) -> Self {
+        Self {
+            code: response.code.clone(),
+            status: Some(response.status),
+            message: response.error.clone(),
+            field_errors: response.fields.clone(),
+        }
+    }
+}
+
+/// Server error code and HTTP status of errors recognized by the library client
+fn client_error_code(error: &BlogClientError) -> (&'static str, Option<u16>) {
+    match error {
+        BlogClientError::UserAlreadyExists => ("USER_ALREADY_EXISTS", Some(409)),
+        BlogClientError::InvalidCredentials => ("INVALID_CREDENTIALS", Some(401)),
+        BlogClientError::InvalidToken => ("INVALID_TOKEN", Some(401)),
+        BlogClientError::Forbidden => ("FORBIDDEN", Some(403)),
+        BlogClientError::NotFound => ("POST_NOT_FOUND", Some(404)),
+        BlogClientError::RateLimited { .. } => ("RATE_LIMITED", Some(429)),
+        BlogClientError::ReadOnly(_) => ("MAINTENANCE", Some(503)),
+        BlogClientError::Timeout => ("TIMEOUT", None),
+        BlogClientError::Cancelled => ("ABORTED", None),
+        BlogClientError::TokenNotSet => ("NOT_AUTHENTICATED", None),
+        BlogClientError::Reqwest(e) => match e.status() {
+            Some(status) => ("UNEXPECTED_RESPONSE", Some(status.as_u16())),
+            None if error.is_unreachable() => ("OFFLINE", None),
+            None => ("NETWORK_ERROR", None),
+        },
+        _ => ("CLIENT_ERROR", None),
+    }
+}
+
 impl From<JsValue> for AppError {
     fn from(value: JsValue) -> Self {
         AppError::JsValue(value)
     }
 }
 
+/// JS `Error` with `code`, `status` and `field_errors` (array of `{ field, message }`) properties,
+/// errors thrown by JS code are passed as is
 impl From<AppError> for JsValue {
     fn from(value: AppError) -> Self {
-        match value {
-            AppError::JsValue(js_value) => js_value,
-            other => JsValue::from_str(other.to_string().as_str()),
+        if let AppError::JsValue(js_value) = value {
+            return js_value;
         }
+
+        let details = value.details();
+        let error = js_sys::Error::new(&details.message);
+        let field_errors =
+            serde_wasm_bindgen::to_value(&details.field_errors).unwrap_or(JsValue::NULL);
+        // properties can always be defined on a new Error object
+        let _ = Reflect::set(&error, &"code".into(), &details.code.into());
+        let _ = Reflect::set(
+            &error,
+            &"status".into(),
+            &details.status.map_or(JsValue::NULL, JsValue::from),
+        );
+        let _ = Reflect::set(&error, &"field_errors".into(), &field_errors);
+
+        error.into()
     }
 }
@@ -0,0 +1,22 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::Deserialize;
+
+/// Claims of a token issued by the server, read without signature verification
+#[derive(Debug, Deserialize)]
+pub(crate) struct Claims {
+    exp: i64,
+}
+
+impl Claims {
+    /// Decodes payload of JWT, `None` if token has unexpected format
+    pub(crate) fn decode(token: &str) -> Option<Self> {
+        let payload = token.split('.').nth(1)?;
+        let payload = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+        serde_json::from_slice(&payload).ok()
+    }
+
+    /// Expiration time in milliseconds since Unix epoch, as used by JS `Date`
+    pub(crate) fn expires_at_millis(&self) -> f64 {
+        self.exp as f64 * 1000.0
+    }
+}
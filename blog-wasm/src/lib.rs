@@ -3,44 +3,107 @@
 
 //! WASM blog client
 
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Duration,
+};
+
+use blog_client::{
+    Transport,
+    blog_client::{BlogClient, Post},
+    error::BlogClientError,
+    middleware::Url,
+    options::{CancellationToken, RequestOptions},
+    retry::RetryPolicy,
+};
+use blog_types::{
+    AuthMode, AuthResponse, ErrorResponse, LoginRequest, RegisterRequest, SessionResponse,
+};
 use gloo_net::http::Method;
-use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{Request, RequestInit, RequestMode, Response, Storage};
+use wasm_bindgen_futures::{JsFuture, spawn_local};
+use web_sys::{AbortSignal, Request, RequestCredentials, RequestInit, RequestMode, Response};
 
 use crate::{
-    dto::{
-        LoginRequest, LoginResponse, Post, PostCollection, PostData, RegisterRequest,
-        RegisterResponse,
-    },
+    cache::PostsCache,
+    draft::{Draft, DraftAutosave},
+    dto::CurrentUser,
     error::AppError,
+    optimistic::{now, settle},
+    pager::{PageSource, PostsPager},
+    session::{AuthData, Session},
+    subscription::PostsSubscription,
+    types::{JsCurrentUser, JsDraft, JsPost, to_js},
 };
 
+mod cache;
+mod draft;
 mod dto;
 mod error;
+mod jwt;
+mod markdown;
+mod optimistic;
+mod pager;
+mod session;
+mod subscription;
+mod types;
+
+pub use markdown::render_markdown;
 
-const AUTH_DATA_KEY: &str = "auth_data";
+const DEFAULT_API_PREFIX: &str = "/api";
+/// Time given to a read including its retries
+const CALL_BUDGET: Duration = Duration::from_secs(20);
 
 /// Struct for WASM blog client
 #[wasm_bindgen]
 pub struct BlogApp {
     server_url: String,
-    auth_data: Option<AuthData>,
+    api_prefix: String,
+    /// Base of API endpoints, always ends with slash
+    api_url: Url,
+    /// Cleared by requests rejected with 401, which take `&self` so JS can run them concurrently
+    session: Rc<Session>,
+    last_temp_id: Cell<i64>,
+    /// Shared with post subscriptions, which keep it up to date
+    cache: Rc<RefCell<PostsCache>>,
+    auth_mode: AuthMode,
+    drafts: DraftAutosave,
 }
 
 #[wasm_bindgen]
 impl BlogApp {
     /// Create new client
+    ///
+    /// `api_prefix` is the path under which server API is mounted, `/api` by default,
+    /// e.g. `/blog/api` when the server is behind a reverse proxy.
+    /// Path of `server_url` is not used, leading and trailing slashes of prefix are optional.
+    ///
+    /// With `cookie_auth` set to `true` the token is kept by the browser in an HttpOnly
+    /// session cookie, which scripts can't steal, and only the CSRF token of the session
+    /// is stored in local storage
     #[wasm_bindgen(constructor)]
-    pub fn new(server_url: String) -> Result<BlogApp, JsValue> {
-        let mut app = BlogApp {
-            server_url,
-            auth_data: None,
+    pub fn new(
+        server_url: String,
+        api_prefix: Option<String>,
+        cookie_auth: Option<bool>,
+    ) -> Result<BlogApp, JsValue> {
+        let api_prefix = api_prefix.unwrap_or_else(|| String::from(DEFAULT_API_PREFIX));
+        let api_url = api_url(&server_url, &api_prefix)?;
+        let auth_mode = match cookie_auth {
+            Some(true) => AuthMode::Cookie,
+            _ => AuthMode::Bearer,
         };
-
-        app.auth_data = app.load_auth_data()?;
-        Ok(app)
+        Ok(BlogApp {
+            server_url,
+            api_prefix,
+            api_url,
+            session: Rc::new(Session::restore()?),
+            last_temp_id: Cell::new(0),
+            cache: Rc::default(),
+            auth_mode,
+            drafts: DraftAutosave::new(),
+        })
     }
 
     /// Register request
@@ -50,68 +113,87 @@ impl BlogApp {
         username: String,
         email: String,
         password: String,
-    ) -> Result<JsValue, JsValue> {
-        let url = format!("{}/auth/register", self.server_url);
+    ) -> Result<String, JsValue> {
+        let url = self.auth_endpoint("auth/register")?;
         let body = serde_json::json!(RegisterRequest {
             username,
             email,
             password
         });
 
-        let response = Self::request(Method::POST, &url, Some(body), None).await?;
-        let auth_response: RegisterResponse = serde_wasm_bindgen::from_value(response)?;
-        let auth_data = AuthData::from(auth_response);
-
-        self.save_auth_data(&auth_data)?;
-        self.auth_data = Some(auth_data);
+        let response = self.request(Method::POST, &url, Some(body)).await?;
+        self.session.login(self.auth_data(response)?)?;
 
-        Ok(serde_wasm_bindgen::to_value("register success")?)
+        Ok(String::from("register success"))
     }
 
     /// Login request
     #[wasm_bindgen]
-    pub async fn login(&mut self, username: String, password: String) -> Result<JsValue, JsValue> {
-        let url = format!("{}/auth/login", self.server_url);
+    pub async fn login(&mut self, username: String, password: String) -> Result<String, JsValue> {
+        let url = self.auth_endpoint("auth/login")?;
         let body = serde_json::json!(LoginRequest { username, password });
 
-        let response = Self::request(Method::POST, &url, Some(body), None).await?;
-        let login_response: LoginResponse = serde_wasm_bindgen::from_value(response)?;
-        let auth_data = AuthData::from(login_response);
+        let response = self.request(Method::POST, &url, Some(body)).await?;
+        self.session.login(self.auth_data(response)?)?;
 
-        self.save_auth_data(&auth_data)?;
-        self.auth_data = Some(auth_data);
-
-        Ok(serde_wasm_bindgen::to_value("log in success")?)
+        Ok(String::from("log in success"))
     }
 
-    /// Logout request
+    /// Logout request, session cookie is removed by server in cookie auth mode
     #[wasm_bindgen]
-    pub async fn logout(&mut self) -> Result<JsValue, JsValue> {
-        self.auth_data = None;
-        self.delete_auth_data()?;
+    pub async fn logout(&mut self) -> Result<String, JsValue> {
+        let result = match self.auth_mode {
+            AuthMode::Cookie => {
+                let url = self.endpoint("auth/logout")?;
+                self.request(Method::POST, &url, None).await.map(drop)
+            }
+            AuthMode::Bearer => Ok(()),
+        };
+        // stored data is forgotten even if server is unreachable
+        self.drafts.discard(self.session.user_id())?;
+        self.session.logout()?;
+        result?;
 
-        Ok(serde_wasm_bindgen::to_value("log out success")?)
+        Ok(String::from("log out success"))
     }
 
-    /// Load posts request
+    /// Load posts request, rejected with `ABORTED` code when `signal` is aborted
+    ///
+    /// Page loaded before is taken from cache unless `refresh` is `true`
     #[wasm_bindgen]
-    pub async fn load_posts(&self, offset: u64, limit: u64) -> Result<JsValue, JsValue> {
-        let url = format!("{}/posts?offset={offset}&limit={limit}", self.server_url);
+    pub async fn load_posts(
+        &self,
+        offset: u64,
+        limit: u64,
+        signal: Option<AbortSignal>,
+        refresh: Option<bool>,
+    ) -> Result<PostsPager, JsValue> {
+        let source = PageSource::new(self.client().await?, self.cache.clone(), None);
+        let refresh = refresh.unwrap_or_default();
+        Ok(source.load(offset, limit, signal, refresh).await?)
+    }
 
-        let response = Self::request(Method::GET, &url, None, None).await?;
-        let posts = serde_wasm_bindgen::from_value::<PostCollection>(response)?;
-        Ok(serde_wasm_bindgen::to_value(&posts)?)
+    /// Search posts request, most relevant posts first.
+    /// Rejected with `ABORTED` code when `signal` is aborted
+    #[wasm_bindgen]
+    pub async fn search_posts(
+        &self,
+        query: String,
+        offset: u64,
+        limit: u64,
+        signal: Option<AbortSignal>,
+    ) -> Result<PostsPager, JsValue> {
+        let source = PageSource::new(self.client().await?, self.cache.clone(), Some(query));
+        Ok(source.load(offset, limit, signal, false).await?)
     }
 
     /// Create post request
     #[wasm_bindgen]
-    pub async fn create_post(&self, title: String, content: String) -> Result<JsValue, JsValue> {
-        let url = format!("{}/posts", self.server_url);
-        let body = serde_json::json!(PostData { title, content });
-
-        let response = Self::request(Method::POST, &url, Some(body), self.token_opt()).await?;
-        let post = serde_wasm_bindgen::from_value::<Post>(response)?;
-        Ok(serde_wasm_bindgen::to_value(&post)?)
+    pub async fn create_post(&self, title: String, content: String) -> Result<JsPost, JsValue> {
+        let result = self.client().await?.create_post(title, content).await;
+        let post = self.session.logout_if_unauthorized(result)?;
+        self.cache.borrow_mut().post_changed(&post);
+        Ok(to_js(&post)?)
     }
 
     /// Update post request
@@ -121,102 +203,339 @@ impl BlogApp {
         id: i64,
         title: String,
         content: String,
-    ) -> Result<JsValue, JsValue> {
-        let url = format!("{}/posts/{}", self.server_url, id);
-        let body = serde_json::json!(PostData { title, content });
-
-        let response = Self::request(Method::PUT, &url, Some(body), self.token_opt()).await?;
-        let post = serde_wasm_bindgen::from_value::<Post>(response)?;
-        Ok(serde_wasm_bindgen::to_value(&post)?)
+    ) -> Result<JsPost, JsValue> {
+        let result = self.client().await?.update_post(id, title, content).await;
+        let post = self.session.logout_if_unauthorized(result)?;
+        self.cache.borrow_mut().post_changed(&post);
+        Ok(to_js(&post)?)
     }
 
     /// Delete post request
     #[wasm_bindgen]
-    pub async fn delete_post(&self, id: i64) -> Result<JsValue, JsValue> {
-        let url = format!("{}/posts/{}", self.server_url, id);
-        Self::request(Method::DELETE, &url, None, self.token_opt()).await
+    pub async fn delete_post(&self, id: i64) -> Result<(), JsValue> {
+        let result = self.client().await?.delete_post(id).await;
+        self.session.logout_if_unauthorized(result)?;
+        self.cache.borrow_mut().post_deleted(id);
+        Ok(())
+    }
+
+    /// Logged in user request, rejected with `NOT_AUTHENTICATED` code if not logged in
+    #[wasm_bindgen]
+    pub async fn get_current_user(&self) -> Result<JsCurrentUser, JsValue> {
+        let result = self.client().await?.user_stats().await;
+        let stats = self.session.logout_if_unauthorized(result)?;
+        let user = CurrentUser {
+            id: stats.user_id,
+            username: stats.username,
+            posts: stats.posts,
+        };
+        Ok(to_js(&user)?)
+    }
+
+    /// Create post without waiting for server
+    ///
+    /// Provisional post with negative temporary id is returned at once, `on_settled`
+    /// is called with the created post or with the error when server answers
+    #[wasm_bindgen]
+    pub fn create_post_optimistic(
+        &self,
+        title: String,
+        content: String,
+        #[wasm_bindgen(unchecked_param_type = "(settlement: Settlement) => void")]
+        on_settled: js_sys::Function,
+    ) -> Result<JsPost, JsValue> {
+        let author_id = self.author_id()?;
+        let temp_id = self.next_temp_id();
+        let now = now();
+        let provisional = Post {
+            id: temp_id,
+            title: title.clone(),
+            content: content.clone(),
+            author_id,
+            author_username: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let cache = self.cache.clone();
+        self.spawn_call(
+            async move |client| client.create_post(title, content).await,
+            move |result| {
+                if let Ok(post) = &result {
+                    cache.borrow_mut().post_changed(post);
+                }
+                settle(&on_settled, temp_id, result.map(Some));
+            },
+        );
+
+        Ok(to_js(&provisional)?)
+    }
+
+    /// Update post without waiting for server
+    ///
+    /// Provisional post with new title and content is returned at once, `on_settled`
+    /// is called with `temp_id` equal to post id when server answers
+    #[wasm_bindgen]
+    pub fn update_post_optimistic(
+        &self,
+        id: i64,
+        title: String,
+        content: String,
+        #[wasm_bindgen(unchecked_param_type = "(settlement: Settlement) => void")]
+        on_settled: js_sys::Function,
+    ) -> Result<JsPost, JsValue> {
+        let now = now();
+        let provisional = match self.cache.borrow().post(id) {
+            Some(post) => Post {
+                title: title.clone(),
+                content: content.clone(),
+                updated_at: now,
+                ..post
+            },
+            None => Post {
+                id,
+                title: title.clone(),
+                content: content.clone(),
+                author_id: self.author_id()?,
+                author_username: None,
+                created_at: now,
+                updated_at: now,
+            },
+        };
+
+        let cache = self.cache.clone();
+        self.spawn_call(
+            async move |client| client.update_post(id, title, content).await,
+            move |result| {
+                if let Ok(post) = &result {
+                    cache.borrow_mut().post_changed(post);
+                }
+                settle(&on_settled, id, result.map(Some));
+            },
+        );
+
+        Ok(to_js(&provisional)?)
+    }
+
+    /// Delete post without waiting for server, `on_settled` is called with `temp_id`
+    /// equal to post id when server answers
+    #[wasm_bindgen]
+    pub fn delete_post_optimistic(
+        &self,
+        id: i64,
+        #[wasm_bindgen(unchecked_param_type = "(settlement: Settlement) => void")]
+        on_settled: js_sys::Function,
+    ) {
+        let cache = self.cache.clone();
+        self.spawn_call(
+            async move |client| client.delete_post(id).await,
+            move |result| {
+                if result.is_ok() {
+                    cache.borrow_mut().post_deleted(id);
+                }
+                settle(&on_settled, id, result.map(|()| None));
+            },
+        );
+    }
+
+    /// Saves draft of the post editor to local storage once user stops typing for a second
+    ///
+    /// `post_id` is the edited post, `null` for a new post. Only the last draft of the
+    /// logged in user is kept, it stays in this browser since server has no storage for drafts
+    /// and is removed on logout
+    #[wasm_bindgen]
+    pub fn autosave_draft(
+        &self,
+        post_id: Option<i64>,
+        title: String,
+        content: String,
+    ) -> Result<(), JsValue> {
+        self.drafts.schedule(
+            self.session.user_id(),
+            Draft {
+                post_id,
+                title,
+                content,
+                saved_at: js_sys::Date::now(),
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Draft of the logged in user saved by `autosave_draft` for editor recovery,
+    /// `undefined` if there is none
+    #[wasm_bindgen]
+    pub fn load_draft(&self) -> Result<Option<JsDraft>, JsValue> {
+        let draft = self.drafts.load(self.session.user_id())?;
+        Ok(draft.map(|draft| to_js(&draft)).transpose()?)
+    }
+
+    /// Removes the draft, called when the post is published or the changes are saved
+    #[wasm_bindgen]
+    pub fn discard_draft(&self) -> Result<(), JsValue> {
+        self.drafts.discard(self.session.user_id())?;
+        Ok(())
     }
 
-    /// Check if user is authenticated
+    /// Check if user is authenticated with a token which has not expired
     #[wasm_bindgen]
     pub fn is_authenticated(&self) -> bool {
-        self.auth_data.is_some()
+        self.session.is_authenticated()
+    }
+
+    /// Registers `callback` called with `true` after login or registration and with `false`
+    /// after logout, expiry of the token or its rejection by server.
+    /// Login and logout in other tabs of the app are reported as well
+    #[wasm_bindgen]
+    pub fn on_auth_change(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "(authenticated: boolean) => void")]
+        callback: js_sys::Function,
+    ) {
+        self.session.on_change(callback);
+    }
+
+    /// Expiration time of the stored token as JS `Date`, `undefined` if not logged in
+    /// or the token can't be decoded
+    #[wasm_bindgen]
+    pub fn token_expires_at(&self) -> Option<js_sys::Date> {
+        let expires_at = self.session.expires_at()?;
+        Some(js_sys::Date::new(&JsValue::from_f64(expires_at)))
     }
 
     /// Check if post belongs to current user
     #[wasm_bindgen]
     pub fn post_belongs_to_current_user(&self, author_id: i64) -> bool {
-        self.auth_data
-            .as_ref()
-            .is_some_and(|ad| ad.user_id == author_id)
+        self.session.user_id() == Some(author_id)
     }
 
-    /// Get post request
+    /// Get post request, rejected with `ABORTED` code when `signal` is aborted
+    ///
+    /// Post loaded before is taken from cache unless `refresh` is `true`
     #[wasm_bindgen]
-    pub async fn get_post(&self, id: i64) -> Result<JsValue, JsValue> {
-        let url = format!("{}/posts/{}", self.server_url, id);
-        let response = Self::request(Method::GET, &url, None, None).await?;
-        Ok(response)
+    pub async fn get_post(
+        &self,
+        id: i64,
+        signal: Option<AbortSignal>,
+        refresh: Option<bool>,
+    ) -> Result<JsPost, JsValue> {
+        let cached = self.cache.borrow().post(id);
+        if let Some(post) = cached.filter(|_| refresh != Some(true)) {
+            return Ok(to_js(&post)?);
+        }
+
+        let client = self.client().await?;
+        let post = run_call(signal, client.get_post(id)).await?;
+        self.cache.borrow_mut().insert_post(&post);
+        Ok(to_js(&post)?)
     }
 
-    fn save_auth_data(&self, auth_data: &AuthData) -> Result<(), AppError> {
-        let storage = self.get_local_storage()?;
-        let json = serde_json::to_string(auth_data)?;
-        storage.set_item(AUTH_DATA_KEY, &json)?;
-        Ok(())
+    /// Subscribes to changes of posts, `callback` is called with every `PostEvent`
+    ///
+    /// Keep the returned subscription while updates are needed and `close()` it afterwards
+    #[wasm_bindgen]
+    pub fn subscribe_posts(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "(event: PostEvent) => void")]
+        callback: js_sys::Function,
+    ) -> Result<PostsSubscription, JsValue> {
+        let url = self.endpoint("posts/events")?;
+        Ok(PostsSubscription::open(url, callback, self.cache.clone())?)
     }
 
-    fn delete_auth_data(&self) -> Result<(), AppError> {
-        let storage = self.get_local_storage()?;
-        storage.remove_item(AUTH_DATA_KEY)?;
-        Ok(())
+    /// Library client for post requests, authorized with the stored token if it is set
+    ///
+    /// Future doesn't borrow the app, so it can be awaited by background tasks
+    fn client(&self) -> impl Future<Output = Result<BlogClient, AppError>> + 'static {
+        let builder = BlogClient::builder(Transport::Http(self.server_url.clone()))
+            .api_prefix(self.api_prefix.clone())
+            .retry_policy(retry_policy())
+            .cookie_session(self.auth_mode == AuthMode::Cookie);
+        let token = self.session.token();
+
+        async move {
+            let client = builder.build().await?;
+            if let Some(token) = token {
+                client.set_token(token)?;
+            }
+            Ok(client)
+        }
     }
 
-    fn load_auth_data(&self) -> Result<Option<AuthData>, AppError> {
-        let storage = self.get_local_storage()?;
-        let json_str = if let Some(json_str) = storage.get_item(AUTH_DATA_KEY)? {
-            json_str
-        } else {
-            return Ok(None);
-        };
-        let data = serde_json::from_str::<AuthData>(&json_str)?;
+    /// Runs client call in background and passes its result to `then`,
+    /// token rejected by server logs user out
+    fn spawn_call<T: 'static>(
+        &self,
+        call: impl AsyncFnOnce(BlogClient) -> Result<T, BlogClientError> + 'static,
+        then: impl FnOnce(Result<T, AppError>) + 'static,
+    ) {
+        let (client, session) = (self.client(), self.session.clone());
+        spawn_local(async move {
+            let result = match client.await {
+                Ok(client) => session.logout_if_unauthorized(call(client).await),
+                Err(e) => Err(e),
+            };
+            then(result);
+        });
+    }
 
-        Ok(Some(data))
+    /// Id of the logged in user, provisional posts are written by them
+    fn author_id(&self) -> Result<i64, AppError> {
+        Ok(self.session.user_id().ok_or(BlogClientError::TokenNotSet)?)
     }
 
-    fn get_local_storage(&self) -> Result<Storage, AppError> {
-        let window = web_sys::window().ok_or(AppError::LocalStorageUnavailable)?;
-        let local_storage = window
-            .local_storage()?
-            .ok_or(AppError::LocalStorageUnavailable)?;
+    /// Next negative id of provisional posts
+    fn next_temp_id(&self) -> i64 {
+        let id = self.last_temp_id.get() - 1;
+        self.last_temp_id.set(id);
+        id
+    }
 
-        Ok(local_storage)
+    fn endpoint(&self, path: &str) -> Result<String, AppError> {
+        let url = self.api_url.join(path).map_err(BlogClientError::from)?;
+        Ok(url.to_string())
     }
 
-    fn token_opt(&self) -> Option<&str> {
-        match &self.auth_data {
-            Some(data) => Some(&data.token),
-            None => None,
+    /// Endpoint of registration or login which issues token for the auth mode of the app
+    fn auth_endpoint(&self, path: &str) -> Result<String, AppError> {
+        match self.auth_mode {
+            AuthMode::Cookie => self.endpoint(&format!("{path}?mode=cookie")),
+            AuthMode::Bearer => self.endpoint(path),
         }
     }
 
+    /// Auth data from response of registration or login
+    fn auth_data(&self, response: JsValue) -> Result<AuthData, AppError> {
+        let auth_data = match self.auth_mode {
+            AuthMode::Cookie => {
+                AuthData::from(serde_wasm_bindgen::from_value::<SessionResponse>(response)?)
+            }
+            AuthMode::Bearer => {
+                AuthData::from(serde_wasm_bindgen::from_value::<AuthResponse>(response)?)
+            }
+        };
+        Ok(auth_data)
+    }
+
+    /// Sends request with JSON body, error response is parsed into [`AppError::Server`]
+    ///
+    /// Cookies are sent and accepted in cookie auth mode only
     async fn request(
+        &self,
         method: Method,
         url: &str,
         body: Option<serde_json::Value>,
-        token: Option<&str>,
-    ) -> Result<JsValue, JsValue> {
+    ) -> Result<JsValue, AppError> {
         let opts = RequestInit::new();
         opts.set_method(method.as_str());
         opts.set_mode(RequestMode::Cors);
+        if self.auth_mode == AuthMode::Cookie {
+            opts.set_credentials(RequestCredentials::Include);
+        }
 
         let headers = web_sys::Headers::new()?;
         headers.append("Content-Type", "application/json")?;
 
-        if let Some(ref token) = token {
-            headers.append("Authorization", &format!("Bearer {}", token))?;
-        }
-
         opts.set_headers(&headers);
 
         if let Some(b) = body {
@@ -226,44 +545,85 @@ impl BlogApp {
 
         let request = Request::new_with_str_and_init(url, &opts)?;
         let window = web_sys::window().ok_or(JsValue::from_str("Window not available"))?;
-        let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
+        let resp_value = JsFuture::from(window.fetch_with_request(&request))
+            .await
+            .map_err(AppError::Offline)?;
         let resp: Response = resp_value.dyn_into()?;
 
         if !resp.ok() {
-            return Err(JsValue::from_str(&format!(
-                "HTTP error! status: {}",
-                resp.status()
-            )));
+            let status = resp.status();
+            let body = JsFuture::from(resp.json()?).await;
+            let response = body
+                .ok()
+                .and_then(|body| serde_wasm_bindgen::from_value::<ErrorResponse>(body).ok())
+                .unwrap_or_else(|| ErrorResponse {
+                    code: String::from("UNEXPECTED_RESPONSE"),
+                    error: format!("HTTP error, status: {status}"),
+                    status,
+                    fields: Vec::new(),
+                });
+            return Err(AppError::Server(response));
         }
 
         if resp.status() == 204 {
             Ok(JsValue::UNDEFINED)
         } else {
-            JsFuture::from(resp.json()?).await
+            Ok(JsFuture::from(resp.json()?).await?)
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct AuthData {
-    token: String,
-    user_id: i64,
+/// Runs library client call until it finishes, runs out of [`CALL_BUDGET`]
+/// or `signal` is aborted
+///
+/// Aborted call is dropped, which aborts its `fetch`
+pub(crate) async fn run_call<T>(
+    signal: Option<AbortSignal>,
+    request: impl Future<Output = Result<T, BlogClientError>>,
+) -> Result<T, AppError> {
+    let options = RequestOptions::new().timeout(CALL_BUDGET);
+    let Some(signal) = signal else {
+        return Ok(options.run(request).await?);
+    };
+    if signal.aborted() {
+        return Err(BlogClientError::Cancelled.into());
+    }
+
+    let token = CancellationToken::new();
+    let on_abort = Closure::<dyn FnMut()>::new({
+        let token = token.clone();
+        move || token.cancel()
+    });
+    signal.add_event_listener_with_callback("abort", on_abort.as_ref().unchecked_ref())?;
+
+    let result = options.cancellation(token).run(request).await;
+
+    signal.remove_event_listener_with_callback("abort", on_abort.as_ref().unchecked_ref())?;
+    Ok(result?)
 }
 
-impl From<RegisterResponse> for AuthData {
-    fn from(value: RegisterResponse) -> Self {
-        Self {
-            token: value.token,
-            user_id: value.user.id,
-        }
+/// Backoff of reads, updates and deletes failed on network errors or 5xx responses,
+/// slower than library default since browser may be offline for a while
+fn retry_policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 4,
+        initial_backoff: Duration::from_millis(500),
+        max_backoff: Duration::from_secs(5),
+        ..RetryPolicy::default()
     }
 }
 
-impl From<LoginResponse> for AuthData {
-    fn from(value: LoginResponse) -> Self {
-        Self {
-            token: value.token,
-            user_id: value.user.id,
-        }
-    }
+/// Joins server origin and API prefix the same way as HTTP client of blog-client library
+fn api_url(server_url: &str, api_prefix: &str) -> Result<Url, AppError> {
+    let prefix = api_prefix.trim_matches('/');
+    let path = if prefix.is_empty() {
+        String::from("/")
+    } else {
+        format!("/{prefix}/")
+    };
+
+    let url = Url::parse(server_url)
+        .and_then(|url| url.join(&path))
+        .map_err(BlogClientError::from)?;
+    Ok(url)
 }
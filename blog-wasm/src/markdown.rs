@@ -0,0 +1,17 @@
+//! Markdown rendering of post content
+
+use pulldown_cmark::{Options, Parser, html};
+use wasm_bindgen::prelude::*;
+
+/// Renders Markdown to HTML safe to insert into the page
+///
+/// Supports CommonMark with tables, strikethrough and task lists. Scripts,
+/// event handlers and other unsafe markup are removed from the result
+#[wasm_bindgen]
+pub fn render_markdown(content: &str) -> String {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new_ext(content, options));
+    ammonia::clean(&unsafe_html)
+}
@@ -0,0 +1,51 @@
+//! Results of optimistic changes, applied by frontend before the server confirms them
+
+use blog_client::blog_client::Post;
+use chrono::{DateTime, Utc};
+use js_sys::{Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{error::AppError, types::to_js};
+
+/// Reports result of the change to `on_settled` callback
+///
+/// `confirmed` settlement carries the post returned by server, if any,
+/// `rolled_back` one carries the error and tells frontend to revert the change
+pub(crate) fn settle(
+    on_settled: &js_sys::Function,
+    temp_id: i64,
+    result: Result<Option<Post>, AppError>,
+) {
+    if let Err(e) = settlement(temp_id, result)
+        .and_then(|settlement| on_settled.call1(&JsValue::NULL, &settlement))
+    {
+        web_sys::console::error_1(&e);
+    }
+}
+
+fn settlement(temp_id: i64, result: Result<Option<Post>, AppError>) -> Result<JsValue, JsValue> {
+    let settlement = Object::new();
+    Reflect::set(
+        &settlement,
+        &"temp_id".into(),
+        &JsValue::from_f64(temp_id as f64),
+    )?;
+    match result {
+        Ok(post) => {
+            Reflect::set(&settlement, &"status".into(), &"confirmed".into())?;
+            if let Some(post) = post {
+                Reflect::set(&settlement, &"post".into(), &to_js::<JsValue>(&post)?)?;
+            }
+        }
+        Err(e) => {
+            Reflect::set(&settlement, &"status".into(), &"rolled_back".into())?;
+            Reflect::set(&settlement, &"error".into(), &e.into())?;
+        }
+    }
+    Ok(settlement.into())
+}
+
+/// Current time of the browser, chrono can't read the clock on wasm32 without extra features
+pub(crate) fn now() -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(js_sys::Date::now() as i64).unwrap_or_default()
+}
@@ -0,0 +1,159 @@
+//! Pages of posts with navigation between them
+
+use std::{cell::RefCell, rc::Rc};
+
+use blog_client::blog_client::{BlogClient, PostsCollection};
+use wasm_bindgen::prelude::*;
+use web_sys::AbortSignal;
+
+use crate::{
+    cache::PostsCache,
+    error::AppError,
+    run_call,
+    types::{JsPosts, to_js},
+};
+
+/// Page of posts returned by `BlogApp::load_posts` and `BlogApp::search_posts`
+///
+/// Loads neighbouring pages of the same list, so frontend doesn't compute offsets
+#[wasm_bindgen]
+pub struct PostsPager {
+    page: PostsCollection,
+    source: Rc<PageSource>,
+}
+
+/// Where pages come from: all posts, cached, or search results
+pub(crate) struct PageSource {
+    client: BlogClient,
+    cache: Rc<RefCell<PostsCache>>,
+    query: Option<String>,
+}
+
+impl PageSource {
+    pub(crate) fn new(
+        client: BlogClient,
+        cache: Rc<RefCell<PostsCache>>,
+        query: Option<String>,
+    ) -> Rc<Self> {
+        Rc::new(Self {
+            client,
+            cache,
+            query,
+        })
+    }
+
+    /// Loads page, page of all posts loaded before is taken from cache unless `refresh` is `true`
+    pub(crate) async fn load(
+        self: Rc<Self>,
+        offset: u64,
+        limit: u64,
+        signal: Option<AbortSignal>,
+        refresh: bool,
+    ) -> Result<PostsPager, AppError> {
+        let page = match &self.query {
+            Some(query) => {
+                let request = self
+                    .client
+                    .search_posts(query.clone(), Some(limit), Some(offset));
+                run_call(signal, request).await?
+            }
+            None => {
+                let cached = self.cache.borrow().page(offset, limit);
+                match cached.filter(|_| !refresh) {
+                    Some(page) => page,
+                    None => {
+                        let request = self.client.get_posts(Some(limit), Some(offset));
+                        let page = run_call(signal, request).await?;
+                        self.cache.borrow_mut().insert_page(offset, limit, &page);
+                        page
+                    }
+                }
+            }
+        };
+
+        Ok(PostsPager { page, source: self })
+    }
+}
+
+#[wasm_bindgen]
+impl PostsPager {
+    /// Posts of this page
+    #[wasm_bindgen(getter)]
+    pub fn posts(&self) -> Result<JsPosts, JsValue> {
+        Ok(to_js(&self.page.posts)?)
+    }
+
+    /// Offset of the first post of this page
+    #[wasm_bindgen(getter)]
+    pub fn offset(&self) -> u64 {
+        self.page.offset
+    }
+
+    /// Number of posts per page
+    #[wasm_bindgen(getter)]
+    pub fn limit(&self) -> u64 {
+        self.page.limit
+    }
+
+    /// Total number of posts in the list
+    #[wasm_bindgen(getter)]
+    pub fn total_posts(&self) -> u64 {
+        self.page.total_posts
+    }
+
+    /// Number of this page starting from 1
+    pub fn page_number(&self) -> u64 {
+        self.page.offset / self.page_size() + 1
+    }
+
+    /// Number of pages, empty list is shown on a single page
+    pub fn total_pages(&self) -> u64 {
+        self.page.total_posts.div_ceil(self.page_size()).max(1)
+    }
+
+    /// Check if there are posts after this page
+    pub fn has_next(&self) -> bool {
+        self.page.offset + self.page_size() < self.page.total_posts
+    }
+
+    /// Check if there are posts before this page
+    pub fn has_prev(&self) -> bool {
+        self.page.offset > 0
+    }
+
+    /// Loads the next page, rejected with `ABORTED` code when `signal` is aborted
+    pub async fn load_next(&self, signal: Option<AbortSignal>) -> Result<PostsPager, JsValue> {
+        self.load(self.page.offset + self.page_size(), signal, false)
+            .await
+    }
+
+    /// Loads the previous page, rejected with `ABORTED` code when `signal` is aborted
+    pub async fn load_prev(&self, signal: Option<AbortSignal>) -> Result<PostsPager, JsValue> {
+        let offset = self.page.offset.saturating_sub(self.page_size());
+        self.load(offset, signal, false).await
+    }
+
+    /// Loads this page again bypassing cache
+    pub async fn reload(&self, signal: Option<AbortSignal>) -> Result<PostsPager, JsValue> {
+        self.load(self.page.offset, signal, true).await
+    }
+}
+
+impl PostsPager {
+    /// Step between pages, at least one post so zero limit doesn't break offset math
+    fn page_size(&self) -> u64 {
+        self.page.limit.max(1)
+    }
+
+    async fn load(
+        &self,
+        offset: u64,
+        signal: Option<AbortSignal>,
+        refresh: bool,
+    ) -> Result<PostsPager, JsValue> {
+        let source = self.source.clone();
+        Ok(source
+            .load(offset, self.page.limit, signal, refresh)
+            .await?)
+    }
+}
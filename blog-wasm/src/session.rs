@@ -0,0 +1,286 @@
+//! Auth data of the logged in user, kept in local storage and shared by browser tabs
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+};
+
+use blog_client::error::BlogClientError;
+use blog_types::{AuthResponse, SessionResponse};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{Storage, StorageEvent};
+
+use crate::{error::AppError, jwt::Claims};
+
+const AUTH_DATA_KEY: &str = "auth_data";
+/// Longest delay of `setTimeout`, later expiry is checked again after it
+const MAX_TIMER_DELAY_MS: f64 = i32::MAX as f64;
+
+/// Auth data of the logged in user
+///
+/// Callbacks registered by `on_change` are called with `true` after login and
+/// with `false` after logout, token expiry or rejection of the token by server.
+/// Login and logout in other tabs are received from `storage` events
+pub(crate) struct Session {
+    state: Rc<State>,
+    on_storage: Closure<dyn FnMut(StorageEvent)>,
+}
+
+struct State {
+    auth_data: RefCell<Option<AuthData>>,
+    callbacks: RefCell<Vec<js_sys::Function>>,
+    expiry_timer: Cell<Option<i32>>,
+    on_expiry: Closure<dyn FnMut()>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct AuthData {
+    /// Bearer token, or CSRF token of a cookie session
+    pub(crate) token: String,
+    pub(crate) user_id: i64,
+    /// Expiry of a cookie session in milliseconds since Unix epoch,
+    /// bearer token carries its expiry itself
+    #[serde(default)]
+    pub(crate) expires_at: Option<f64>,
+}
+
+impl Session {
+    /// Restores auth data saved in local storage, expired data is removed
+    pub(crate) fn restore() -> Result<Self, AppError> {
+        let state = Rc::new_cyclic(|state: &Weak<State>| State {
+            auth_data: RefCell::new(None),
+            callbacks: RefCell::new(Vec::new()),
+            expiry_timer: Cell::new(None),
+            on_expiry: Closure::new({
+                let state = state.clone();
+                move || {
+                    if let Some(state) = state.upgrade() {
+                        state.expiry_timer.set(None);
+                        state.check_expiry();
+                    }
+                }
+            }),
+        });
+
+        let on_storage = Closure::new({
+            let state = Rc::downgrade(&state);
+            move |event: StorageEvent| {
+                // key is null when the whole storage is cleared
+                let key = event.key();
+                if let Some(state) = state.upgrade()
+                    && key.as_deref().is_none_or(|key| key == AUTH_DATA_KEY)
+                {
+                    state.reload();
+                }
+            }
+        });
+        web_sys::window()
+            .ok_or(AppError::LocalStorageUnavailable)?
+            .add_event_listener_with_callback("storage", on_storage.as_ref().unchecked_ref())?;
+
+        let auth_data = load()?;
+        state.replace(auth_data);
+        if state.is_expired() {
+            delete()?;
+            state.replace(None);
+        }
+
+        Ok(Self { state, on_storage })
+    }
+
+    pub(crate) fn token(&self) -> Option<String> {
+        let auth_data = self.state.auth_data.borrow();
+        auth_data.as_ref().map(|auth_data| auth_data.token.clone())
+    }
+
+    pub(crate) fn user_id(&self) -> Option<i64> {
+        let auth_data = self.state.auth_data.borrow();
+        auth_data.as_ref().map(|auth_data| auth_data.user_id)
+    }
+
+    /// Expiration time of the token in milliseconds since Unix epoch
+    pub(crate) fn expires_at(&self) -> Option<f64> {
+        self.state.auth_data.borrow().as_ref()?.expires_at()
+    }
+
+    /// Check if user is logged in with a token which has not expired
+    pub(crate) fn is_authenticated(&self) -> bool {
+        self.state.is_authenticated()
+    }
+
+    pub(crate) fn login(&self, auth_data: AuthData) -> Result<(), AppError> {
+        save(&auth_data)?;
+        self.state.replace(Some(auth_data));
+        self.state.notify();
+        Ok(())
+    }
+
+    pub(crate) fn logout(&self) -> Result<(), AppError> {
+        delete()?;
+        self.state.replace(None);
+        self.state.notify();
+        Ok(())
+    }
+
+    /// Forgets token rejected by server, so UI shows logged out state
+    pub(crate) fn logout_if_unauthorized<T>(
+        &self,
+        result: Result<T, BlogClientError>,
+    ) -> Result<T, AppError> {
+        if let Err(BlogClientError::InvalidToken) = &result {
+            self.logout()?;
+        }
+        Ok(result?)
+    }
+
+    pub(crate) fn on_change(&self, callback: js_sys::Function) {
+        self.state.callbacks.borrow_mut().push(callback);
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if let Some(window) = web_sys::window() {
+            let _ = window.remove_event_listener_with_callback(
+                "storage",
+                self.on_storage.as_ref().unchecked_ref(),
+            );
+        }
+        self.state.replace(None);
+    }
+}
+
+impl State {
+    fn is_authenticated(&self) -> bool {
+        self.auth_data.borrow().is_some() && !self.is_expired()
+    }
+
+    fn is_expired(&self) -> bool {
+        self.auth_data
+            .borrow()
+            .as_ref()
+            .is_some_and(AuthData::is_expired)
+    }
+
+    /// Sets auth data and schedules its expiry, callbacks are not called
+    fn replace(&self, auth_data: Option<AuthData>) {
+        if let Some(timer) = self.expiry_timer.take()
+            && let Some(window) = web_sys::window()
+        {
+            window.clear_timeout_with_handle(timer);
+        }
+
+        let expires_at = auth_data.as_ref().and_then(AuthData::expires_at);
+        *self.auth_data.borrow_mut() = auth_data;
+
+        if let Some(expires_at) = expires_at
+            && let Some(window) = web_sys::window()
+        {
+            let delay = (expires_at - js_sys::Date::now()).clamp(0.0, MAX_TIMER_DELAY_MS);
+            match window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                self.on_expiry.as_ref().unchecked_ref(),
+                delay as i32,
+            ) {
+                Ok(timer) => self.expiry_timer.set(Some(timer)),
+                Err(e) => web_sys::console::error_1(&e),
+            }
+        }
+    }
+
+    /// Logs out when token has expired, otherwise waits for the expiry again
+    fn check_expiry(&self) {
+        if !self.is_expired() {
+            let auth_data = self.auth_data.borrow_mut().take();
+            self.replace(auth_data);
+            return;
+        }
+
+        if let Err(e) = delete() {
+            web_sys::console::error_1(&e.into());
+        }
+        self.replace(None);
+        self.notify();
+    }
+
+    /// Takes auth data changed by another tab
+    fn reload(&self) {
+        let auth_data = load().unwrap_or_else(|e| {
+            web_sys::console::error_1(&e.into());
+            None
+        });
+        self.replace(auth_data);
+        self.notify();
+    }
+
+    fn notify(&self) {
+        let authenticated = JsValue::from_bool(self.is_authenticated());
+        // callback may register another callback
+        let callbacks = self.callbacks.borrow().clone();
+        for callback in callbacks {
+            if let Err(e) = callback.call1(&JsValue::NULL, &authenticated) {
+                web_sys::console::error_1(&e);
+            }
+        }
+    }
+}
+
+impl AuthData {
+    fn expires_at(&self) -> Option<f64> {
+        self.expires_at
+            .or_else(|| Claims::decode(&self.token).map(|claims| claims.expires_at_millis()))
+    }
+
+    /// Token which can't be decoded is left for the server to judge
+    fn is_expired(&self) -> bool {
+        self.expires_at()
+            .is_some_and(|expires_at| expires_at <= js_sys::Date::now())
+    }
+}
+
+impl From<AuthResponse> for AuthData {
+    fn from(value: AuthResponse) -> Self {
+        Self {
+            token: value.token,
+            user_id: value.user.id,
+            expires_at: None,
+        }
+    }
+}
+
+impl From<SessionResponse> for AuthData {
+    fn from(value: SessionResponse) -> Self {
+        Self {
+            token: value.csrf_token,
+            user_id: value.user.id,
+            expires_at: Some(value.expires_at as f64 * 1000.0),
+        }
+    }
+}
+
+fn save(auth_data: &AuthData) -> Result<(), AppError> {
+    let json = serde_json::to_string(auth_data)?;
+    local_storage()?.set_item(AUTH_DATA_KEY, &json)?;
+    Ok(())
+}
+
+fn delete() -> Result<(), AppError> {
+    local_storage()?.remove_item(AUTH_DATA_KEY)?;
+    Ok(())
+}
+
+fn load() -> Result<Option<AuthData>, AppError> {
+    let Some(json) = local_storage()?.get_item(AUTH_DATA_KEY)? else {
+        return Ok(None);
+    };
+    Ok(Some(serde_json::from_str(&json)?))
+}
+
+pub(crate) fn local_storage() -> Result<Storage, AppError> {
+    let window = web_sys::window().ok_or(AppError::LocalStorageUnavailable)?;
+    let local_storage = window
+        .local_storage()?
+        .ok_or(AppError::LocalStorageUnavailable)?;
+
+    Ok(local_storage)
+}
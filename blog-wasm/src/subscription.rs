@@ -0,0 +1,168 @@
+//! Live post updates received from the server's event stream
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+};
+
+use blog_client::blog_client::PostEvent;
+use wasm_bindgen::prelude::*;
+use web_sys::{EventSource, MessageEvent};
+
+use crate::{cache::PostsCache, error::AppError, types::to_js};
+
+/// Name of server-sent events with post changes
+const POST_EVENT: &str = "post";
+/// Delay before reopening the stream closed by the browser
+const RECONNECT_DELAY_MS: i32 = 3000;
+
+/// Subscription to post changes returned by `BlogApp::subscribe_posts`
+///
+/// Browser reconnects after network errors by itself, the stream it gave up on
+/// is reopened after a delay. Events are no longer delivered after `close()`
+/// or when the subscription is freed
+#[wasm_bindgen]
+pub struct PostsSubscription {
+    connection: Rc<Connection>,
+}
+
+struct Connection {
+    url: String,
+    callback: js_sys::Function,
+    cache: Rc<RefCell<PostsCache>>,
+    source: RefCell<Option<EventSource>>,
+    reconnect_timer: Cell<Option<i32>>,
+    on_post: Closure<dyn FnMut(MessageEvent)>,
+    on_error: Closure<dyn FnMut()>,
+    on_reconnect: Closure<dyn FnMut()>,
+}
+
+impl PostsSubscription {
+    /// Opens event stream at `url`, calling `callback` with every post change
+    /// after it is applied to `cache`
+    pub(crate) fn open(
+        url: String,
+        callback: js_sys::Function,
+        cache: Rc<RefCell<PostsCache>>,
+    ) -> Result<Self, AppError> {
+        let connection = Rc::new_cyclic(|connection: &Weak<Connection>| Connection {
+            url,
+            callback,
+            cache,
+            source: RefCell::new(None),
+            reconnect_timer: Cell::new(None),
+            on_post: Closure::new({
+                let connection = connection.clone();
+                move |event: MessageEvent| {
+                    if let Some(connection) = connection.upgrade() {
+                        connection.deliver(&event);
+                    }
+                }
+            }),
+            on_error: Closure::new({
+                let connection = connection.clone();
+                move || {
+                    if let Some(connection) = connection.upgrade() {
+                        connection.schedule_reconnect();
+                    }
+                }
+            }),
+            on_reconnect: Closure::new({
+                let connection = connection.clone();
+                move || {
+                    if let Some(connection) = connection.upgrade() {
+                        connection.reconnect_timer.set(None);
+                        if let Err(e) = connection.connect() {
+                            web_sys::console::error_1(&e.into());
+                            connection.schedule_reconnect();
+                        }
+                    }
+                }
+            }),
+        });
+
+        connection.connect()?;
+        Ok(Self { connection })
+    }
+}
+
+#[wasm_bindgen]
+impl PostsSubscription {
+    /// Closes event stream, callback is not called anymore
+    pub fn close(&self) {
+        self.connection.close();
+    }
+}
+
+impl Drop for PostsSubscription {
+    fn drop(&mut self) {
+        self.connection.close();
+    }
+}
+
+impl Connection {
+    fn connect(&self) -> Result<(), AppError> {
+        let source = EventSource::new(&self.url)?;
+        source
+            .add_event_listener_with_callback(POST_EVENT, self.on_post.as_ref().unchecked_ref())?;
+        source.set_onerror(Some(self.on_error.as_ref().unchecked_ref()));
+
+        if let Some(previous) = self.source.replace(Some(source)) {
+            previous.close();
+        }
+        Ok(())
+    }
+
+    /// Reopens the stream later if browser stopped reconnecting
+    ///
+    /// Stream closed by the subscriber has no source and is never reopened
+    fn schedule_reconnect(&self) {
+        let closed = self
+            .source
+            .borrow()
+            .as_ref()
+            .is_some_and(|source| source.ready_state() == EventSource::CLOSED);
+        if !closed || self.reconnect_timer.get().is_some() {
+            return;
+        }
+
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        match window.set_timeout_with_callback_and_timeout_and_arguments_0(
+            self.on_reconnect.as_ref().unchecked_ref(),
+            RECONNECT_DELAY_MS,
+        ) {
+            Ok(timer) => self.reconnect_timer.set(Some(timer)),
+            Err(e) => web_sys::console::error_1(&e),
+        }
+    }
+
+    /// Passes typed event to the callback, malformed events are logged and skipped
+    fn deliver(&self, event: &MessageEvent) {
+        let result = event
+            .data()
+            .as_string()
+            .ok_or(AppError::InvalidEvent)
+            .and_then(|data| Ok(serde_json::from_str::<PostEvent>(&data)?))
+            .inspect(|event| self.cache.borrow_mut().apply(event))
+            .and_then(|event| to_js::<JsValue>(&event))
+            .map_err(JsValue::from)
+            .and_then(|event| self.callback.call1(&JsValue::NULL, &event));
+
+        if let Err(e) = result {
+            web_sys::console::error_1(&e);
+        }
+    }
+
+    fn close(&self) {
+        if let Some(timer) = self.reconnect_timer.take()
+            && let Some(window) = web_sys::window()
+        {
+            window.clear_timeout_with_handle(timer);
+        }
+        if let Some(source) = self.source.take() {
+            source.close();
+        }
+    }
+}
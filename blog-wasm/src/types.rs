@@ -0,0 +1,87 @@
+//! TypeScript definitions of values returned to JavaScript
+
+use serde::Serialize;
+use wasm_bindgen::{JsCast, prelude::*};
+
+use crate::error::AppError;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &str = r#"
+/** Blog post, dates are ISO 8601 strings */
+export interface Post {
+    id: number;
+    title: string;
+    content: string;
+    author_id: number;
+    author_username?: string;
+    created_at: string;
+    updated_at: string;
+}
+
+/** Logged in user */
+export interface CurrentUser {
+    id: number;
+    username: string;
+    /** Number of posts written by the user */
+    posts: number;
+}
+
+/** Work in progress of the post editor saved by `BlogApp.autosave_draft` */
+export interface Draft {
+    /** Edited post, absent for a new post */
+    post_id?: number;
+    title: string;
+    content: string;
+    /** Time of the last change in milliseconds since Unix epoch */
+    saved_at: number;
+}
+
+/** Change of a post received by `BlogApp.subscribe_posts` */
+export type PostEvent =
+    | (Post & { kind: "created" | "updated" })
+    | { kind: "deleted"; id: number };
+
+/** Server result of an optimistic change, passed to `on_settled` callback */
+export type Settlement =
+    | { temp_id: number; status: "confirmed"; post?: Post }
+    | { temp_id: number; status: "rolled_back"; error: BlogError };
+
+/** Invalid request field */
+export interface FieldError {
+    field: string;
+    message: string;
+}
+
+/** Error rejecting promises returned by `BlogApp` methods */
+export interface BlogError extends Error {
+    /** Server error code, e.g. `VALIDATION_FAILED`, or client side code, e.g. `OFFLINE` */
+    code: string;
+    /** HTTP status, `null` if no response was received */
+    status: number | null;
+    field_errors: FieldError[];
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    /// Post serialized to JS object
+    #[wasm_bindgen(typescript_type = "Post")]
+    pub type JsPost;
+
+    /// Current user serialized to JS object
+    #[wasm_bindgen(typescript_type = "CurrentUser")]
+    pub type JsCurrentUser;
+
+    /// Draft serialized to JS object
+    #[wasm_bindgen(typescript_type = "Draft")]
+    pub type JsDraft;
+
+    /// Posts serialized to JS array
+    #[wasm_bindgen(typescript_type = "Post[]")]
+    pub type JsPosts;
+}
+
+/// Serializes value to JS object of the declared TypeScript type
+pub(crate) fn to_js<T: JsCast>(value: &impl Serialize) -> Result<T, AppError> {
+    Ok(serde_wasm_bindgen::to_value(value)?.unchecked_into())
+}
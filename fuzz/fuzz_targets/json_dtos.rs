@@ -0,0 +1,31 @@
+//! JSON bodies accepted by the server and responses parsed by clients
+#![no_main]
+
+use blog_server::domain::post::{CreatePostParams, PatchPostParams, UpdatePostParams};
+use blog_types::{
+    AuthQuery, AuthResponse, CreatePostRequest, ErrorResponse, LoginRequest, RegisterRequest,
+    SessionResponse, UpdatePostRequest,
+};
+use libfuzzer_sys::fuzz_target;
+use serde::de::DeserializeOwned;
+
+fn parse<T: DeserializeOwned>(data: &[u8]) {
+    let _ = serde_json::from_slice::<T>(data);
+}
+
+fuzz_target!(|data: &[u8]| {
+    // request bodies of HTTP handlers
+    parse::<RegisterRequest>(data);
+    parse::<LoginRequest>(data);
+    parse::<CreatePostParams>(data);
+    parse::<UpdatePostParams>(data);
+    parse::<PatchPostParams>(data);
+
+    // shared DTOs, also parsed by clients from server responses
+    parse::<AuthQuery>(data);
+    parse::<AuthResponse>(data);
+    parse::<SessionResponse>(data);
+    parse::<ErrorResponse>(data);
+    parse::<CreatePostRequest>(data);
+    parse::<UpdatePostRequest>(data);
+});
@@ -0,0 +1,29 @@
+//! Tokens from `Authorization` header and session cookie, and the key list from environment
+#![no_main]
+
+use std::sync::LazyLock;
+
+use blog_server::infrastructure::jwt::{JwtKey, JwtService};
+use libfuzzer_sys::fuzz_target;
+
+static JWT_SERVICE: LazyLock<JwtService> = LazyLock::new(|| {
+    let primary = JwtKey {
+        id: String::from("primary"),
+        secret: String::from("fuzz-secret"),
+    };
+    let secondary = JwtKey {
+        id: String::from("secondary"),
+        secret: String::from("old-fuzz-secret"),
+    };
+    JwtService::new(primary, vec![secondary]).unwrap()
+});
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    // a forged token must never verify
+    assert!(JWT_SERVICE.verify_token(input).is_err());
+    let _ = JwtKey::parse_list(input);
+});
@@ -0,0 +1,13 @@
+//! Markdown rendering and sanitization of post content
+#![no_main]
+
+use blog_wasm::render_markdown;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|content: &str| {
+    let html = render_markdown(content).to_ascii_lowercase();
+
+    // markup from the content is escaped or removed, never passed through
+    assert!(!html.contains("<script"), "script tag in {html}");
+    assert!(!html.contains("<iframe"), "iframe in {html}");
+});